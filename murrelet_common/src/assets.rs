@@ -75,6 +75,10 @@ impl VectorLayersAssetLookup {
     pub fn layer_for_key(&self, key: &str) -> &[String] {
         &self.filename_to_polyline_layers[key].layers
     }
+
+    pub fn insert(&mut self, filename: String, asset: VectorAsset) {
+        self.filename_to_polyline_layers.insert(filename, asset);
+    }
 }
 
 pub trait IsColorType {}
@@ -161,6 +165,15 @@ impl Assets {
     pub fn layer_for_key(&self, key: &str) -> &[String] {
         self.vectors.layer_for_key(key)
     }
+
+    // for reloading one file, replaces the old version
+    pub fn insert_vector(&mut self, filename: String, asset: VectorAsset) {
+        self.vectors.insert(filename, asset);
+    }
+
+    pub fn insert_raster(&mut self, filename: String, asset: RasterAsset) {
+        self.rasters.insert(filename, asset);
+    }
 }
 
 pub type AssetsRef = Arc<Assets>;
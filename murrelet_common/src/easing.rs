@@ -0,0 +1,161 @@
+//! named easing curves (quad, cubic, ..., elastic) and css-style cubic bezier
+//! easing. expressions use these too, as `cubic_in_out(t)` and
+//! `bezier_ease(x1, y1, x2, y2, t)`.
+
+use std::f64::consts::PI;
+
+use crate::clamp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EaseFamily {
+    Quad,
+    Cubic,
+    Quart,
+    Expo,
+    Sine,
+    Circ,
+    Back,
+    Elastic,
+}
+impl EaseFamily {
+    const ALL: [EaseFamily; 8] = [
+        EaseFamily::Quad,
+        EaseFamily::Cubic,
+        EaseFamily::Quart,
+        EaseFamily::Expo,
+        EaseFamily::Sine,
+        EaseFamily::Circ,
+        EaseFamily::Back,
+        EaseFamily::Elastic,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            EaseFamily::Quad => "quad",
+            EaseFamily::Cubic => "cubic",
+            EaseFamily::Quart => "quart",
+            EaseFamily::Expo => "expo",
+            EaseFamily::Sine => "sine",
+            EaseFamily::Circ => "circ",
+            EaseFamily::Back => "back",
+            EaseFamily::Elastic => "elastic",
+        }
+    }
+
+    // the "in" version, the others are built from this
+    fn ease_in(&self, t: f64) -> f64 {
+        match self {
+            EaseFamily::Quad => t * t,
+            EaseFamily::Cubic => t * t * t,
+            EaseFamily::Quart => t * t * t * t,
+            EaseFamily::Expo => {
+                if t <= 0.0 {
+                    0.0
+                } else {
+                    2.0f64.powf(10.0 * t - 10.0)
+                }
+            }
+            EaseFamily::Sine => 1.0 - (t * PI / 2.0).cos(),
+            EaseFamily::Circ => 1.0 - (1.0 - t * t).sqrt(),
+            EaseFamily::Back => {
+                let c1 = 1.70158;
+                (c1 + 1.0) * t * t * t - c1 * t * t
+            }
+            EaseFamily::Elastic => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else {
+                    -(2.0f64.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * 2.0 * PI / 3.0).sin()
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EaseDir {
+    In,
+    Out,
+    InOut,
+}
+impl EaseDir {
+    const ALL: [EaseDir; 3] = [EaseDir::In, EaseDir::Out, EaseDir::InOut];
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            EaseDir::In => "in",
+            EaseDir::Out => "out",
+            EaseDir::InOut => "in_out",
+        }
+    }
+}
+
+// e.g. Easing::new(EaseFamily::Cubic, EaseDir::InOut), or "cubic_in_out"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Easing {
+    family: EaseFamily,
+    dir: EaseDir,
+}
+impl Easing {
+    pub fn new(family: EaseFamily, dir: EaseDir) -> Self {
+        Self { family, dir }
+    }
+
+    pub fn all() -> Vec<Easing> {
+        EaseFamily::ALL
+            .iter()
+            .flat_map(|family| EaseDir::ALL.iter().map(|dir| Easing::new(*family, *dir)))
+            .collect()
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}_{}", self.family.name(), self.dir.suffix())
+    }
+
+    pub fn from_name(name: &str) -> Option<Easing> {
+        Self::all().into_iter().find(|e| e.name() == name)
+    }
+
+    // t is clamped to 0 to 1. it starts at 0 and ends at 1, but back and
+    // elastic go past those in between
+    pub fn ease(&self, t: f64) -> f64 {
+        let t = clamp(t, 0.0, 1.0);
+        let f = |t| self.family.ease_in(t);
+        match self.dir {
+            EaseDir::In => f(t),
+            EaseDir::Out => 1.0 - f(1.0 - t),
+            EaseDir::InOut => {
+                if t < 0.5 {
+                    f(2.0 * t) / 2.0
+                } else {
+                    1.0 - f(2.0 - 2.0 * t) / 2.0
+                }
+            }
+        }
+    }
+}
+
+// like css's cubic-bezier(x1, y1, x2, y2), the curve goes from (0, 0) to
+// (1, 1) and this finds the y where it's at x = t
+pub fn bezier_ease(x1: f64, y1: f64, x2: f64, y2: f64, t: f64) -> f64 {
+    let t = clamp(t, 0.0, 1.0);
+    // x has to only go forward, so keep these in 0 to 1
+    let (x1, x2) = (clamp(x1, 0.0, 1.0), clamp(x2, 0.0, 1.0));
+
+    let bezier = |a: f64, b: f64, s: f64| {
+        let inv = 1.0 - s;
+        3.0 * inv * inv * s * a + 3.0 * inv * s * s * b + s * s * s
+    };
+
+    // x(s) is increasing, so bisect for the s that gives t
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..40 {
+        let mid = 0.5 * (lo + hi);
+        if bezier(x1, x2, mid) < t {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    bezier(y1, y2, 0.5 * (lo + hi))
+}
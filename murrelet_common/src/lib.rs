@@ -13,16 +13,20 @@ pub mod intersection;
 
 mod assets;
 mod color;
+mod easing;
 mod geometry;
 mod idx;
 mod iter;
 mod metric;
+mod noise;
 mod polyline;
 mod transform;
 pub mod triangulate;
 
+pub use self::noise::*;
 pub use assets::*;
 pub use color::*;
+pub use easing::*;
 pub use geometry::*;
 pub use idx::*;
 pub use iter::*;
@@ -483,7 +487,7 @@ impl Circle {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum LivecodeValue {
     Float(f64),
     Bool(bool),
@@ -534,7 +538,7 @@ pub struct LivecodeSrc {
     vs: Vec<Box<dyn IsLivecodeSrc>>,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct CustomVars(Option<HashMap<String, f32>>);
 
 impl CustomVars {
@@ -565,7 +569,7 @@ impl CustomVars {
 }
 
 // what is sent from apps (like nannou)
-#[derive(Default)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct MurreletAppInput {
     pub keys: Option<[bool; 26]>,
     pub window_dims: Vec2,
@@ -0,0 +1,177 @@
+//! seeded noise: simplex, fbm, ridged, worley and curl. the same seed always
+//! gives the same noise, so pass in the app seed to get reproducible renders.
+
+use ::noise::{NoiseFn, Simplex};
+use glam::{Vec2, Vec3, vec2};
+
+#[derive(Debug, Clone, Copy)]
+pub struct MurreletNoise {
+    seed: u32,
+    simplex: Simplex,
+}
+impl MurreletNoise {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            simplex: Simplex::new(seed),
+        }
+    }
+
+    // the app seed is a float, so use all of its bits. casting would make
+    // 0.2 and 0.7 the same, and every negative seed 0
+    pub fn from_seed_f32(seed: f32) -> Self {
+        // -0.0 has different bits, but it's the same seed
+        let seed = if seed == 0.0 { 0 } else { seed.to_bits() };
+        Self::new(seed)
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    // -1 to 1
+    pub fn simplex2(&self, p: Vec2) -> f32 {
+        self.simplex.get([p.x as f64, p.y as f64]) as f32
+    }
+
+    pub fn simplex3(&self, p: Vec3) -> f32 {
+        self.simplex.get([p.x as f64, p.y as f64, p.z as f64]) as f32
+    }
+
+    // usually time is the last one
+    pub fn simplex4(&self, p: Vec3, w: f32) -> f32 {
+        self.simplex
+            .get([p.x as f64, p.y as f64, p.z as f64, w as f64]) as f32
+    }
+
+    // layers of simplex, each `lacunarity` times the frequency and `gain` times
+    // the amplitude of the last. -1 to 1
+    pub fn fbm(&self, p: Vec3, octaves: usize, lacunarity: f32, gain: f32) -> f32 {
+        self.octaves(p, octaves, lacunarity, gain, |n| n)
+    }
+
+    // like fbm, but with sharp ridges where the noise crosses 0. 0 to 1
+    pub fn ridged(&self, p: Vec3, octaves: usize, lacunarity: f32, gain: f32) -> f32 {
+        self.octaves(p, octaves, lacunarity, gain, |n| (1.0 - n.abs()).powi(2))
+    }
+
+    fn octaves<F>(&self, p: Vec3, octaves: usize, lacunarity: f32, gain: f32, f: F) -> f32
+    where
+        F: Fn(f32) -> f32,
+    {
+        let mut freq = 1.0;
+        let mut amp = 1.0;
+        let mut total = 0.0;
+        let mut total_amp = 0.0;
+        for i in 0..octaves.max(1) {
+            // shift each octave so they don't all line up at the origin
+            let offset = Vec3::splat(i as f32 * 17.31);
+            total += amp * f(self.simplex3(p * freq + offset));
+            total_amp += amp;
+            freq *= lacunarity;
+            amp *= gain;
+        }
+        total / total_amp
+    }
+
+    // distance to the closest of some randomly placed points, one per cell.
+    // about 0 to 1
+    pub fn worley2(&self, p: Vec2) -> f32 {
+        let cell = p.floor();
+        let mut closest = f32::MAX;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let c = cell + vec2(dx as f32, dy as f32);
+                let (x, y) = (c.x as i32, c.y as i32);
+                let pt = c + vec2(self.hash01(x, y, 0, 0), self.hash01(x, y, 0, 1));
+                closest = closest.min(pt.distance(p));
+            }
+        }
+        closest
+    }
+
+    pub fn worley3(&self, p: Vec3) -> f32 {
+        let cell = p.floor();
+        let mut closest = f32::MAX;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let c = cell + Vec3::new(dx as f32, dy as f32, dz as f32);
+                    let (x, y, z) = (c.x as i32, c.y as i32, c.z as i32);
+                    let pt = c + Vec3::new(
+                        self.hash01(x, y, z, 0),
+                        self.hash01(x, y, z, 1),
+                        self.hash01(x, y, z, 2),
+                    );
+                    closest = closest.min(pt.distance(p));
+                }
+            }
+        }
+        closest
+    }
+
+    // a swirly flow field with no sources or sinks, good for moving particles.
+    // it's the curl of simplex3(x, y, t)
+    pub fn curl2(&self, p: Vec2, t: f32) -> Vec2 {
+        let eps = 1e-3;
+        let n = |x: f32, y: f32| self.simplex3(Vec3::new(x, y, t));
+        let dx = (n(p.x + eps, p.y) - n(p.x - eps, p.y)) / (2.0 * eps);
+        let dy = (n(p.x, p.y + eps) - n(p.x, p.y - eps)) / (2.0 * eps);
+        vec2(dy, -dx)
+    }
+
+    // 0 to 1, the same for the same cell and seed
+    fn hash01(&self, x: i32, y: i32, z: i32, k: u32) -> f32 {
+        let mut h = self.seed.wrapping_mul(0x9E3779B9);
+        for v in [x as u32, y as u32, z as u32, k] {
+            h ^= v.wrapping_mul(0x85EBCA6B);
+            h = h.rotate_left(13).wrapping_mul(0xC2B2AE35);
+        }
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7FEB352D);
+        h ^= h >> 15;
+        (h >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_seeds_stay_different() {
+        let seeds =
+            [0.0, 0.2, 0.7, 1.0, 2.0, -1.0, -2.5].map(|s| MurreletNoise::from_seed_f32(s).seed());
+        for (i, a) in seeds.iter().enumerate() {
+            for b in &seeds[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert_eq!(MurreletNoise::from_seed_f32(-0.0).seed(), 0);
+
+        let p = Vec3::new(0.3, 1.7, 2.2);
+        let a = MurreletNoise::from_seed_f32(0.2);
+        let b = MurreletNoise::from_seed_f32(0.7);
+        assert_ne!(a.simplex3(p), b.simplex3(p));
+        assert_eq!(a.simplex3(p), MurreletNoise::from_seed_f32(0.2).simplex3(p));
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        let n = MurreletNoise::from_seed_f32(3.5);
+        for i in 0..200 {
+            let p = Vec3::new(i as f32 * 0.37, i as f32 * -0.11, i as f32 * 0.05);
+            let fbm = n.fbm(p, 4, 2.0, 0.5);
+            assert!((-1.0..=1.0).contains(&fbm), "fbm {}", fbm);
+            let ridged = n.ridged(p, 4, 2.0, 0.5);
+            assert!((0.0..=1.0).contains(&ridged), "ridged {}", ridged);
+            // the closest point is at most a cell's diagonal away
+            let worley = n.worley2(p.truncate());
+            assert!(
+                (0.0..=2.0_f32.sqrt()).contains(&worley),
+                "worley {}",
+                worley
+            );
+        }
+    }
+}
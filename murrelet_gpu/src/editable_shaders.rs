@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fs, path::PathBuf};
 
 use crate::{
     build_shader_custom_vertex, gpu_macros::ShaderStr, graphics_ref::GraphicsVertex,
@@ -24,6 +24,10 @@ pub struct ShaderStrings {
     #[livecode(kind = "none")]
     #[lerpable(method = "skip")]
     shaders: HashMap<String, String>,
+    // name to a .wgsl file, for shaders that live in their own file
+    #[livecode(kind = "none", serde_default = "default")]
+    #[lerpable(method = "skip")]
+    files: HashMap<String, String>,
 }
 impl ShaderStrings {
     fn shader_str<VertexKind: GraphicsVertex>(shader: &str) -> String {
@@ -100,39 +104,65 @@ impl ShaderStrings {
     }
 
     pub fn has_changed(&self, other: &ControlShaderStrings) -> bool {
-        self.shaders != other.shaders
+        self.files != other.files
+            || other
+                .to_normal()
+                .is_none_or(|other| self.shaders != other.shaders)
     }
 
     pub fn naga_if_needed<VertexKind: GraphicsVertex>(
         &self,
         prev_shaders: &ControlShaderStrings,
     ) -> bool {
-        if self.has_changed(prev_shaders) {
-            let mut all_success = true;
+        self.has_changed(prev_shaders) && self.compiles::<VertexKind>()
+    }
+
+    fn compiles<VertexKind: GraphicsVertex>(&self) -> bool {
+        let mut all_success = true;
+
+        for (name, shader_str) in self.shaders.iter() {
+            let t = ShaderStrings::shader_str::<VertexKind>(shader_str);
+            if let Err(err) = naga::front::wgsl::parse_str(&t) {
+                println!(
+                    "error with shader {:?}, {:?}, not updating until it works!",
+                    name, err
+                );
+                all_success = false;
+            }
+        }
 
-            for (name, shader_str) in self.shaders.iter() {
-                let t = ShaderStrings::shader_str::<VertexKind>(shader_str);
-                if let Err(err) = naga::front::wgsl::parse_str(&t) {
+        all_success
+    }
+}
+
+impl ControlShaderStrings {
+    // reads in the shader files, none if one of them can't be read
+    fn to_normal(&self) -> Option<ShaderStrings> {
+        let mut shaders = self.shaders.clone();
+        let mut all_success = true;
+        for (name, path) in &self.files {
+            match fs::read_to_string(path) {
+                Ok(shader) => {
+                    shaders.insert(name.clone(), shader);
+                }
+                Err(err) => {
                     println!(
-                        "error with shader {:?}, {:?}, not updating until it works!",
-                        name, err
+                        "error reading shader {:?} from {:?}, {:?}, not updating until it works!",
+                        name, path, err
                     );
                     all_success = false;
                 }
             }
-
-            all_success
-        } else {
-            false
         }
+        all_success.then(|| ShaderStrings {
+            shaders,
+            files: self.files.clone(),
+        })
     }
-}
 
-impl ControlShaderStrings {
-    fn to_normal(&self) -> ShaderStrings {
-        ShaderStrings {
-            shaders: self.shaders.clone(),
-        }
+    // the files to watch, see LiveCoder::watch_shader_files
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        self.files.values().map(PathBuf::from).collect()
     }
 
     pub fn should_update<VertexKind: GraphicsVertex>(
@@ -140,11 +170,26 @@ impl ControlShaderStrings {
         prev: &ControlShaderStrings,
         force_reload: bool,
     ) -> Option<ShaderStrings> {
-        let shaders = self.to_normal();
+        self.should_update_with_files::<VertexKind>(prev, false, force_reload)
+    }
 
-        let shader_changed_and_compiles = shaders.naga_if_needed::<VertexKind>(prev);
+    // same, but `files_changed` is if one of the shader files changed since
+    // the last time, like from LiveCoder::take_shader_changes
+    pub fn should_update_with_files<VertexKind: GraphicsVertex>(
+        &self,
+        prev: &ControlShaderStrings,
+        files_changed: bool,
+        force_reload: bool,
+    ) -> Option<ShaderStrings> {
+        let changed = files_changed || self.shaders != prev.shaders || self.files != prev.files;
+        if !changed && !force_reload {
+            return None;
+        }
 
-        if force_reload || shader_changed_and_compiles {
+        // a file that can't be read is like one that doesn't compile, but
+        // it's missing, so even force_reload keeps the old ones
+        let shaders = self.to_normal()?;
+        if force_reload || shaders.compiles::<VertexKind>() {
             // just in case there's lerp, be sure to use the one we tested
             Some(shaders)
         } else {
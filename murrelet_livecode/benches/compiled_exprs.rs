@@ -0,0 +1,101 @@
+// compares evalexpr's tree walk against CompiledExpr for a bunch of unit
+// cells, which is where it gets slow. run with
+//
+//   cargo bench -p murrelet_livecode --bench compiled_exprs
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use murrelet_common::IdxInRange;
+use murrelet_livecode::{
+    expr::ExprWorldContextValues,
+    lazy::{ControlLazyNodeF32, LazyNodeF32},
+    livecode::{ControlF32, LivecodeFromWorld},
+    state::{LivecodeWorldState, LivecodeWorldStateInner},
+};
+
+const CELLS: usize = 2000;
+const FRAMES: usize = 10;
+
+const EXPRS: &[&str] = &[
+    "s(ease(t, 0.25), 1.0, 20.0)",
+    "clamp(sin(t + i_pct, 0.5) * 2.0, 0.0, 1.0) * i_totalf",
+    "if(i_i % 2 == 0, smooth(i_pct), 1.0 - tri(i_pct)) + rn(42, i_i) * 0.1",
+    "remap(i_pct, 0.0, 1.0, -PI, PI) * (1.0 + 0.5 * bounce(t, 0.125))",
+];
+
+fn world(compiled: bool) -> LivecodeWorldState {
+    LivecodeWorldState::new_legacy(LivecodeWorldStateInner::new_dummy_with_funcs())
+        .unwrap()
+        .with_compiled_exprs(compiled)
+}
+
+fn cell_vals(i: usize) -> ExprWorldContextValues {
+    ExprWorldContextValues::new_from_idx(IdxInRange::new(i, CELLS))
+}
+
+// like unitcells, each cell gets its own world and evaluates every field
+fn control_frame(w: &LivecodeWorldState, controls: &[ControlF32]) -> f32 {
+    let mut total = 0.0;
+    for i in 0..CELLS {
+        let cell = w.clone_with_vals(cell_vals(i), "i_");
+        for c in controls {
+            total += c.o(&cell).unwrap();
+        }
+    }
+    total
+}
+
+// lazy nodes get evaluated by the sketch for each index
+fn lazy_frame(w: &LivecodeWorldState, controls: &[ControlLazyNodeF32]) -> f32 {
+    let lazies: Vec<LazyNodeF32> = controls.iter().map(|c| c.o(w).unwrap()).collect();
+    let mut total = 0.0;
+    for i in 0..CELLS {
+        for lazy in &lazies {
+            total += lazy.eval_idx(IdxInRange::new(i, CELLS), "i").unwrap();
+        }
+    }
+    total
+}
+
+fn time_frames<F: Fn() -> f32>(f: F) -> (Duration, f32) {
+    let result = f(); // warm up
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        black_box(f());
+    }
+    (start.elapsed() / FRAMES as u32, result)
+}
+
+fn report(name: &str, tree: Duration, compiled: Duration) {
+    println!(
+        "{:<10} tree walk {:>8.2?}/frame   compiled {:>8.2?}/frame   {:.1}x",
+        name,
+        tree,
+        compiled,
+        tree.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
+
+fn main() {
+    println!("{} cells, {} expressions", CELLS, EXPRS.len());
+
+    let controls = EXPRS
+        .iter()
+        .map(|s| ControlF32::force_from_str(s))
+        .collect::<Vec<_>>();
+    let (tree, tree_result) = time_frames(|| control_frame(&world(false), &controls));
+    let (compiled, compiled_result) = time_frames(|| control_frame(&world(true), &controls));
+    assert_eq!(tree_result, compiled_result);
+    report("control", tree, compiled);
+
+    let lazy_controls = EXPRS
+        .iter()
+        .map(|s| ControlLazyNodeF32::new(evalexpr::build_operator_tree(s).unwrap()))
+        .collect::<Vec<_>>();
+    let (tree, tree_result) = time_frames(|| lazy_frame(&world(false), &lazy_controls));
+    let (compiled, compiled_result) = time_frames(|| lazy_frame(&world(true), &lazy_controls));
+    assert_eq!(tree_result, compiled_result);
+    report("lazy", tree, compiled);
+}
@@ -0,0 +1,422 @@
+// expressions turned into something cheaper to run than evalexpr's tree walk.
+// variables get a slot so each one is looked up once per eval, constant parts
+// are folded, and we don't allocate an argument vec for every node. the
+// operators follow evalexpr's rules (ints stay ints, string +, etc), and
+// functions still go through the context so they're the exact same ones.
+//
+// anything we don't handle (assignments) just doesn't compile, and the
+// caller falls back to the node.
+use evalexpr::{
+    Context, EmptyContext, EvalexprError, EvalexprResult, Node, Operator, Value,
+    build_operator_tree, error::expect_number_or_string,
+};
+use itertools::Itertools;
+
+// name of the argument when calling an evalexpr builtin through a node
+const BUILTIN_ARG: &str = "__arg";
+
+#[derive(Debug, Clone)]
+pub struct CompiledExpr {
+    root: Op,
+    vars: Vec<String>,
+}
+impl CompiledExpr {
+    pub fn compile(node: &Node) -> Option<Self> {
+        let mut vars = vec![];
+        let root = compile_node(node, &mut vars)?;
+        Some(Self { root, vars })
+    }
+
+    // the variables it reads, in slot order
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    pub fn eval<C: Context>(&self, ctx: &C) -> EvalexprResult<Value> {
+        // missing ones only error if they're used, same as evalexpr
+        let slots = self.vars.iter().map(|x| ctx.get_value(x)).collect_vec();
+        self.root.eval(&slots, &self.vars, ctx)
+    }
+
+    // same conversions as ControlF32
+    pub fn eval_f32<C: Context>(&self, ctx: &C) -> EvalexprResult<f32> {
+        match self.eval(ctx)? {
+            Value::Float(x) => Ok(x as f32),
+            Value::Int(x) => Ok(x as f32),
+            Value::Boolean(b) => Ok(if b { 1.0 } else { -1.0 }),
+            v => Err(EvalexprError::expected_boolean(v)),
+        }
+    }
+
+    // and ControlBool
+    pub fn eval_bool<C: Context>(&self, ctx: &C) -> EvalexprResult<bool> {
+        match self.eval(ctx)? {
+            Value::Boolean(b) => Ok(b),
+            Value::Float(x) => Ok(x > 0.0),
+            Value::Int(x) => Ok(x > 0),
+            v => Err(EvalexprError::expected_int(v)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Exp,
+    Eq,
+    Neq,
+    Gt,
+    Lt,
+    Geq,
+    Leq,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone)]
+struct Call {
+    name: String,
+    arg: Op,
+    builtin: Node, // `name(__arg)`, for when the context doesn't have it
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Const(Value),
+    Var(usize),
+    Neg(Box<Op>),
+    Not(Box<Op>),
+    Binary(BinOp, Box<Op>, Box<Op>),
+    Tuple(Vec<Op>),
+    Chain(Vec<Op>),
+    Call(Box<Call>),
+}
+impl Op {
+    fn eval<C: Context>(
+        &self,
+        slots: &[Option<&Value>],
+        names: &[String],
+        ctx: &C,
+    ) -> EvalexprResult<Value> {
+        match self {
+            Op::Const(v) => Ok(v.clone()),
+            Op::Var(i) => slots[*i]
+                .cloned()
+                .ok_or_else(|| EvalexprError::VariableIdentifierNotFound(names[*i].clone())),
+            Op::Neg(a) => neg(a.eval(slots, names, ctx)?),
+            Op::Not(a) => Ok(Value::Boolean(!a.eval(slots, names, ctx)?.as_boolean()?)),
+            Op::Binary(op, a, b) => {
+                // evalexpr evaluates both sides, even for && and ||
+                let a = a.eval(slots, names, ctx)?;
+                let b = b.eval(slots, names, ctx)?;
+                binary(*op, a, b)
+            }
+            Op::Tuple(items) => Ok(Value::Tuple(
+                items
+                    .iter()
+                    .map(|x| x.eval(slots, names, ctx))
+                    .collect::<EvalexprResult<Vec<_>>>()?,
+            )),
+            Op::Chain(items) => {
+                let mut last = Value::Empty;
+                for x in items {
+                    last = x.eval(slots, names, ctx)?;
+                }
+                Ok(last)
+            }
+            Op::Call(call) => {
+                let arg = call.arg.eval(slots, names, ctx)?;
+                match ctx.call_function(&call.name, &arg) {
+                    Err(EvalexprError::FunctionIdentifierNotFound(_))
+                        if !ctx.are_builtin_functions_disabled() =>
+                    {
+                        call.builtin.eval_with_context(&BuiltinArgContext(&arg))
+                    }
+                    result => result,
+                }
+            }
+        }
+    }
+
+    fn is_const(&self) -> bool {
+        matches!(self, Op::Const(_))
+    }
+
+    // if everything going in is a constant, just do it now. if it errors,
+    // leave it so it errors the same way every frame
+    fn fold(self) -> Op {
+        let can_fold = match &self {
+            Op::Neg(a) | Op::Not(a) => a.is_const(),
+            Op::Binary(_, a, b) => a.is_const() && b.is_const(),
+            Op::Tuple(items) | Op::Chain(items) => items.iter().all(|x| x.is_const()),
+            _ => false,
+        };
+        if can_fold && let Ok(v) = self.eval(&[], &[], &EmptyContext) {
+            return Op::Const(v);
+        }
+        self
+    }
+}
+
+fn compile_node(node: &Node, vars: &mut Vec<String>) -> Option<Op> {
+    let children = node.children();
+    let compile_children = |vars: &mut Vec<String>| {
+        children
+            .iter()
+            .map(|x| compile_node(x, vars))
+            .collect::<Option<Vec<_>>>()
+    };
+
+    let op = match node.operator() {
+        Operator::RootNode => match children {
+            [] => Op::Const(Value::Empty),
+            [child] => compile_node(child, vars)?,
+            _ => return None,
+        },
+        Operator::Const { value } => Op::Const(value.clone()),
+        Operator::VariableIdentifierRead { identifier } => {
+            let slot = match vars.iter().position(|x| x == identifier) {
+                Some(slot) => slot,
+                None => {
+                    vars.push(identifier.clone());
+                    vars.len() - 1
+                }
+            };
+            Op::Var(slot)
+        }
+        Operator::Neg => Op::Neg(Box::new(
+            compile_children(vars)?.into_iter().exactly_one().ok()?,
+        )),
+        Operator::Not => Op::Not(Box::new(
+            compile_children(vars)?.into_iter().exactly_one().ok()?,
+        )),
+        Operator::Tuple => Op::Tuple(compile_children(vars)?),
+        Operator::Chain if !children.is_empty() => Op::Chain(compile_children(vars)?),
+        Operator::FunctionIdentifier { identifier } => {
+            let arg = compile_children(vars)?.into_iter().exactly_one().ok()?;
+            let builtin = build_operator_tree(&format!("{}({})", identifier, BUILTIN_ARG)).ok()?;
+            Op::Call(Box::new(Call {
+                name: identifier.clone(),
+                arg,
+                builtin,
+            }))
+        }
+        op => {
+            let bin_op = match op {
+                Operator::Add => BinOp::Add,
+                Operator::Sub => BinOp::Sub,
+                Operator::Mul => BinOp::Mul,
+                Operator::Div => BinOp::Div,
+                Operator::Mod => BinOp::Mod,
+                Operator::Exp => BinOp::Exp,
+                Operator::Eq => BinOp::Eq,
+                Operator::Neq => BinOp::Neq,
+                Operator::Gt => BinOp::Gt,
+                Operator::Lt => BinOp::Lt,
+                Operator::Geq => BinOp::Geq,
+                Operator::Leq => BinOp::Leq,
+                Operator::And => BinOp::And,
+                Operator::Or => BinOp::Or,
+                // assignments, these need a mutable context
+                _ => return None,
+            };
+            let (a, b) = compile_children(vars)?.into_iter().collect_tuple()?;
+            Op::Binary(bin_op, Box::new(a), Box::new(b))
+        }
+    };
+
+    Some(op.fold())
+}
+
+fn neg(a: Value) -> EvalexprResult<Value> {
+    a.as_number()?;
+    if let Ok(x) = a.as_int() {
+        x.checked_neg()
+            .map(Value::Int)
+            .ok_or(EvalexprError::NegationError { argument: a })
+    } else {
+        Ok(Value::Float(-a.as_number()?))
+    }
+}
+
+// arithmetic on two ints stays an int (and errors on overflow), otherwise float
+fn int_or_float(
+    a: Value,
+    b: Value,
+    int_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+    err: fn(Value, Value) -> EvalexprError,
+) -> EvalexprResult<Value> {
+    let (x, y) = (a.as_number()?, b.as_number()?);
+    if let (Ok(i), Ok(j)) = (a.as_int(), b.as_int()) {
+        int_op(i, j).map(Value::Int).ok_or_else(|| err(a, b))
+    } else {
+        Ok(Value::Float(float_op(x, y)))
+    }
+}
+
+fn compare(a: &Value, b: &Value, cmp: fn(std::cmp::Ordering) -> bool) -> EvalexprResult<Value> {
+    expect_number_or_string(a)?;
+    expect_number_or_string(b)?;
+    let ord = if let (Ok(x), Ok(y)) = (a.as_string(), b.as_string()) {
+        x.partial_cmp(&y)
+    } else if let (Ok(x), Ok(y)) = (a.as_int(), b.as_int()) {
+        x.partial_cmp(&y)
+    } else {
+        a.as_number()?.partial_cmp(&b.as_number()?)
+    };
+    // NaN compares false with everything
+    Ok(Value::Boolean(ord.is_some_and(cmp)))
+}
+
+fn binary(op: BinOp, a: Value, b: Value) -> EvalexprResult<Value> {
+    match op {
+        BinOp::Add => {
+            expect_number_or_string(&a)?;
+            expect_number_or_string(&b)?;
+            if let (Value::String(x), Value::String(y)) = (&a, &b) {
+                Ok(Value::String(format!("{}{}", x, y)))
+            } else if let (Ok(x), Ok(y)) = (a.as_number(), b.as_number()) {
+                if let (Ok(i), Ok(j)) = (a.as_int(), b.as_int()) {
+                    i.checked_add(j)
+                        .map(Value::Int)
+                        .ok_or(EvalexprError::AdditionError {
+                            augend: a,
+                            addend: b,
+                        })
+                } else {
+                    Ok(Value::Float(x + y))
+                }
+            } else {
+                Err(EvalexprError::wrong_type_combination(
+                    Operator::Add,
+                    vec![(&a).into(), (&b).into()],
+                ))
+            }
+        }
+        BinOp::Sub => int_or_float(
+            a,
+            b,
+            i64::checked_sub,
+            |x, y| x - y,
+            |minuend, subtrahend| EvalexprError::SubtractionError {
+                minuend,
+                subtrahend,
+            },
+        ),
+        BinOp::Mul => int_or_float(
+            a,
+            b,
+            i64::checked_mul,
+            |x, y| x * y,
+            |multiplicand, multiplier| EvalexprError::MultiplicationError {
+                multiplicand,
+                multiplier,
+            },
+        ),
+        BinOp::Div => int_or_float(
+            a,
+            b,
+            i64::checked_div,
+            |x, y| x / y,
+            |dividend, divisor| EvalexprError::DivisionError { dividend, divisor },
+        ),
+        BinOp::Mod => int_or_float(
+            a,
+            b,
+            i64::checked_rem,
+            |x, y| x % y,
+            |dividend, divisor| EvalexprError::ModulationError { dividend, divisor },
+        ),
+        BinOp::Exp => Ok(Value::Float(a.as_number()?.powf(b.as_number()?))),
+        BinOp::Eq => Ok(Value::Boolean(a == b)),
+        BinOp::Neq => Ok(Value::Boolean(a != b)),
+        BinOp::Gt => compare(&a, &b, |o| o.is_gt()),
+        BinOp::Lt => compare(&a, &b, |o| o.is_lt()),
+        BinOp::Geq => compare(&a, &b, |o| o.is_ge()),
+        BinOp::Leq => compare(&a, &b, |o| o.is_le()),
+        BinOp::And => {
+            let (x, y) = (a.as_boolean()?, b.as_boolean()?);
+            Ok(Value::Boolean(x && y))
+        }
+        BinOp::Or => {
+            let (x, y) = (a.as_boolean()?, b.as_boolean()?);
+            Ok(Value::Boolean(x || y))
+        }
+    }
+}
+
+// lets a builtin (floor, min, math::sin, ...) be called with an argument we
+// already evaluated
+struct BuiltinArgContext<'a>(&'a Value);
+impl Context for BuiltinArgContext<'_> {
+    fn get_value(&self, identifier: &str) -> Option<&Value> {
+        (identifier == BUILTIN_ARG).then_some(self.0)
+    }
+
+    fn call_function(&self, identifier: &str, _argument: &Value) -> EvalexprResult<Value> {
+        Err(EvalexprError::FunctionIdentifierNotFound(
+            identifier.to_owned(),
+        ))
+    }
+
+    fn are_builtin_functions_disabled(&self) -> bool {
+        false
+    }
+
+    fn set_builtin_functions_disabled(&mut self, _disabled: bool) -> EvalexprResult<()> {
+        Err(EvalexprError::ContextNotMutable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evalexpr::ContextWithMutableVariables;
+
+    use super::*;
+    use crate::expr::init_evalexpr_func_ctx;
+
+    #[test]
+    fn compiled_matches_evalexpr() {
+        let mut ctx = init_evalexpr_func_ctx().unwrap();
+        ctx.set_value("t".to_owned(), Value::Float(1.3)).unwrap();
+        ctx.set_value("i".to_owned(), Value::Int(7)).unwrap();
+        ctx.set_value("b".to_owned(), Value::Boolean(true)).unwrap();
+
+        let exprs = [
+            "s(ease(t, 0.25), 1.0, 20.0)",
+            "i / 2 + i % 3 - -i",
+            "t * 2 + 1 / 3 ^ 2",
+            "clamp(sin(t, 0.5) * i, 0.0, 1.0)",
+            "if(b && t > 1, floor(t * 10), max(i, 3))",
+            "math::sin(t) + min(1, 2.0)",
+            "idx((1, 2, 3), i)",
+            "\"a\" + \"b\" == \"ab\"",
+            "(t, i, b)",
+            "2 * 3; t",
+            "i < 10 || nope > 1",
+            "9223372036854775807 + i",
+            "sin(t, 1.0, 2.0, 3.0)",
+            "nope(t)",
+            "missing + 1",
+        ];
+
+        for s in exprs {
+            let node = build_operator_tree(s).unwrap();
+            let compiled = CompiledExpr::compile(&node).unwrap();
+            assert_eq!(
+                format!("{:?}", compiled.eval(&ctx)),
+                format!("{:?}", node.eval_with_context(&ctx)),
+                "{}",
+                s
+            );
+        }
+
+        // can't assign without a mutable context
+        assert!(CompiledExpr::compile(&build_operator_tree("a = 1").unwrap()).is_none());
+    }
+}
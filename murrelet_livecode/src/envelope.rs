@@ -0,0 +1,246 @@
+// attack/decay/sustain/release envelopes, so a key or pad hit can act like a note.
+//
+//   envelopes:
+//     hit:
+//       trigger: kAf
+//       attack: 0.05
+//       decay: 0.2
+//       sustain: 0.6
+//       release: 0.5
+//
+// while the trigger is on it ramps up to 1 over `attack`, falls to `sustain`
+// over `decay` and stays there. when it turns off it falls to 0 over `release`.
+// the times are in seconds, or in beats with `beats: true`. the value shows up
+// in the world as `hit`, so `ctx` and every field can use it.
+use std::collections::{BTreeMap, HashMap};
+
+use itertools::Itertools;
+use lerpable::{Lerpable, step};
+use murrelet_common::LivecodeValue;
+use murrelet_gui::CanMakeGUI;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    livecode::{GetLivecodeIdentifiers, LivecodeExpr, LivecodeFunction, LivecodeVariable},
+    nestedit::{NestEditable, NestedMod},
+    state::{LiveCodeTimeInstantInfo, LivecodeWorldState},
+    stateful::is_triggered,
+    types::{LivecodeError, LivecodeResult},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct EnvelopeConf {
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    trigger: LivecodeExpr, // on while it's true, or a number > 0
+    #[serde(default)]
+    attack: f32,
+    #[serde(default)]
+    decay: f32,
+    #[serde(default = "_default_sustain")]
+    sustain: f32, // level to hold at, 0 to 1
+    #[serde(default)]
+    release: f32,
+    #[serde(default)]
+    beats: bool, // measure the times in beats instead of seconds
+}
+
+fn _default_sustain() -> f32 {
+    1.0
+}
+
+impl EnvelopeConf {
+    fn now(&self, time: &LiveCodeTimeInstantInfo) -> f32 {
+        if self.beats {
+            time.beat()
+        } else {
+            time.seconds()
+        }
+    }
+
+    fn level(&self, s: &EnvelopeState, now: f32) -> f32 {
+        let elapsed = now - s.since;
+        if s.gate {
+            if elapsed < self.attack {
+                s.from + (1.0 - s.from) * elapsed / self.attack
+            } else if elapsed < self.attack + self.decay {
+                1.0 + (self.sustain - 1.0) * (elapsed - self.attack) / self.decay
+            } else {
+                self.sustain
+            }
+        } else if elapsed < self.release {
+            s.from * (1.0 - elapsed / self.release)
+        } else {
+            0.0
+        }
+    }
+
+    // moves the state along to `now` and returns the level
+    fn step(&self, s: &mut EnvelopeState, gate: bool, now: f32) -> f32 {
+        // time went backwards (reset), so start this stage over
+        if now < s.since {
+            s.since = now;
+        }
+        if gate != s.gate {
+            *s = EnvelopeState {
+                gate,
+                since: now,
+                from: self.level(s, now),
+            };
+        }
+        self.level(s, now)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct EnvelopeState {
+    gate: bool,
+    since: f32, // when the gate last changed
+    from: f32,  // the level at that point, so retriggering doesn't jump
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Envelopes(BTreeMap<String, EnvelopeConf>);
+impl Envelopes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.keys().cloned().collect_vec()
+    }
+
+    // checks the triggers, these only need the inputs so use the timeless world
+    pub fn eval_triggers(&self, w: &LivecodeWorldState) -> LivecodeResult<Vec<bool>> {
+        let ctx = w.to_local();
+        self.0
+            .values()
+            .map(|e| {
+                e.trigger
+                    .eval_with_context(&ctx)
+                    .and_then(|v| is_triggered(&v))
+                    .map_err(|err| e.trigger.to_err(err))
+            })
+            .collect()
+    }
+}
+
+// keeps track of when each envelope was triggered. these stick around through
+// reloads, so a note that's ringing out keeps going.
+#[derive(Debug, Clone, Default)]
+pub struct EnvelopeStates(HashMap<String, EnvelopeState>);
+impl EnvelopeStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `triggers` is from Envelopes::eval_triggers. returns the values to add
+    // to the world
+    pub fn update(
+        &mut self,
+        envelopes: &Envelopes,
+        triggers: &[bool],
+        time: &LiveCodeTimeInstantInfo,
+    ) -> Vec<(String, LivecodeValue)> {
+        self.0.retain(|name, _| envelopes.0.contains_key(name));
+
+        envelopes
+            .0
+            .iter()
+            .zip(triggers)
+            .map(|((name, conf), gate)| {
+                let s = self.0.entry(name.clone()).or_default();
+                let level = conf.step(s, *gate, conf.now(time));
+                (name.clone(), LivecodeValue::float(level))
+            })
+            .collect_vec()
+    }
+}
+
+impl GetLivecodeIdentifiers for Envelopes {
+    fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
+        self.0
+            .values()
+            .flat_map(|e| {
+                e.trigger
+                    .iter_read_variable_identifiers()
+                    .map(LivecodeVariable::from_str)
+                    .collect_vec()
+            })
+            .sorted()
+            .dedup()
+            .collect_vec()
+    }
+
+    fn function_identifiers(&self) -> Vec<LivecodeFunction> {
+        self.0
+            .values()
+            .flat_map(|e| {
+                e.trigger
+                    .iter_function_identifiers()
+                    .map(LivecodeFunction::from_str)
+                    .collect_vec()
+            })
+            .sorted()
+            .dedup()
+            .collect_vec()
+    }
+}
+
+impl CanMakeGUI for Envelopes {
+    fn make_gui() -> murrelet_gui::MurreletGUISchema {
+        murrelet_gui::MurreletGUISchema::Val(murrelet_gui::ValueGUI::Defs)
+    }
+}
+
+impl NestEditable for Envelopes {
+    fn nest_update(&self, _mods: NestedMod) -> Self {
+        self.clone()
+    }
+
+    fn nest_get(&self, _getter: &[&str]) -> LivecodeResult<String> {
+        Err(LivecodeError::NestGetExtra("Envelopes".to_owned()))
+    }
+}
+
+impl Lerpable for Envelopes {
+    fn lerpify<T: lerpable::IsLerpingMethod>(&self, other: &Self, pct: &T) -> Self {
+        step(self, other, pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_attack_decay_sustain_release() {
+        let conf = EnvelopeConf {
+            trigger: LivecodeExpr::new("kAf").unwrap(),
+            attack: 1.0,
+            decay: 1.0,
+            sustain: 0.5,
+            release: 2.0,
+            beats: false,
+        };
+
+        let mut s = EnvelopeState::default();
+        let mut run = |gate: bool, now: f32| conf.step(&mut s, gate, now);
+
+        assert_eq!(run(false, 0.0), 0.0);
+        assert_eq!(run(true, 1.0), 0.0);
+        assert_eq!(run(true, 1.5), 0.5);
+        assert_eq!(run(true, 2.0), 1.0);
+        assert_eq!(run(true, 2.5), 0.75);
+        assert_eq!(run(true, 10.0), 0.5);
+        assert_eq!(run(false, 11.0), 0.5);
+        assert_eq!(run(false, 12.0), 0.25);
+        // retriggered halfway through the release, so it ramps from there
+        assert_eq!(run(true, 12.0), 0.25);
+        assert_eq!(run(true, 12.5), 0.625);
+        assert_eq!(run(false, 20.0), 0.5);
+        assert_eq!(run(false, 30.0), 0.0);
+    }
+}
@@ -4,294 +4,357 @@ use std::{collections::HashMap, f64::consts::PI, fmt::Debug, sync::Arc};
 use evalexpr::*;
 use glam::{Vec2, vec2};
 use itertools::Itertools;
-use murrelet_common::{IdxInRange, LivecodeValue, clamp, ease, lerp, map_range, smoothstep};
+use murrelet_common::{
+    Easing, IdxInRange, LivecodeValue, bezier_ease, clamp, ease, lerp, map_range, smoothstep,
+};
 use noise::{NoiseFn, Perlin};
 use rand::{Rng, SeedableRng, rngs::StdRng};
 
 use crate::types::{AdditionalContextNode, LivecodeError, LivecodeResult};
 use regex::Regex;
 
-pub fn init_evalexpr_func_ctx() -> LivecodeResult<HashMapContext> {
-    context_map!{
-        // constants
-        "PI" => Value::Float(PI),
-        "ROOT2" => Value::Float(2.0_f64.sqrt()),
-        "ROOT3" => Value::Float(3.0_f64.sqrt()),
-
-        // functions
-        "print" => Function::new(move |argument| {
-            if let Ok(a) = argument.as_float() {
-                println!("{:?} (float)", a);
-            } else {
-                let a = argument.as_int()?;
-                println!("{:?} (int)", a);
-            }
-            Ok(Value::Empty)
-        }),
-        "p" => Function::new(move |argument| {
-            if let Ok(a) = argument.as_float() {
-                println!("{:?} (float)", a);
-                Ok(Value::Float(a))
-            } else {
-                let a = argument.as_int()?;
-                println!("{:?} (int)", a);
-                Ok(Value::Int(a))
-            }
-        }),
-        "manymod" => Function::new(move |argument| {
-            let a = argument.as_tuple()?;
+// keeps the names next to the functions, so the lint can tell what's defined
+// without having to call anything
+macro_rules! livecode_functions {
+    ($($name:literal => Function::new($($func:tt)*)),* $(,)?) => {
+        pub const LIVECODE_FUNCTIONS: &[&str] = &[$($name),*];
+
+        fn livecode_function_ctx() -> EvalexprResult<HashMapContext> {
+            context_map! { $($name => Function::new($($func)*)),* }
+        }
+    };
+}
+
+livecode_functions! {
+    "print" => Function::new(move |argument| {
+        if let Ok(a) = argument.as_float() {
+            println!("{:?} (float)", a);
+        } else {
+            let a = argument.as_int()?;
+            println!("{:?} (int)", a);
+        }
+        Ok(Value::Empty)
+    }),
+    "p" => Function::new(move |argument| {
+        if let Ok(a) = argument.as_float() {
+            println!("{:?} (float)", a);
+            Ok(Value::Float(a))
+        } else {
+            let a = argument.as_int()?;
+            println!("{:?} (int)", a);
+            Ok(Value::Int(a))
+        }
+    }),
+    "manymod" => Function::new(move |argument| {
+        let a = argument.as_tuple()?;
 
-            let mut result = 0;
-            let mut offset = 1;
+        let mut result = 0;
+        let mut offset = 1;
 
-            for val in &a {
-                let tuple = val.as_fixed_len_tuple(2)?;
-                let (var, mod_thing) = (tuple[0].as_number()? as i64, tuple[1].as_number()? as i64);
+        for val in &a {
+            let tuple = val.as_fixed_len_tuple(2)?;
+            let (var, mod_thing) = (tuple[0].as_number()? as i64, tuple[1].as_number()? as i64);
 
-                result += (var % mod_thing) * offset;
-                offset *= mod_thing;
+            result += (var % mod_thing) * offset;
+            offset *= mod_thing;
+        }
+        Ok(Value::Int(result))
+    }),
+    "trigger" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (val, last_val, rate) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = (val / rate).floor() > (last_val / rate).floor();
+        Ok(Value::Boolean(f))
+    }),
+
+    "clamp" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (x, min, max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = clamp(x as f32, min as f32, max as f32);
+        Ok(Value::Float(f as f64))
+    }),
+    "mix" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (min, max, pct) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = lerp(min as f32, max as f32, pct as f32);
+        Ok(Value::Float(f as f64))
+    }),
+    "s" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (src, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = map_range(src, 0.0, 1.0, out_min, out_max);
+        Ok(Value::Float(f as f64))
+    }),
+    "s11" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (src, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = map_range(src, -1.0, 1.0, out_min, out_max);
+        Ok(Value::Float(f as f64))
+    }),
+    "slog" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (src, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = map_range(src, 0.0, 1.0, 10.0f64.powf(out_min), 10.0f64.powf(out_max));
+        Ok(Value::Float(f as f64))
+    }),
+    "remap" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(5)?;
+        let (src, in_min, in_max, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?, tuple[3].as_number()?, tuple[4].as_number()?);
+        let f = map_range(src, in_min, in_max, out_min, out_max);
+        Ok(Value::Float(f as f64))
+    }),
+    // map and clamp. clmap.
+    "clmap" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(5)?;
+        let (src, in_min, in_max, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?, tuple[3].as_number()?, tuple[4].as_number()?);
+        let f = map_range(clamp(src, in_min, in_max), in_min, in_max, out_min, out_max);
+        Ok(Value::Float(f as f64))
+    }),
+    // tri(i) makes 0.5 be 1, and 0 and 1 be 0
+    "tri" => Function::new(|argument| {
+        let src = argument.as_number()?;
+        let f = 1.0 - (src * 2.0 - 1.0).abs();
+        Ok(Value::Float(f))
+    }),
+    // l2 version of this, use power instead!
+    "tri2" => Function::new(|argument| {
+        let src = argument.as_number()?;
+        let f = 1.0 - (src * 2.0 - 1.0).powi(2);
+        Ok(Value::Float(f))
+    }),
+    "smooth" => Function::new(|argument| {
+        let t = argument.as_number()?;
+        let f = smoothstep(t, 0.0, 1.0);
+        Ok(Value::Float(f))
+    }),
+    // bounce(t, 0.25)
+    "bounce" => Function::new(|argument| {
+        let (src, mult, offset) = match argument.as_fixed_len_tuple(3) {
+            Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
+            Err(_) => {
+                let tuple = argument.as_fixed_len_tuple(2)?;
+                (tuple[0].as_number()?, tuple[1].as_number()?, 0.0)
             }
-            Ok(Value::Int(result))
-        }),
-        "trigger" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (val, last_val, rate) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = (val / rate).floor() > (last_val / rate).floor();
-            Ok(Value::Boolean(f))
-        }),
-
-        "clamp" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (x, min, max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = clamp(x as f32, min as f32, max as f32);
-            Ok(Value::Float(f as f64))
-        }),
-        "mix" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (min, max, pct) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = lerp(min as f32, max as f32, pct as f32);
-            Ok(Value::Float(f as f64))
-        }),
-        "s" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (src, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = map_range(src, 0.0, 1.0, out_min, out_max);
-            Ok(Value::Float(f as f64))
-        }),
-        "s11" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (src, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = map_range(src, -1.0, 1.0, out_min, out_max);
-            Ok(Value::Float(f as f64))
-        }),
-        "slog" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (src, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = map_range(src, 0.0, 1.0, 10.0f64.powf(out_min), 10.0f64.powf(out_max));
-            Ok(Value::Float(f as f64))
-        }),
-        "remap" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(5)?;
-            let (src, in_min, in_max, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?, tuple[3].as_number()?, tuple[4].as_number()?);
-            let f = map_range(src, in_min, in_max, out_min, out_max);
-            Ok(Value::Float(f as f64))
-        }),
-        // map and clamp. clmap.
-        "clmap" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(5)?;
-            let (src, in_min, in_max, out_min, out_max) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?, tuple[3].as_number()?, tuple[4].as_number()?);
-            let f = map_range(clamp(src, in_min, in_max), in_min, in_max, out_min, out_max);
-            Ok(Value::Float(f as f64))
-        }),
-        // tri(i) makes 0.5 be 1, and 0 and 1 be 0
-        "tri" => Function::new(|argument| {
-            let src = argument.as_number()?;
-            let f = 1.0 - (src * 2.0 - 1.0).abs();
-            Ok(Value::Float(f))
-        }),
-        // l2 version of this, use power instead!
-        "tri2" => Function::new(|argument| {
-            let src = argument.as_number()?;
-            let f = 1.0 - (src * 2.0 - 1.0).powi(2);
-            Ok(Value::Float(f))
-        }),
-        "smooth" => Function::new(|argument| {
-            let t = argument.as_number()?;
-            let f = smoothstep(t, 0.0, 1.0);
-            Ok(Value::Float(f))
-        }),
-        // bounce(t, 0.25)
-        "bounce" => Function::new(|argument| {
-            let (src, mult, offset) = match argument.as_fixed_len_tuple(3) {
-                Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
-                Err(_) => {
-                    let tuple = argument.as_fixed_len_tuple(2)?;
-                    (tuple[0].as_number()?, tuple[1].as_number()?, 0.0)
-                }
-            };
-            let f = ((src * mult + offset) * PI * 2.0).sin() * 0.5 + 0.5;
-            Ok(Value::Float(f))
-        }),
-        "saw" => Function::new(|argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (src, mult) = (tuple[0].as_number()?, tuple[1].as_number()?);
-            // make a sawtooth
-            let f = ((src * mult) % 2.0 - 1.0).abs();
-            Ok(Value::Float(f))
-        }),
-        "ease" => Function::new(|argument| {
-            let (src, mult, offset) = match argument.as_fixed_len_tuple(3) {
-                Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
-                Err(_) => {
-                    let tuple = argument.as_fixed_len_tuple(2)?;
-                    (tuple[0].as_number()?, tuple[1].as_number()?, 0.0)
-                }
-            };
-            let f = ease(src, mult, offset);
-            Ok(Value::Float(f))
-        }),
-        "smoothstep" => Function::new(|argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (t, edge0, edge1) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = smoothstep(t, edge0, edge1);
-            Ok(Value::Float(f))
-        }),
-
-        "step" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (src, val) = (tuple[0].as_number()?, tuple[1].as_number()?);
-            let f = if src > val { 1.0 } else { 0.0 };
-            Ok(Value::Float(f))
-        }),
-        "pulse" => Function::new(|argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (pct, t, size) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let f = smoothstep(t, pct - size, pct) - smoothstep(t, pct, pct + size);
-            Ok(Value::Float(f))
-        }),
-        "ramp" => Function::new(|argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (src, length) = (tuple[0].as_number()?, tuple[1].as_number()?);
-            let f = (src * length).fract();
-            Ok(Value::Float(f))
-        }),
-        "idx" => Function::new(|argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (src, idx) = (tuple[0].as_tuple()?, tuple[1].as_number()?);
-            let idx = (idx as usize) % src.len();
-            let f = &src[idx];
-            Ok(f.clone())
-        }),
-        "rn" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (seed, idx) = (tuple[0].as_number()?, tuple[1].as_number()?);
-            let rn = StdRng::seed_from_u64((seed + 19247.0 * idx) as u64).gen_range(0.0..1.0);
-            Ok(Value::Float(rn))
-        }),
-        "perlin" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(3)?;
-            let (x, y, z) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
-            let perlin = Perlin::new(42); // todo, should we add seed to the inputs?
-            let rn = perlin.get([x, y, z]);
-            Ok(Value::Float(rn))
-        }),
-        "len" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (x, y) = (tuple[0].as_number()?, tuple[1].as_number()?);
-
-            let len = vec2(x as f32, y as f32).length();
-            Ok(Value::Float(len as f64))
-        }),
-
-        "pow" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (x, y) = (tuple[0].as_number()?, tuple[1].as_number()?);
-
-            let p = x.powf(y);
-            Ok(Value::Float(p))
-        }),
-        "sin" => Function::new(move |argument| {
-            let (t, w, phase) = match argument.as_fixed_len_tuple(3) {
-                Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
-                Err(_) => {
-                    match argument.as_fixed_len_tuple(2) {
-                        Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, 0.0),
-                        Err(_) => {
-                            (argument.as_float()?, 1.0, 0.0)
-                        },
-                    }
+        };
+        let f = ((src * mult + offset) * PI * 2.0).sin() * 0.5 + 0.5;
+        Ok(Value::Float(f))
+    }),
+    "saw" => Function::new(|argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (src, mult) = (tuple[0].as_number()?, tuple[1].as_number()?);
+        // make a sawtooth
+        let f = ((src * mult) % 2.0 - 1.0).abs();
+        Ok(Value::Float(f))
+    }),
+    "ease" => Function::new(|argument| {
+        let (src, mult, offset) = match argument.as_fixed_len_tuple(3) {
+            Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
+            Err(_) => {
+                let tuple = argument.as_fixed_len_tuple(2)?;
+                (tuple[0].as_number()?, tuple[1].as_number()?, 0.0)
+            }
+        };
+        let f = ease(src, mult, offset);
+        Ok(Value::Float(f))
+    }),
+    // css-style cubic-bezier(x1, y1, x2, y2) at t
+    "bezier_ease" => Function::new(|argument| {
+        let tuple = argument.as_fixed_len_tuple(5)?;
+        let (x1, y1, x2, y2, t) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?, tuple[3].as_number()?, tuple[4].as_number()?);
+        let f = bezier_ease(x1, y1, x2, y2, t);
+        Ok(Value::Float(f))
+    }),
+    "smoothstep" => Function::new(|argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (t, edge0, edge1) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = smoothstep(t, edge0, edge1);
+        Ok(Value::Float(f))
+    }),
+
+    "step" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (src, val) = (tuple[0].as_number()?, tuple[1].as_number()?);
+        let f = if src > val { 1.0 } else { 0.0 };
+        Ok(Value::Float(f))
+    }),
+    "pulse" => Function::new(|argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (pct, t, size) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let f = smoothstep(t, pct - size, pct) - smoothstep(t, pct, pct + size);
+        Ok(Value::Float(f))
+    }),
+    "ramp" => Function::new(|argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (src, length) = (tuple[0].as_number()?, tuple[1].as_number()?);
+        let f = (src * length).fract();
+        Ok(Value::Float(f))
+    }),
+    "idx" => Function::new(|argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (src, idx) = (tuple[0].as_tuple()?, tuple[1].as_number()?);
+        let idx = (idx as usize) % src.len();
+        let f = &src[idx];
+        Ok(f.clone())
+    }),
+    "rn" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (seed, idx) = (tuple[0].as_number()?, tuple[1].as_number()?);
+        let rn = StdRng::seed_from_u64((seed + 19247.0 * idx) as u64).gen_range(0.0..1.0);
+        Ok(Value::Float(rn))
+    }),
+    "perlin" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(3)?;
+        let (x, y, z) = (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?);
+        let perlin = Perlin::new(42); // todo, should we add seed to the inputs?
+        let rn = perlin.get([x, y, z]);
+        Ok(Value::Float(rn))
+    }),
+    "len" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (x, y) = (tuple[0].as_number()?, tuple[1].as_number()?);
+
+        let len = vec2(x as f32, y as f32).length();
+        Ok(Value::Float(len as f64))
+    }),
+
+    "pow" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (x, y) = (tuple[0].as_number()?, tuple[1].as_number()?);
+
+        let p = x.powf(y);
+        Ok(Value::Float(p))
+    }),
+    "sin" => Function::new(move |argument| {
+        let (t, w, phase) = match argument.as_fixed_len_tuple(3) {
+            Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
+            Err(_) => {
+                match argument.as_fixed_len_tuple(2) {
+                    Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, 0.0),
+                    Err(_) => {
+                        (argument.as_float()?, 1.0, 0.0)
+                    },
                 }
-            };
-            let f = (PI * 2.0 * (w * t + phase)).sin();
-            Ok(Value::Float(f))
-        }),
-        "sinpos" => Function::new(move |argument| {
-            let (t, w, phase) = match argument.as_fixed_len_tuple(3) {
-                Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
-                Err(_) => {
-                    match argument.as_fixed_len_tuple(2) {
-                        Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, 0.0),
-                        Err(_) => {
-                            (argument.as_float()?, 1.0, 0.0)
-                        },
-                    }
+            }
+        };
+        let f = (PI * 2.0 * (w * t + phase)).sin();
+        Ok(Value::Float(f))
+    }),
+    "sinpos" => Function::new(move |argument| {
+        let (t, w, phase) = match argument.as_fixed_len_tuple(3) {
+            Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
+            Err(_) => {
+                match argument.as_fixed_len_tuple(2) {
+                    Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, 0.0),
+                    Err(_) => {
+                        (argument.as_float()?, 1.0, 0.0)
+                    },
                 }
-            };
-            let f = 0.5 + 0.5 * (PI * 2.0 * (w * t + phase)).sin();
-            Ok(Value::Float(f))
-        }),
-        "quantize" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (x, y) = (tuple[0].as_number()?, tuple[1].as_number()?);
-
-            let p = (x * y).floor() / y;
-            Ok(Value::Float(p))
-        }),
-
-        "cos" => Function::new(move |argument| {
-            let (t, w, phase) = match argument.as_fixed_len_tuple(3) {
-                Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
-                Err(_) => {
-                    match argument.as_fixed_len_tuple(2) {
-                        Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, 0.0),
-                        Err(_) => {
-                            (argument.as_float()?, 1.0, 0.0)
-                        },
-                    }
+            }
+        };
+        let f = 0.5 + 0.5 * (PI * 2.0 * (w * t + phase)).sin();
+        Ok(Value::Float(f))
+    }),
+    "quantize" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (x, y) = (tuple[0].as_number()?, tuple[1].as_number()?);
+
+        let p = (x * y).floor() / y;
+        Ok(Value::Float(p))
+    }),
+
+    "cos" => Function::new(move |argument| {
+        let (t, w, phase) = match argument.as_fixed_len_tuple(3) {
+            Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, tuple[2].as_number()?),
+            Err(_) => {
+                match argument.as_fixed_len_tuple(2) {
+                    Ok(tuple) => (tuple[0].as_number()?, tuple[1].as_number()?, 0.0),
+                    Err(_) => {
+                        (argument.as_float()?, 1.0, 0.0)
+                    },
                 }
-            };
-            let f = (PI * 2.0 * (w * t + phase)).cos();
-            Ok(Value::Float(f))
-        }),
-        "res" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(9)?;
-            let (x, y, aa, bb, m, n, a, b) = (
-                tuple[0].as_number()?, tuple[1].as_number()?,
-                tuple[2].as_number()?, tuple[3].as_number()?,
-                tuple[4].as_number()?, tuple[5].as_number()?,
-                tuple[6].as_number()?, tuple[7].as_number()?,
-            );
-            let f = aa * (m * PI * x / a).cos() * (n * PI * y / a).cos() - bb * (n * PI * x / b).cos() * (m * PI * y / b).cos();
-            Ok(Value::Float(f))
-        }),
-        "len" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(2)?;
-            let (x1, y1) = (
-                tuple[0].as_number()?, tuple[1].as_number()?,
-            );
-            let f = vec2(x1 as f32, y1 as f32).length();
-            Ok(Value::Float(f as f64))
-        }),
-        "dist" => Function::new(move |argument| {
-            let tuple = argument.as_fixed_len_tuple(4)?;
-            let (x1, y1, x2, y2) = (
-                tuple[0].as_number()?, tuple[1].as_number()?,
-                tuple[2].as_number()?, tuple[3].as_number()?,
-            );
-            let f = vec2(x1 as f32, y1 as f32).distance(vec2(x2 as f32, y2 as f32));
-            Ok(Value::Float(f as f64))
-        })
-    }.map_err(|err| {LivecodeError::EvalExpr("error in init_evalexpr_func_ctx!".to_string(), err)})
+            }
+        };
+        let f = (PI * 2.0 * (w * t + phase)).cos();
+        Ok(Value::Float(f))
+    }),
+    "res" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(9)?;
+        let (x, y, aa, bb, m, n, a, b) = (
+            tuple[0].as_number()?, tuple[1].as_number()?,
+            tuple[2].as_number()?, tuple[3].as_number()?,
+            tuple[4].as_number()?, tuple[5].as_number()?,
+            tuple[6].as_number()?, tuple[7].as_number()?,
+        );
+        let f = aa * (m * PI * x / a).cos() * (n * PI * y / a).cos() - bb * (n * PI * x / b).cos() * (m * PI * y / b).cos();
+        Ok(Value::Float(f))
+    }),
+    "len" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (x1, y1) = (
+            tuple[0].as_number()?, tuple[1].as_number()?,
+        );
+        let f = vec2(x1 as f32, y1 as f32).length();
+        Ok(Value::Float(f as f64))
+    }),
+    "dist" => Function::new(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(4)?;
+        let (x1, y1, x2, y2) = (
+            tuple[0].as_number()?, tuple[1].as_number()?,
+            tuple[2].as_number()?, tuple[3].as_number()?,
+        );
+        let f = vec2(x1 as f32, y1 as f32).distance(vec2(x2 as f32, y2 as f32));
+        Ok(Value::Float(f as f64))
+    })
+}
+
+// everything init_evalexpr_func_ctx defines
+pub fn livecode_function_names() -> Vec<String> {
+    LIVECODE_FUNCTIONS
+        .iter()
+        .map(|x| x.to_string())
+        .chain(Easing::all().iter().map(|e| e.name().to_owned()))
+        .collect()
+}
+
+pub fn init_evalexpr_func_ctx() -> LivecodeResult<HashMapContext> {
+    let mut ctx = livecode_function_ctx().map_err(|err| {
+        LivecodeError::EvalExpr("error in init_evalexpr_func_ctx!".to_string(), err)
+    })?;
+
+    // constants
+    for (name, value) in [
+        ("PI", PI),
+        ("ROOT2", 2.0_f64.sqrt()),
+        ("ROOT3", 3.0_f64.sqrt()),
+    ] {
+        ctx.set_value(name.to_owned(), Value::Float(value))
+            .map_err(|err| LivecodeError::EvalExpr(format!("error adding `{}`", name), err))?;
+    }
+
+    // quad_in(t), cubic_in_out(t), elastic_out(t), etc
+    for e in Easing::all() {
+        ctx.set_function(
+            e.name(),
+            Function::new(move |argument| Ok(Value::Float(e.ease(argument.as_number()?)))),
+        )
+        .map_err(|err| {
+            LivecodeError::EvalExpr(format!("error adding function `{}`", e.name()), err)
+        })?;
+    }
+
+    Ok(ctx)
+}
+
+// what new_from_idx adds, without the allocating
+pub(crate) fn idx_vals(idx: IdxInRange) -> [(&'static str, LivecodeValue); 6] {
+    [
+        ("i", LivecodeValue::Int(idx.i() as i64)),
+        ("if", LivecodeValue::Float(idx.i() as f64)),
+        ("pct", LivecodeValue::Float(idx.pct() as f64)),
+        ("x", LivecodeValue::Float(idx.pct() as f64)), // just in case i use the wrong one
+        ("total", LivecodeValue::Int(idx.total() as i64)),
+        ("totalf", LivecodeValue::Float(idx.total() as f64)),
+    ]
 }
 
 pub fn lc_val_to_expr(v: &LivecodeValue) -> Value {
@@ -314,6 +377,10 @@ impl ExprWorldContextValues {
         Self::new(vec![])
     }
 
+    pub fn names(&self) -> Vec<String> {
+        self.0.keys().cloned().sorted().collect_vec()
+    }
+
     pub fn update_ctx(&self, ctx: &mut HashMapContext) -> LivecodeResult<()> {
         for (identifier, value) in &self.0 {
             // todo, maybe handle the result here to help dev
@@ -343,17 +410,12 @@ impl ExprWorldContextValues {
     }
 
     pub fn new_from_idx(idx: IdxInRange) -> Self {
-        Self::new(vec![
-            ("i".to_string(), LivecodeValue::Int(idx.i() as i64)),
-            ("if".to_string(), LivecodeValue::Float(idx.i() as f64)),
-            ("pct".to_string(), LivecodeValue::Float(idx.pct() as f64)),
-            ("x".to_string(), LivecodeValue::Float(idx.pct() as f64)), // just in case i use the wrong one
-            ("total".to_string(), LivecodeValue::Int(idx.total() as i64)),
-            (
-                "totalf".to_string(),
-                LivecodeValue::Float(idx.total() as f64),
-            ),
-        ])
+        Self::new(
+            idx_vals(idx)
+                .into_iter()
+                .map(|(name, v)| (name.to_string(), v))
+                .collect_vec(),
+        )
     }
 
     pub fn new_from_totaless_idx(idx: usize) -> Self {
@@ -593,6 +655,10 @@ impl MixedEvalDefs {
         &self.vals
     }
 
+    pub(crate) fn has_nodes(&self) -> bool {
+        !self.nodes.is_empty()
+    }
+
     pub fn add_idx(&mut self, i: IdxInRange, prefix: &str) {
         self.set_vals(ExprWorldContextValues::new_from_idx(i).with_prefix(prefix));
     }
@@ -607,3 +673,42 @@ impl MixedEvalDefs {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easings_start_at_0_and_end_at_1() {
+        let ctx = init_evalexpr_func_ctx().unwrap();
+        let eval = |s: String| {
+            build_operator_tree(&s)
+                .unwrap()
+                .eval_number_with_context(&ctx)
+                .unwrap()
+        };
+
+        for e in Easing::all() {
+            let name = e.name();
+            assert!(eval(format!("{}(0.0)", name)).abs() < 1e-6, "{}", name);
+            assert!(
+                (eval(format!("{}(1.0)", name)) - 1.0).abs() < 1e-6,
+                "{}",
+                name
+            );
+            assert!(
+                (eval(format!("{}(2)", name)) - 1.0).abs() < 1e-6,
+                "{}",
+                name
+            );
+        }
+        assert_eq!(eval("quad_in(0.5)".to_owned()), 0.25);
+        assert_eq!(eval("cubic_in_out(0.5)".to_owned()), 0.5);
+
+        // the straight line is just t
+        let linear = eval("bezier_ease(0.25, 0.25, 0.75, 0.75, 0.3)".to_owned());
+        assert!((linear - 0.3).abs() < 1e-6);
+        let css_ease = eval("bezier_ease(0.25, 0.1, 0.25, 1.0, 0.5)".to_owned());
+        assert!((css_ease - 0.8024).abs() < 1e-3);
+    }
+}
@@ -1,24 +1,24 @@
 use std::sync::Arc;
 
 use crate::{
-    expr::{ExprWorldContextValues, MixedEvalDefs, ToMixedDefs},
+    expr::{ExprWorldContextValues, MixedEvalDefs, ToMixedDefs, idx_vals, lc_val_to_expr},
     livecode::{
-        GetLivecodeIdentifiers, LivecodeFromWorld, LivecodeFunction, LivecodeToControl,
-        LivecodeVariable,
+        GetLivecodeIdentifiers, LivecodeExpr, LivecodeFromWorld, LivecodeFunction,
+        LivecodeToControl, LivecodeVariable,
     },
     nestedit::{NestEditable, NestedMod},
     state::{LivecodeWorldState, WorldWithLocalVariables},
     types::{LivecodeError, LivecodeResult},
 };
-use evalexpr::Node;
+use evalexpr::{Context, EvalexprError, EvalexprResult, Node, Value};
 
 use itertools::Itertools;
 use lerpable::IsLerpingMethod;
 use lerpable::{Lerpable, step};
 use murrelet_common::{IdxInRange, LivecodeValue, MurreletColor};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ControlLazyNodeF32 {
@@ -26,14 +26,14 @@ pub enum ControlLazyNodeF32 {
     Bool(bool),
     Float(f32),
     #[cfg_attr(feature = "schemars", schemars(with = "String"))]
-    Expr(Node),
+    Expr(LivecodeExpr),
 }
 
 impl ControlLazyNodeF32 {
     pub const ZERO: Self = ControlLazyNodeF32::Float(0.0);
 
     pub fn new(n: Node) -> Self {
-        Self::Expr(n)
+        Self::Expr(LivecodeExpr::from_node(n))
     }
 
     pub fn new_f32(n: f32) -> Self {
@@ -63,7 +63,7 @@ impl GetLivecodeIdentifiers for ControlLazyNodeF32 {
                 .iter_variable_identifiers()
                 .sorted()
                 .dedup()
-                .map(LivecodeVariable::from_str)
+                .map(LivecodeVariable::deferred)
                 .collect_vec(),
             _ => vec![],
         }
@@ -85,11 +85,11 @@ impl GetLivecodeIdentifiers for ControlLazyNodeF32 {
 // todo, figure out how to only build this context once per unitcell/etc
 #[derive(Debug, Clone)]
 pub struct LazyNodeF32Inner {
-    n: Arc<Node>,                   // what will be evaluated!
+    n: Arc<LivecodeExpr>,           // what will be evaluated!
     world: WorldWithLocalVariables, // this is a reference :D
 }
 impl LazyNodeF32Inner {
-    pub fn new(n: Node, world: LivecodeWorldState) -> Self {
+    pub fn new(n: LivecodeExpr, world: LivecodeWorldState) -> Self {
         Self {
             n: Arc::new(n),
             world: world.to_local(),
@@ -108,6 +108,21 @@ impl LazyNodeF32Inner {
         c
     }
 
+    // eval_idx gets called for every index, so skip building the map of idx
+    // variables. None if compiled exprs are off
+    fn eval_idx_compiled(&self, idx: IdxInRange, prefix: &str) -> Option<LivecodeResult<f32>> {
+        let c = self.n.compiled().filter(|_| self.world.compiled_exprs())?;
+        let ctx = IdxContext {
+            world: &self.world,
+            prefix,
+            vals: idx_vals(idx).map(|(name, v)| (name, lc_val_to_expr(&v))),
+        };
+        Some(
+            c.eval_f32(&ctx)
+                .map_err(|err| LivecodeError::EvalExpr("error evaluating lazy".to_string(), err)),
+        )
+    }
+
     // internal function to build the ctx
     fn build_ctx(&self) -> &WorldWithLocalVariables {
         &self.world
@@ -117,6 +132,12 @@ impl LazyNodeF32Inner {
     pub fn eval(&self) -> LivecodeResult<f32> {
         let ctx = self.build_ctx();
 
+        if let Some(c) = self.n.compiled().filter(|_| ctx.compiled_exprs()) {
+            return c
+                .eval_f32(ctx)
+                .map_err(|err| LivecodeError::EvalExpr("error evaluating lazy".to_string(), err));
+        }
+
         self.n
             .eval_float_with_context(ctx)
             .or_else(|_| self.n.eval_int_with_context(ctx).map(|x| x as f64))
@@ -130,6 +151,35 @@ impl LazyNodeF32Inner {
     }
 }
 
+// the idx variables (`{prefix}_pct`, etc) on top of the world
+struct IdxContext<'a> {
+    world: &'a WorldWithLocalVariables,
+    prefix: &'a str,
+    vals: [(&'static str, Value); 6],
+}
+impl Context for IdxContext<'_> {
+    fn get_value(&self, identifier: &str) -> Option<&Value> {
+        identifier
+            .strip_prefix(self.prefix)
+            .and_then(|x| x.strip_prefix('_'))
+            .and_then(|name| self.vals.iter().find(|(n, _)| *n == name))
+            .map(|(_, v)| v)
+            .or_else(|| self.world.get_value(identifier))
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        self.world.call_function(identifier, argument)
+    }
+
+    fn are_builtin_functions_disabled(&self) -> bool {
+        self.world.are_builtin_functions_disabled()
+    }
+
+    fn set_builtin_functions_disabled(&mut self, _disabled: bool) -> EvalexprResult<()> {
+        Err(EvalexprError::ContextNotMutable)
+    }
+}
+
 // // expr that we can add things
 #[derive(Debug, Clone, Default)]
 pub enum LazyNodeF32 {
@@ -154,7 +204,8 @@ impl LazyNodeF32 {
         )
     }
 
-    pub fn n(&self) -> Option<&Node> {
+    // the expression with its source, so it can be written out again
+    pub fn expr(&self) -> Option<&LivecodeExpr> {
         match self {
             LazyNodeF32::Uninitialized => None,
             LazyNodeF32::Node(n) => Some(&n.n),
@@ -162,6 +213,14 @@ impl LazyNodeF32 {
         }
     }
 
+    pub fn n(&self) -> Option<&Node> {
+        match self {
+            LazyNodeF32::Uninitialized => None,
+            LazyNodeF32::Node(n) => Some(n.n.node()),
+            LazyNodeF32::NoCtxNode(_) => None,
+        }
+    }
+
     pub fn eval_with_ctx<M: ToMixedDefs>(&self, more_defs: &M) -> LivecodeResult<f32> {
         // update ctx
         let with_more_ctx = self.add_more_defs(more_defs)?;
@@ -192,6 +251,9 @@ impl LazyNodeF32 {
                 Err(LivecodeError::Raw("uninitialized lazy node".to_owned()))
             }
             LazyNodeF32::Node(v) => {
+                if let Some(result) = v.eval_idx_compiled(idx, prefix) {
+                    return result;
+                }
                 let vals =
                     ExprWorldContextValues::new_from_idx(idx).with_prefix(&format!("{}_", prefix));
                 v.add_expr_values(&vals).eval()
@@ -317,7 +379,7 @@ impl NestEditable for LazyVec2 {
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ControlLazyVec2(Vec<ControlLazyNodeF32>);
 
@@ -382,7 +444,7 @@ impl LazyVec3 {
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ControlLazyVec3(Vec<ControlLazyNodeF32>);
 impl LivecodeFromWorld<LazyVec3> for ControlLazyVec3 {
@@ -455,7 +517,7 @@ impl LazyMurreletColor {
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ControlLazyMurreletColor(Vec<ControlLazyNodeF32>);
 
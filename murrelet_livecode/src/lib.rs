@@ -1,10 +1,19 @@
 pub mod app_src;
 pub mod boop;
 pub mod cachedcompute;
+pub mod compiled;
+pub mod envelope;
 pub mod expr;
 pub mod lazy;
+pub mod lint;
 pub mod livecode;
 pub mod nestedit;
+pub mod noisefuncs;
+pub mod sourcemap;
 pub mod state;
+pub mod stateful;
+pub mod timeline;
+pub mod transition;
 pub mod types;
 pub mod unitcells;
+pub mod userfuncs;
@@ -0,0 +1,316 @@
+// checks the variables and functions a config uses against what the world
+// knows about, so a typo shows up when the config reloads instead of as an
+// eval error (or a silent default) later.
+use std::{collections::HashSet, fmt};
+
+use itertools::Itertools;
+use murrelet_common::{IdxInRange2d, SimpleTransform2d};
+
+use crate::{
+    expr::{IntoExprWorldContext, livecode_function_names},
+    livecode::{GetLivecodeIdentifiers, LivecodeFunction, LivecodeVariable},
+    noisefuncs::NOISE_FUNCTIONS,
+    stateful::{ITEM_VAR, STATEFUL_FUNCTIONS},
+    types::AdditionalContextNode,
+    unitcells::{UnitCellContext, UnitCellIdx},
+};
+
+// evalexpr's builtins, it doesn't have a way to list them
+const BUILTIN_FUNCTIONS: &[&str] = &[
+    "math::ln",
+    "math::log",
+    "math::log2",
+    "math::log10",
+    "math::exp",
+    "math::exp2",
+    "math::pow",
+    "math::cos",
+    "math::acos",
+    "math::cosh",
+    "math::acosh",
+    "math::sin",
+    "math::asin",
+    "math::sinh",
+    "math::asinh",
+    "math::tan",
+    "math::atan",
+    "math::tanh",
+    "math::atanh",
+    "math::atan2",
+    "math::sqrt",
+    "math::cbrt",
+    "math::hypot",
+    "math::abs",
+    "math::is_nan",
+    "math::is_finite",
+    "math::is_infinite",
+    "math::is_normal",
+    "floor",
+    "round",
+    "ceil",
+    "typeof",
+    "min",
+    "max",
+    "if",
+    "contains",
+    "contains_any",
+    "len",
+    "str::to_lowercase",
+    "str::to_uppercase",
+    "str::trim",
+    "str::from",
+    "str::substring",
+    "bitand",
+    "bitor",
+    "bitxor",
+    "bitnot",
+    "shl",
+    "shr",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintKind {
+    Variable,
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LintWarning {
+    pub kind: LintKind,
+    pub name: String,
+    pub suggestion: Option<String>,
+}
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            LintKind::Variable => "variable",
+            LintKind::Function => "function",
+        };
+        write!(f, "unknown {} `{}`", kind, self.name)?;
+        if let Some(s) = &self.suggestion {
+            write!(f, ", did you mean `{}`?", s)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct IdentifierLint {
+    vars: HashSet<String>,
+    funcs: HashSet<String>,
+}
+impl IdentifierLint {
+    // known_vars is usually world.vars(). the functions are the ones every
+    // world has, user functions need to be added with add_funcs
+    pub fn new(known_vars: HashSet<String>) -> Self {
+        let funcs = BUILTIN_FUNCTIONS
+            .iter()
+            .chain(STATEFUL_FUNCTIONS.iter())
+            .chain(NOISE_FUNCTIONS.iter())
+            .map(|x| x.to_string())
+            .chain(livecode_function_names())
+            .collect();
+
+        Self {
+            vars: known_vars,
+            funcs,
+        }
+    }
+
+    // e.g. things the config's ctx defines
+    pub fn add_vars<I: IntoIterator<Item = String>>(&mut self, vars: I) {
+        self.vars.extend(vars);
+    }
+
+    pub fn add_funcs<I: IntoIterator<Item = String>>(&mut self, funcs: I) {
+        self.funcs.extend(funcs);
+    }
+
+    fn is_known_var(&self, name: &str) -> bool {
+        // osc values get a default when they're used, see LiveCoder. ITEM_VAR
+        // is added to stateful calls when they're parsed
+        self.vars.contains(name) || name.starts_with("oo_") || name == ITEM_VAR
+    }
+
+    fn is_known_func(&self, name: &str) -> bool {
+        self.funcs.contains(name)
+    }
+
+    // ctx nodes are usually deferred, but the app's ctx is evaluated with the world
+    pub fn check_ctx(&self, ctx: &AdditionalContextNode) -> Vec<LintWarning> {
+        let vars = ctx
+            .used_vars()
+            .iter()
+            .map(|x| LivecodeVariable::from_str(x))
+            .collect_vec();
+        self.check_identifiers(vars, ctx.funcs())
+    }
+
+    pub fn check<T: GetLivecodeIdentifiers>(&self, conf: &T) -> Vec<LintWarning> {
+        self.check_identifiers(conf.variable_identifiers(), conf.function_identifiers())
+    }
+
+    fn check_identifiers(
+        &self,
+        vars: Vec<LivecodeVariable>,
+        funcs: Vec<LivecodeFunction>,
+    ) -> Vec<LintWarning> {
+        let vars = vars
+            .into_iter()
+            .filter(|x| !x.is_deferred() && !self.is_known_var(&x.name))
+            .map(|x| x.name)
+            .sorted()
+            .dedup()
+            .map(|name| LintWarning {
+                kind: LintKind::Variable,
+                suggestion: closest(&name, self.vars.iter()),
+                name,
+            });
+
+        let funcs = funcs
+            .into_iter()
+            .map(|x| x.name().to_owned())
+            .filter(|x| !self.is_known_func(x))
+            .sorted()
+            .dedup()
+            .map(|name| LintWarning {
+                kind: LintKind::Function,
+                suggestion: closest(&name, self.funcs.iter()),
+                name,
+            });
+
+        vars.chain(funcs).collect_vec()
+    }
+}
+
+// the variables a repeat adds for each item, e.g. `i_x`, `i_frac`
+pub fn repeat_var_names(prefix: &str) -> Vec<String> {
+    let idx = UnitCellIdx::from_idx2d(IdxInRange2d::new(0, 0, 1), 1.0);
+    let mut names = idx
+        .as_expr_world_context_values()
+        .with_prefix(prefix)
+        .names();
+    names.push("vseed".to_owned());
+    names
+}
+
+// same for unitcells, which also get things like `u_width`
+pub fn unitcell_var_names(prefix: &str) -> Vec<String> {
+    let idx = UnitCellIdx::from_idx2d(IdxInRange2d::new(0, 0, 1), 1.0);
+    UnitCellContext::new(idx, SimpleTransform2d::ident())
+        .as_expr_world_context_values()
+        .with_prefix(prefix)
+        .names()
+}
+
+// drops the variables that are provided inside of a unitcell, since they
+// aren't missing. the ctx's own needs get checked too.
+pub fn without_unitcell_vars(
+    vars: Vec<LivecodeVariable>,
+    prefix: &str,
+    ctx: Option<AdditionalContextNode>,
+) -> Vec<LivecodeVariable> {
+    let mut scoped = unitcell_var_names(prefix);
+    let mut ctx_vars = vec![];
+    if let Some(ctx) = ctx {
+        scoped.extend(ctx.defined_vars());
+        ctx_vars = ctx
+            .used_vars()
+            .iter()
+            .map(|x| LivecodeVariable::from_str(x))
+            .collect_vec();
+    }
+
+    vars.into_iter()
+        .chain(ctx_vars)
+        .filter(|x| !scoped.contains(&x.name))
+        .collect_vec()
+}
+
+// for suggestions. optimal string alignment distance, so swapping two letters
+// (m13 vs m31) only counts as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect_vec();
+    let b = b.chars().collect_vec();
+
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+pub fn closest<'a, I>(name: &str, candidates: I) -> Option<String>
+where
+    I: Iterator<Item = &'a String>,
+{
+    // short names need to be closer, otherwise everything matches `t`
+    let max_dist = if name.chars().count() <= 4 { 1 } else { 2 };
+
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= max_dist)
+        .min()
+        .map(|(_, c)| c.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::livecode::ControlF32;
+    use evalexpr::EvalexprError;
+
+    #[test]
+    fn builtins_are_still_builtins() {
+        // calling it with nothing will fail, but it'll fail differently if it exists
+        for f in BUILTIN_FUNCTIONS {
+            assert!(
+                !matches!(
+                    evalexpr::eval(&format!("{}()", f)),
+                    Err(EvalexprError::FunctionIdentifierNotFound(_))
+                ),
+                "{} isn't a builtin",
+                f
+            );
+        }
+    }
+
+    #[test]
+    fn suggests_close_names() {
+        let known = ["m13", "t", "time"].iter().map(|x| x.to_string()).collect();
+        let lint = IdentifierLint::new(known);
+
+        let conf = vec![
+            ControlF32::force_from_str("smoth(m31) + t"),
+            ControlF32::force_from_str("clamp(time, 0.0, 1.0) * whatever"),
+        ];
+        let warnings = lint
+            .check(&conf)
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect_vec();
+
+        assert_eq!(
+            warnings,
+            vec![
+                "unknown variable `m31`, did you mean `m13`?",
+                "unknown variable `whatever`",
+                "unknown function `smoth`, did you mean `smooth`?",
+            ]
+        );
+    }
+}
@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::sync::Arc;
 
+use evalexpr::EvalexprError;
 use evalexpr::Node;
 use evalexpr::build_operator_tree;
 use glam::Vec2;
@@ -13,11 +15,15 @@ use murrelet_common::AnglePi;
 use murrelet_common::clamp;
 
 use murrelet_common::MurreletColor;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::compiled::CompiledExpr;
 use crate::lazy::ControlLazyNodeF32;
 use crate::lazy::LazyNodeF32;
+use crate::sourcemap::SourceLoc;
+use crate::sourcemap::locate_expr;
 use crate::state::LivecodeWorldState;
+use crate::stateful::tag_call_sites;
 use crate::types::AdditionalContextNode;
 use crate::types::ControlVecElement;
 use crate::types::LivecodeError;
@@ -71,7 +77,10 @@ where
     Source: LivecodeFromWorld<Target>,
 {
     fn o(&self, w: &LivecodeWorldState) -> LivecodeResult<Vec<Target>> {
-        self.iter().map(|x| x.o(w)).collect::<Result<Vec<_>, _>>()
+        self.iter()
+            .enumerate()
+            .map(|(i, x)| x.o(w).map_err(|e| e.with_field_path(&i.to_string())))
+            .collect::<Result<Vec<_>, _>>()
     }
 }
 
@@ -166,7 +175,11 @@ where
 
 impl LivecodeToControl<ControlLazyNodeF32> for LazyNodeF32 {
     fn to_control(&self) -> ControlLazyNodeF32 {
-        ControlLazyNodeF32::new(self.n().cloned().unwrap())
+        match self {
+            LazyNodeF32::Uninitialized => ControlLazyNodeF32::ZERO,
+            LazyNodeF32::Node(_) => ControlLazyNodeF32::Expr(self.expr().cloned().unwrap()),
+            LazyNodeF32::NoCtxNode(c) => c.clone(),
+        }
     }
 }
 
@@ -181,17 +194,39 @@ where
 }
 
 // wrappers around identifiers evalexpr gives us, right now
-// just to control midi controller
+// just to control midi controller and for the lint
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct LivecodeVariable {
     pub name: String,
+    // lazy things and ctx nodes get evaluated with whatever the code gives
+    // them, so we can't tell if these are missing until then
+    deferred: bool,
 }
 impl LivecodeVariable {
     pub fn from_str(name: &str) -> Self {
         Self {
             name: name.to_string(),
+            deferred: false,
         }
     }
+
+    pub fn deferred(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            deferred: true,
+        }
+    }
+
+    pub fn into_deferred(self) -> Self {
+        Self {
+            deferred: true,
+            ..self
+        }
+    }
+
+    pub fn is_deferred(&self) -> bool {
+        self.deferred
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -204,6 +239,10 @@ impl LivecodeFunction {
             name: name.to_string(),
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 pub trait GetLivecodeIdentifiers {
@@ -231,7 +270,7 @@ impl GetLivecodeIdentifiers for ControlF32 {
             ControlF32::Bool(_) => vec![],
             ControlF32::Float(_) => vec![],
             ControlF32::Expr(node) => node
-                .iter_variable_identifiers()
+                .iter_function_identifiers()
                 .dedup()
                 .map(LivecodeFunction::from_str)
                 .collect_vec(),
@@ -353,14 +392,24 @@ impl GetLivecodeIdentifiers for String {
 //
 impl GetLivecodeIdentifiers for AdditionalContextNode {
     fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
-        self.vars()
+        self.used_vars()
+            .iter()
+            .map(|x| LivecodeVariable::deferred(x))
+            .collect_vec()
     }
 
     fn function_identifiers(&self) -> Vec<LivecodeFunction> {
-        vec![]
+        self.funcs()
     }
 }
 
+// for things like lazy structs, where we don't know the variables until later
+pub fn deferred_identifiers(vars: Vec<LivecodeVariable>) -> Vec<LivecodeVariable> {
+    vars.into_iter()
+        .map(LivecodeVariable::into_deferred)
+        .collect_vec()
+}
+
 impl<K, V> GetLivecodeIdentifiers for HashMap<K, V> {
     fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
         vec![]
@@ -405,7 +454,7 @@ impl GetLivecodeIdentifiers for ControlBool {
             ControlBool::Raw(_) => vec![],
             ControlBool::Float(_) => vec![],
             ControlBool::Expr(node) => node
-                .iter_variable_identifiers()
+                .iter_function_identifiers()
                 .dedup()
                 .map(LivecodeFunction::from_str)
                 .collect_vec(),
@@ -499,7 +548,109 @@ pub fn _auto_default_bool_true() -> ControlBool {
     ControlBool::Raw(true)
 }
 
-#[derive(Debug, Deserialize, Clone)]
+// an expression, along with the text it came from and where that was in the yaml.
+// boxed so the control enums don't get huge
+#[derive(Debug, Clone)]
+pub struct LivecodeExpr(Box<LivecodeExprInner>);
+
+#[derive(Debug, Clone)]
+struct LivecodeExprInner {
+    node: Node,
+    src: String,
+    loc: Option<SourceLoc>,
+    compiled: Option<Arc<CompiledExpr>>, // built once here, so once per reload
+}
+
+impl LivecodeExpr {
+    fn new_with_loc(node: Node, src: String, loc: Option<SourceLoc>) -> Self {
+        let compiled = CompiledExpr::compile(&node).map(Arc::new);
+        Self(Box::new(LivecodeExprInner {
+            node,
+            src,
+            loc,
+            compiled,
+        }))
+    }
+
+    pub fn new(src: &str) -> Result<Self, EvalexprError> {
+        let mut node = build_operator_tree(src)?;
+        tag_call_sites(&mut node);
+        Ok(Self::new_with_loc(node, src.to_owned(), None))
+    }
+
+    pub fn from_node(node: Node) -> Self {
+        let src = node.to_string();
+        Self::new_with_loc(node, src, None)
+    }
+
+    pub fn node(&self) -> &Node {
+        &self.0.node
+    }
+
+    // None if it does something the compiled version doesn't support
+    pub fn compiled(&self) -> Option<&CompiledExpr> {
+        self.0.compiled.as_deref()
+    }
+
+    pub fn src(&self) -> &str {
+        &self.0.src
+    }
+
+    pub fn loc(&self) -> Option<&SourceLoc> {
+        self.0.loc.as_ref()
+    }
+
+    pub fn to_err(&self, err: EvalexprError) -> LivecodeError {
+        let msg = format!("evalexpr err `{}`", self.src());
+        match self.loc() {
+            Some(loc) => LivecodeError::EvalExprAt(msg, Box::new(loc.clone()), err),
+            None => LivecodeError::EvalExpr(msg, err),
+        }
+    }
+}
+
+impl std::ops::Deref for LivecodeExpr {
+    type Target = Node;
+
+    fn deref(&self) -> &Self::Target {
+        self.node()
+    }
+}
+
+// written back as it was typed
+impl Serialize for LivecodeExpr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.src())
+    }
+}
+
+impl<'de> Deserialize<'de> for LivecodeExpr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let src = String::deserialize(deserializer)?;
+        let loc = locate_expr(&src);
+        match build_operator_tree(&src) {
+            Ok(mut node) => {
+                tag_call_sites(&mut node);
+                Ok(LivecodeExpr::new_with_loc(node, src, loc))
+            }
+            Err(err) => {
+                let at = loc.map(|l| format!("{}: ", l)).unwrap_or_default();
+                Err(serde::de::Error::custom(format!(
+                    "{}couldn't parse expression `{}`: {}",
+                    at, src, err
+                )))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ControlF32 {
@@ -507,7 +658,7 @@ pub enum ControlF32 {
     Bool(bool),
     Float(f32),
     #[cfg_attr(feature = "schemars", schemars(with = "String"))]
-    Expr(Node),
+    Expr(LivecodeExpr),
 }
 
 impl ControlF32 {
@@ -520,7 +671,7 @@ impl ControlF32 {
     }
 
     pub fn force_from_str(s: &str) -> ControlF32 {
-        match build_operator_tree(s) {
+        match LivecodeExpr::new(s) {
             Ok(e) => Self::Expr(e),
             Err(err) => {
                 println!("{:?}", err);
@@ -530,8 +681,6 @@ impl ControlF32 {
     }
 
     pub fn _o(&self, w: &LivecodeWorldState) -> LivecodeResult<f32> {
-        let a = w.ctx()?;
-        let ctx = a.as_ref();
         match self {
             ControlF32::Bool(b) => {
                 if *b {
@@ -542,15 +691,22 @@ impl ControlF32 {
             }
             ControlF32::Int(i) => Ok(*i as f32),
             ControlF32::Float(x) => Ok(*x),
-            ControlF32::Expr(e) => e
-                .eval_float_with_context(ctx)
-                .map(|x| x as f32)
-                .or_else(|_| e.eval_int_with_context(ctx).map(|b| b as f32))
-                .or_else(|_| {
-                    e.eval_boolean_with_context(ctx)
-                        .map(|b| if b { 1.0 } else { -1.0 })
-                        .map_err(|err| LivecodeError::EvalExpr("evalexpr err".to_string(), err))
-                }),
+            ControlF32::Expr(e) => {
+                if let Some(result) = w.eval_compiled(e, |c, ctx| c.eval_f32(ctx)) {
+                    return result;
+                }
+
+                let a = w.ctx()?;
+                let ctx = a.as_ref();
+                e.eval_float_with_context(ctx)
+                    .map(|x| x as f32)
+                    .or_else(|_| e.eval_int_with_context(ctx).map(|b| b as f32))
+                    .or_else(|_| {
+                        e.eval_boolean_with_context(ctx)
+                            .map(|b| if b { 1.0 } else { -1.0 })
+                            .map_err(|err| e.to_err(err))
+                    })
+            }
         }
     }
 }
@@ -561,7 +717,7 @@ impl Default for ControlBool {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ControlBool {
@@ -569,11 +725,11 @@ pub enum ControlBool {
     Int(i32),
     Float(f32),
     #[cfg_attr(feature = "schemars", schemars(with = "String"))]
-    Expr(Node),
+    Expr(LivecodeExpr),
 }
 impl ControlBool {
     pub fn force_from_str(s: &str) -> ControlBool {
-        match build_operator_tree(s) {
+        match LivecodeExpr::new(s) {
             Ok(e) => Self::Expr(e),
             Err(err) => {
                 println!("{:?}", err);
@@ -583,22 +739,26 @@ impl ControlBool {
     }
 
     pub fn o(&self, w: &LivecodeWorldState) -> LivecodeResult<bool> {
-        let a = w.ctx()?;
-        let ctx = a.as_ref();
-
         match self {
             ControlBool::Raw(b) => Ok(*b),
             ControlBool::Int(i) => Ok(*i > 0),
             ControlBool::Float(x) => Ok(*x > 0.0),
 
-            ControlBool::Expr(e) => e
-                .eval_boolean_with_context(ctx)
-                .or_else(|_| e.eval_float_with_context(ctx).map(|b| b > 0.0))
-                .or_else(|_| {
-                    e.eval_int_with_context(ctx)
-                        .map(|b| b > 0)
-                        .map_err(|err| LivecodeError::EvalExpr("evalexpr err".to_string(), err))
-                }),
+            ControlBool::Expr(e) => {
+                if let Some(result) = w.eval_compiled(e, |c, ctx| c.eval_bool(ctx)) {
+                    return result;
+                }
+
+                let a = w.ctx()?;
+                let ctx = a.as_ref();
+                e.eval_boolean_with_context(ctx)
+                    .or_else(|_| e.eval_float_with_context(ctx).map(|b| b > 0.0))
+                    .or_else(|_| {
+                        e.eval_int_with_context(ctx)
+                            .map(|b| b > 0)
+                            .map_err(|err| e.to_err(err))
+                    })
+            }
         }
     }
 
@@ -0,0 +1,175 @@
+// noise functions for expressions, using murrelet_common's MurreletNoise.
+//
+// they all use the app's seed (LiveCoder sets it every frame), so the same
+// config and seed gives the same render.
+//
+//   simplex(x, y), simplex(x, y, z), simplex(x, y, z, t)     -1 to 1
+//   fbm(x, y, z, octaves), fbm(x, y, z, octaves, lacunarity, gain)   -1 to 1
+//   ridged(x, y, z, octaves), ridged(x, y, z, octaves, lacunarity, gain)   0 to 1
+//   worley(x, y), worley(x, y, z)    distance to the closest point, about 0 to 1
+//   curlx(x, y, t), curly(x, y, t)   the two parts of a curl noise flow field
+use std::sync::{Arc, RwLock};
+
+use evalexpr::{
+    ContextWithMutableFunctions, EvalexprError, EvalexprResult, Function, HashMapContext, Value,
+};
+use glam::{Vec3, vec2};
+use murrelet_common::MurreletNoise;
+
+use crate::types::{LivecodeError, LivecodeResult};
+
+pub const NOISE_FUNCTIONS: &[&str] = &["simplex", "fbm", "ridged", "worley", "curlx", "curly"];
+
+fn numbers(argument: &Value) -> EvalexprResult<Vec<f32>> {
+    match argument {
+        Value::Tuple(t) => t.iter().map(|x| Ok(x.as_number()? as f32)).collect(),
+        v => Ok(vec![v.as_number()? as f32]),
+    }
+}
+
+fn wrong_amount(lo: usize, hi: usize, actual: usize) -> EvalexprError {
+    EvalexprError::WrongFunctionArgumentAmount {
+        expected: lo..=hi,
+        actual,
+    }
+}
+
+// fbm and ridged default to doubling the frequency and halving the amplitude
+fn octave_args(a: &[f32]) -> EvalexprResult<(Vec3, usize, f32, f32)> {
+    match a {
+        [x, y, z, octaves] => Ok((Vec3::new(*x, *y, *z), *octaves as usize, 2.0, 0.5)),
+        [x, y, z, octaves, lacunarity, gain] => {
+            Ok((Vec3::new(*x, *y, *z), *octaves as usize, *lacunarity, *gain))
+        }
+        _ => Err(wrong_amount(4, 6, a.len())),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NoiseFuncs(Arc<RwLock<MurreletNoise>>);
+impl Default for NoiseFuncs {
+    fn default() -> Self {
+        Self(Arc::new(RwLock::new(MurreletNoise::new(0))))
+    }
+}
+impl NoiseFuncs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // only rebuilds the noise when the seed changes
+    pub fn set_seed(&self, seed: f32) {
+        let noise = MurreletNoise::from_seed_f32(seed);
+        if self.0.read().unwrap().seed() != noise.seed() {
+            *self.0.write().unwrap() = noise;
+        }
+    }
+
+    fn add<F>(&self, ctx: &mut HashMapContext, name: &str, f: F) -> LivecodeResult<()>
+    where
+        F: Fn(&MurreletNoise, &[f32]) -> EvalexprResult<f32> + Send + Sync + Clone + 'static,
+    {
+        // the lint goes by the list, so it needs to be on there
+        debug_assert!(
+            NOISE_FUNCTIONS.contains(&name),
+            "{} isn't in NOISE_FUNCTIONS",
+            name
+        );
+        let noise = self.0.clone();
+        ctx.set_function(
+            name.to_owned(),
+            Function::new(move |argument| {
+                let args = numbers(argument)?;
+                let n = noise.read().unwrap();
+                Ok(Value::Float(f(&n, &args)? as f64))
+            }),
+        )
+        .map_err(|err| LivecodeError::EvalExpr(format!("error adding function `{}`", name), err))
+    }
+
+    pub fn register(&self, ctx: &mut HashMapContext) -> LivecodeResult<()> {
+        self.add(ctx, "simplex", |n, a| match a {
+            [x, y] => Ok(n.simplex2(vec2(*x, *y))),
+            [x, y, z] => Ok(n.simplex3(Vec3::new(*x, *y, *z))),
+            [x, y, z, w] => Ok(n.simplex4(Vec3::new(*x, *y, *z), *w)),
+            _ => Err(wrong_amount(2, 4, a.len())),
+        })?;
+
+        self.add(ctx, "fbm", |n, a| {
+            let (p, octaves, lacunarity, gain) = octave_args(a)?;
+            Ok(n.fbm(p, octaves, lacunarity, gain))
+        })?;
+
+        self.add(ctx, "ridged", |n, a| {
+            let (p, octaves, lacunarity, gain) = octave_args(a)?;
+            Ok(n.ridged(p, octaves, lacunarity, gain))
+        })?;
+
+        self.add(ctx, "worley", |n, a| match a {
+            [x, y] => Ok(n.worley2(vec2(*x, *y))),
+            [x, y, z] => Ok(n.worley3(Vec3::new(*x, *y, *z))),
+            _ => Err(wrong_amount(2, 3, a.len())),
+        })?;
+
+        self.add(ctx, "curlx", |n, a| match a {
+            [x, y, t] => Ok(n.curl2(vec2(*x, *y), *t).x),
+            _ => Err(wrong_amount(3, 3, a.len())),
+        })?;
+
+        self.add(ctx, "curly", |n, a| match a {
+            [x, y, t] => Ok(n.curl2(vec2(*x, *y), *t).y),
+            _ => Err(wrong_amount(3, 3, a.len())),
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evalexpr::build_operator_tree;
+
+    use super::*;
+
+    #[test]
+    fn noise_funcs_follow_the_seed() {
+        let funcs = NoiseFuncs::new();
+        let mut ctx = HashMapContext::new();
+        funcs.register(&mut ctx).unwrap();
+
+        let eval = |s: &str| {
+            build_operator_tree(s)
+                .unwrap()
+                .eval_number_with_context(&ctx)
+                .unwrap()
+        };
+        let exprs = [
+            "simplex(0.3, 1.7)",
+            "simplex(0.3, 1.7, 2.2, 0.5)",
+            "fbm(0.3, 1.7, 2.2, 4)",
+            "ridged(0.3, 1.7, 2.2, 3, 2.0, 0.5)",
+            "worley(0.3, 1.7)",
+            "curlx(0.3, 1.7, 0.5)",
+        ];
+
+        funcs.set_seed(1.0);
+        let first = exprs.map(eval);
+        assert_eq!(first, exprs.map(eval));
+
+        funcs.set_seed(2.0);
+        let second = exprs.map(eval);
+        assert!(first.iter().zip(&second).all(|(a, b)| a != b));
+
+        funcs.set_seed(1.0);
+        assert_eq!(first, exprs.map(eval));
+
+        assert!((-1.0..=1.0).contains(&first[2]));
+        assert!((0.0..=1.0).contains(&first[3]));
+        assert!(
+            build_operator_tree("simplex(1.0)")
+                .unwrap()
+                .eval_with_context(&ctx)
+                .is_err()
+        );
+    }
+}
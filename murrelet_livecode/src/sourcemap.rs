@@ -0,0 +1,401 @@
+// keeps track of where expressions came from in the yaml, so errors can say
+// "config.yaml:12:8" instead of making you hunt for it.
+//
+// serde_yaml doesn't give us spans, so instead we index the scalars in the
+// (preprocessed) text and match them up with the expressions as they're
+// deserialized. serde goes through the document in order, so each expression
+// is the next copy of its text after the last one we found. that way the same
+// template included twice, or `t` showing up all over, still works.
+//
+// layered configs are merged before they're parsed, so each file gets its
+// own cursor. the merged doc follows the first file's order, so that one's
+// tried first, then the files layered on top. an overlay's expression that
+// also shows up later in the base can still get the base's location.
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLoc {
+    pub file: String,
+    pub line: usize, // 1-indexed
+    pub col: usize,  // 1-indexed
+}
+impl fmt::Display for SourceLoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SourceLine {
+    file: String,
+    line: usize,
+    col_offset: usize, // templates get indented when they're included
+}
+
+// maps each line of the preprocessed yaml back to the file it came from
+#[derive(Debug, Clone, Default)]
+pub struct YamlSourceMap {
+    lines: Vec<SourceLine>,
+}
+impl YamlSourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // for text that wasn't preprocessed, every line maps to itself
+    pub fn new_identity(file: &str, text: &str) -> Self {
+        let mut m = Self::new();
+        for i in 0..text.lines().count() {
+            m.push_line(file, i + 1, 0);
+        }
+        m
+    }
+
+    pub fn push_line(&mut self, file: &str, line: usize, col_offset: usize) {
+        self.lines.push(SourceLine {
+            file: file.to_owned(),
+            line,
+            col_offset,
+        })
+    }
+
+    pub fn extend(&mut self, other: &YamlSourceMap, col_offset: usize) {
+        for l in &other.lines {
+            self.lines.push(SourceLine {
+                file: l.file.clone(),
+                line: l.line,
+                col_offset: l.col_offset + col_offset,
+            });
+        }
+    }
+
+    // line and col in the preprocessed text, 1-indexed
+    pub fn to_source(&self, line: usize, col: usize) -> SourceLoc {
+        match line.checked_sub(1).and_then(|i| self.lines.get(i)) {
+            Some(l) => SourceLoc {
+                file: l.file.clone(),
+                line: l.line,
+                col: col.saturating_sub(l.col_offset).max(1),
+            },
+            None => SourceLoc {
+                file: "".to_owned(),
+                line,
+                col,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ScalarLoc {
+    value: String,
+    line: usize,
+    col: usize,
+}
+
+// the scalars in one of the files, and how far through them we are
+#[derive(Debug, Clone)]
+struct IndexedSource {
+    scalars: Vec<(String, SourceLoc)>,
+    next: Cell<usize>, // scalars before this have been used
+}
+impl IndexedSource {
+    fn new(text: &str, source_map: &YamlSourceMap) -> Self {
+        let scalars = index_yaml_scalars(text)
+            .into_iter()
+            .map(|s| (s.value, source_map.to_source(s.line, s.col)))
+            .collect();
+        Self {
+            scalars,
+            next: Cell::new(0),
+        }
+    }
+
+    fn next_match(&self, src: &str) -> Option<usize> {
+        let next = self.next.get();
+        let i = self.scalars[next..].iter().position(|s| s.0 == src)?;
+        Some(next + i)
+    }
+
+    fn take(&self, idx: usize) -> SourceLoc {
+        self.next.set(idx + 1);
+        self.scalars[idx].1.clone()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ExprLocator {
+    sources: Vec<IndexedSource>,
+    source_map: Option<YamlSourceMap>, // for serde's errors, if it's parsing the indexed text
+}
+impl ExprLocator {
+    fn new(sources: &[(String, YamlSourceMap)], source_map: Option<YamlSourceMap>) -> Self {
+        Self {
+            sources: sources
+                .iter()
+                .map(|(text, map)| IndexedSource::new(text, map))
+                .collect(),
+            source_map,
+        }
+    }
+
+    fn locate(&self, src: &str) -> Option<SourceLoc> {
+        let src = src.trim();
+
+        // the first file is the base, so look there first
+        for source in &self.sources {
+            if let Some(idx) = source.next_match(src) {
+                return Some(source.take(idx));
+            }
+        }
+
+        // the scan went wrong somewhere (or serde went back, like an
+        // untagged enum trying again), so only trust it if there's one
+        let mut matches = self.sources.iter().flat_map(|source| {
+            (0..source.scalars.len())
+                .filter(|&i| source.scalars[i].0 == src)
+                .map(move |i| (source, i))
+        });
+        let (source, idx) = matches.next()?;
+        if matches.next().is_some() {
+            return None;
+        }
+        Some(source.take(idx))
+    }
+}
+
+thread_local! {
+    static LOCATOR: RefCell<Option<ExprLocator>> = const { RefCell::new(None) };
+}
+
+// while `f` runs (usually serde_yaml::from_str), expressions that are
+// deserialized will look up their location in `text`
+pub fn with_yaml_source<T, F>(text: &str, source_map: YamlSourceMap, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let sources = [(text.to_owned(), source_map.clone())];
+    with_locator(ExprLocator::new(&sources, Some(source_map)), f)
+}
+
+// same, but `text` was made by merging `sources` (each one's preprocessed
+// text and source map), like layered configs. serde's errors are only
+// mapped back if `text` is one of them as it is
+pub fn with_merged_yaml_source<T, F>(text: &str, sources: &[(String, YamlSourceMap)], f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let source_map = match sources {
+        [(only, map)] if only == text => Some(map.clone()),
+        _ => None,
+    };
+    with_locator(ExprLocator::new(sources, source_map), f)
+}
+
+fn with_locator<T, F>(locator: ExprLocator, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let prev = LOCATOR.with(|l| l.replace(Some(locator)));
+    let result = f();
+    LOCATOR.with(|l| *l.borrow_mut() = prev);
+    result
+}
+
+pub(crate) fn locate_expr(src: &str) -> Option<SourceLoc> {
+    LOCATOR.with(|l| l.borrow().as_ref().and_then(|loc| loc.locate(src)))
+}
+
+// for serde's error locations, which are in terms of the preprocessed text
+pub fn yaml_loc_string(line: usize, col: usize) -> String {
+    LOCATOR.with(
+        |l| match l.borrow().as_ref().and_then(|loc| loc.source_map.as_ref()) {
+            Some(source_map) => source_map.to_source(line, col).to_string(),
+            None => format!("{},{}", line, col),
+        },
+    )
+}
+
+// this is far from a yaml parser, it just finds the things that look like
+// values: `key: value`, `- value` and `[a, b, c]`
+fn index_yaml_scalars(text: &str) -> Vec<ScalarLoc> {
+    let mut scalars = vec![];
+
+    for (line_idx, full_line) in text.lines().enumerate() {
+        let line = strip_comment(full_line);
+
+        let mut col = line.len() - line.trim_start().len();
+        let mut rest = line.trim_start();
+
+        // list items
+        while let Some(r) = rest.strip_prefix("- ") {
+            let r_trimmed = r.trim_start();
+            col += rest.len() - r_trimmed.len();
+            rest = r_trimmed;
+        }
+        if rest == "-" {
+            continue;
+        }
+
+        // keys
+        if let Some(key_end) = find_key_end(rest) {
+            let after = &rest[key_end + 1..];
+            let after_trimmed = after.trim_start();
+            col += key_end + 1 + (after.len() - after_trimmed.len());
+            rest = after_trimmed;
+        }
+
+        let rest = rest.trim_end();
+        if rest.is_empty() || rest == "|" || rest == ">" {
+            continue;
+        }
+
+        if rest.starts_with('[') && rest.ends_with(']') {
+            for (item_col, item) in split_flow_seq(&rest[1..rest.len() - 1]) {
+                push_scalar(&mut scalars, item, line_idx + 1, col + 1 + item_col);
+            }
+        } else {
+            push_scalar(&mut scalars, rest, line_idx + 1, col);
+        }
+    }
+
+    scalars
+}
+
+fn push_scalar(scalars: &mut Vec<ScalarLoc>, raw: &str, line: usize, col: usize) {
+    let (value, col) = match raw.chars().next() {
+        Some(q @ ('"' | '\'')) if raw.len() > 1 && raw.ends_with(q) => {
+            (&raw[1..raw.len() - 1], col + 1)
+        }
+        _ => (raw, col),
+    };
+
+    scalars.push(ScalarLoc {
+        value: value.trim().to_owned(),
+        line,
+        col: col + 1, // 1-indexed
+    });
+}
+
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    match line.find(" #") {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+// `key: value` or `key:`, as long as the key looks like a key
+fn find_key_end(s: &str) -> Option<usize> {
+    let idx = s.find(':')?;
+    let key = &s[..idx];
+    let is_key = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '"' || c == '\'');
+    let followed_by_space = s[idx + 1..].is_empty() || s[idx + 1..].starts_with(' ');
+    if is_key && followed_by_space {
+        Some(idx)
+    } else {
+        None
+    }
+}
+
+// splits "a, sin(t, 2), b" on the top-level commas, with the offset of each item
+fn split_flow_seq(s: &str) -> Vec<(usize, &str)> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push((start, &s[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push((start, &s[start..]));
+
+    items
+        .into_iter()
+        .map(|(offset, item)| {
+            let trimmed = item.trim_start();
+            (offset + item.len() - trimmed.len(), trimmed.trim_end())
+        })
+        .filter(|(_, item)| !item.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_exprs_in_order() {
+        let text = "app:\n  a: t\n  b: t * 2.0\nthing:\n  - t\n  - [0.0, sin(t, 1.0)]\n";
+        let map = YamlSourceMap::new_identity("config.yaml", text);
+
+        // in the order serde would ask
+        with_yaml_source(text, map.clone(), || {
+            assert_eq!(locate_expr("t").unwrap().to_string(), "config.yaml:2:6");
+            assert_eq!(
+                locate_expr("t * 2.0").unwrap().to_string(),
+                "config.yaml:3:6"
+            );
+            assert_eq!(locate_expr("t").unwrap().to_string(), "config.yaml:5:5");
+            assert_eq!(
+                locate_expr("sin(t, 1.0)").unwrap().to_string(),
+                "config.yaml:6:11"
+            );
+            // past the last one, it could be either, so don't guess
+            assert!(locate_expr("t").is_none());
+        });
+
+        // out of order, only the unique ones are trusted
+        with_yaml_source(text, map, || {
+            assert!(locate_expr("sin(t, 1.0)").is_some());
+            assert_eq!(
+                locate_expr("t * 2.0").unwrap().to_string(),
+                "config.yaml:3:6"
+            );
+        });
+
+        assert!(locate_expr("t").is_none());
+    }
+
+    #[test]
+    fn locates_exprs_in_merged_files() {
+        let base = "a: t\nb: t * 2.0\nc: t\n";
+        let overlay = "b: m3\nd: t\n";
+        let sources = [
+            (
+                base.to_owned(),
+                YamlSourceMap::new_identity("base.yaml", base),
+            ),
+            (
+                overlay.to_owned(),
+                YamlSourceMap::new_identity("overlay.yaml", overlay),
+            ),
+        ];
+
+        // what the merged doc would ask for, base order with the overlay's
+        // new keys at the end
+        with_merged_yaml_source("a: t\nb: m3\nc: t\nd: t\n", &sources, || {
+            let loc = |src: &str| locate_expr(src).unwrap().to_string();
+            assert_eq!(loc("t"), "base.yaml:1:4");
+            assert_eq!(loc("m3"), "overlay.yaml:1:4");
+            assert_eq!(loc("t"), "base.yaml:3:4");
+            assert_eq!(loc("t"), "overlay.yaml:2:4");
+            // serde's line numbers are in the merged doc, so they stay as they are
+            assert_eq!(yaml_loc_string(2, 4), "2,4");
+        });
+    }
+}
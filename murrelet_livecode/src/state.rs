@@ -1,16 +1,20 @@
 use std::{
     collections::HashSet,
-    sync::{Arc, RwLock},
+    sync::{Arc, OnceLock, RwLock},
 };
 
 use evalexpr::{Context, EvalexprResult, HashMapContext, IterateVariablesContext, Value};
+use itertools::Itertools;
 use murrelet_common::*;
 
 use crate::{
+    compiled::CompiledExpr,
     expr::{
         ExprWorldContextValues, IntoExprWorldContext, MixedEvalDefs, MixedEvalDefsRef,
         init_evalexpr_func_ctx,
     },
+    livecode::LivecodeExpr,
+    stateful::{ITEM_VAR, item_key},
     types::{AdditionalContextNode, LivecodeResult},
     unitcells::UnitCellContext,
 };
@@ -74,15 +78,19 @@ enum CacheResult {
 #[derive(Clone, Debug)]
 pub struct LivecodeWorldState {
     cached: Arc<RwLock<CachedHM>>,
+    layered: Arc<OnceLock<Arc<WorldWithLocalVariables>>>, // for compiled exprs
     state: Arc<LivecodeWorldStateInner>,
     refs: Vec<MixedEvalDefsRef>,
+    item: i64, // which repeat/unitcell item this is, see push_item
 }
 impl LivecodeWorldState {
     pub fn new_legacy(state: LivecodeWorldStateInner) -> LivecodeResult<Self> {
         Ok(Self {
             cached: CachedHM::new_rw(),
+            layered: Arc::new(OnceLock::new()),
             state: Arc::new(state),
             refs: vec![],
+            item: 0,
         })
     }
 
@@ -93,8 +101,26 @@ impl LivecodeWorldState {
         node: AdditionalContextNode,
         assets: AssetsRef,
     ) -> LivecodeResult<Self> {
-        let state =
-            LivecodeWorldStateInner::new(evalexpr_func_ctx, livecode_src, time, node, assets)?;
+        Self::new_with_vals(evalexpr_func_ctx, livecode_src, time, node, assets, vec![])
+    }
+
+    // more_vals are things computed outside, like envelopes
+    pub fn new_with_vals(
+        evalexpr_func_ctx: &HashMapContext,
+        livecode_src: &LivecodeSrc,
+        time: LiveCodeTimeInstantInfo,
+        node: AdditionalContextNode,
+        assets: AssetsRef,
+        more_vals: Vec<(String, LivecodeValue)>,
+    ) -> LivecodeResult<Self> {
+        let state = LivecodeWorldStateInner::new_with_vals(
+            evalexpr_func_ctx,
+            livecode_src,
+            time,
+            node,
+            assets,
+            more_vals,
+        )?;
 
         Self::new_legacy(state)
     }
@@ -116,11 +142,33 @@ impl LivecodeWorldState {
 
         Self {
             cached: CachedHM::new_rw(),
+            layered: Arc::new(OnceLock::new()),
             state: self.state.clone(),
             refs,
+            item: self.item,
         }
     }
 
+    // same as clone_with_vals, for the `idx`th item of a repeat
+    pub fn clone_to_item(&self, expr: ExprWorldContextValues, prefix: &str, idx: u64) -> Self {
+        let mut w = self.clone_with_vals(expr, prefix);
+        w.push_item(prefix, idx);
+        w
+    }
+
+    // gives each item its own key, made from the one it's inside of, so
+    // stateful functions like lag keep separate state for each one
+    fn push_item(&mut self, prefix: &str, idx: u64) {
+        self.item = item_key(self.item, prefix, idx);
+        self.refs
+            .push(MixedEvalDefsRef::new(MixedEvalDefs::new_from_expr(
+                ExprWorldContextValues::new(vec![(
+                    ITEM_VAR.to_owned(),
+                    LivecodeValue::Int(self.item),
+                )]),
+            )));
+    }
+
     pub fn clone_to_unitcell(
         &self,
         unit_cell_ctx: &UnitCellContext,
@@ -135,15 +183,19 @@ impl LivecodeWorldState {
         refs.push(MixedEvalDefsRef::new(MixedEvalDefs::new_from_expr(
             new_info,
         )));
-        if let Some(node) = maybe_node {
-            refs.push(node.clone());
-        }
 
-        Ok(Self {
+        let mut w = Self {
             cached: CachedHM::new_rw(),
+            layered: Arc::new(OnceLock::new()),
             state: self.state.clone(),
             refs,
-        })
+            item: self.item,
+        };
+        w.push_item(prefix, unit_cell_ctx.item_idx());
+        if let Some(node) = maybe_node {
+            w.refs.push(node.clone());
+        }
+        Ok(w)
     }
 
     pub(crate) fn new_dummy() -> Self {
@@ -165,6 +217,66 @@ impl LivecodeWorldState {
         Ok(arc)
     }
 
+    // use compiled expressions when evaluating, see LiveCodeUtil
+    pub fn with_compiled_exprs(mut self, enabled: bool) -> Self {
+        Arc::make_mut(&mut self.state).compiled_exprs = enabled;
+        self
+    }
+
+    pub fn compiled_exprs(&self) -> bool {
+        self.state.compiled_exprs
+    }
+
+    // compiled exprs don't need their own HashMapContext, so when everything
+    // added on top is just values, skip cloning the world's ctx
+    fn layered_ctx(&self) -> LivecodeResult<Arc<WorldWithLocalVariables>> {
+        if let Some(c) = self.layered.get() {
+            return Ok(c.clone());
+        }
+
+        let ctx = if self.refs.iter().any(|x| x.as_defs().has_nodes()) {
+            // nodes can do anything to the ctx, so build it the usual way
+            WorldWithLocalVariables {
+                base: self.ctx()?,
+                locals: vec![],
+                builtins_disabled: false,
+                compiled_exprs: true,
+            }
+        } else {
+            // later ones win, and locals are checked in order
+            let locals = self
+                .refs
+                .iter()
+                .rev()
+                .flat_map(|x| x.expr_vals().to_vals())
+                .collect_vec();
+            WorldWithLocalVariables {
+                base: self.state.context.clone(),
+                locals,
+                builtins_disabled: false,
+                compiled_exprs: true,
+            }
+        };
+
+        Ok(self.layered.get_or_init(|| Arc::new(ctx)).clone())
+    }
+
+    // None if compiled exprs are off or this one didn't compile
+    pub(crate) fn eval_compiled<T>(
+        &self,
+        e: &LivecodeExpr,
+        f: impl FnOnce(&CompiledExpr, &WorldWithLocalVariables) -> EvalexprResult<T>,
+    ) -> Option<LivecodeResult<T>> {
+        if !self.compiled_exprs() {
+            return None;
+        }
+        let c = e.compiled()?;
+        Some(
+            self.layered_ctx()
+                .and_then(|ctx| f(c, &ctx).map_err(|err| e.to_err(err))),
+        )
+    }
+
     pub fn actual_frame_u64(&self) -> u64 {
         self.state.actual_frame_u64()
     }
@@ -173,9 +285,28 @@ impl LivecodeWorldState {
         self.state.vars()
     }
 
+    // the numbers and bools in the world, sorted by name
+    pub fn values(&self) -> Vec<(String, LivecodeValue)> {
+        let ctx = self.to_local();
+        self.vars()
+            .into_iter()
+            .sorted()
+            .filter_map(|name| {
+                let v = match ctx.get_value(&name)? {
+                    Value::Float(f) => LivecodeValue::Float(*f),
+                    Value::Int(i) => LivecodeValue::Int(*i),
+                    Value::Boolean(b) => LivecodeValue::Bool(*b),
+                    _ => return None,
+                };
+                Some((name, v))
+            })
+            .collect()
+    }
+
     pub fn update_with_defs(&mut self, md: MixedEvalDefsRef) {
         self.refs.push(md);
         self.cached.write().unwrap().clear();
+        self.layered = Arc::new(OnceLock::new());
     }
 
     pub fn time(&self) -> LiveCodeTimeInstantInfo {
@@ -204,6 +335,7 @@ impl LivecodeWorldState {
             base: self.ctx().unwrap(),
             locals,
             builtins_disabled: false,
+            compiled_exprs: self.compiled_exprs(),
         }
     }
 
@@ -212,6 +344,7 @@ impl LivecodeWorldState {
             base: self.ctx().unwrap(),
             locals: vec![],
             builtins_disabled: false,
+            compiled_exprs: self.compiled_exprs(),
         }
     }
 }
@@ -222,6 +355,7 @@ pub struct WorldWithLocalVariables {
     base: Arc<HashMapContext>,
     locals: Vec<(String, Value)>,
     builtins_disabled: bool,
+    compiled_exprs: bool,
 }
 impl WorldWithLocalVariables {
     pub fn update_with_simple_defs(&mut self, more_vals: &ExprWorldContextValues) {
@@ -230,6 +364,10 @@ impl WorldWithLocalVariables {
         self.locals = locals;
     }
 
+    pub(crate) fn compiled_exprs(&self) -> bool {
+        self.compiled_exprs
+    }
+
     pub(crate) fn variable_names(&self) -> Vec<String> {
         let mut names: Vec<String> = self.locals.iter().map(|(k, _)| k.clone()).collect();
         names.extend(self.base.iter_variable_names());
@@ -261,9 +399,10 @@ impl Context for WorldWithLocalVariables {
 
 #[derive(Debug, Clone)]
 pub struct LivecodeWorldStateInner {
-    context: HashMapContext,
+    context: Arc<HashMapContext>,
     stage: LivecodeWorldStateStage,
     assets: AssetsRef,
+    compiled_exprs: bool,
 }
 impl LivecodeWorldStateInner {
     pub fn vars(&self) -> HashSet<String> {
@@ -275,6 +414,7 @@ impl LivecodeWorldStateInner {
         livecode_src: &LivecodeSrc,
         maybe_time: Option<LiveCodeTimeInstantInfo>,
         maybe_node: Option<AdditionalContextNode>,
+        more_vals: Vec<(String, LivecodeValue)>,
     ) -> LivecodeResult<HashMapContext> {
         let mut ctx = evalexpr_func_ctx.clone();
 
@@ -282,6 +422,8 @@ impl LivecodeWorldStateInner {
         if let Some(time) = maybe_time {
             w.extend(time.to_exec_funcs());
         }
+        // things computed outside, like envelopes
+        w.extend(more_vals);
         // add the world to the ctx
         let vals = ExprWorldContextValues::new(w);
         vals.update_ctx(&mut ctx)?;
@@ -301,13 +443,30 @@ impl LivecodeWorldStateInner {
         node: AdditionalContextNode,
         assets: AssetsRef,
     ) -> LivecodeResult<Self> {
-        let context =
-            Self::clone_ctx_and_add_world(evalexpr_func_ctx, livecode_src, Some(time), Some(node))?;
+        Self::new_with_vals(evalexpr_func_ctx, livecode_src, time, node, assets, vec![])
+    }
+
+    pub fn new_with_vals(
+        evalexpr_func_ctx: &HashMapContext,
+        livecode_src: &LivecodeSrc,
+        time: LiveCodeTimeInstantInfo,
+        node: AdditionalContextNode,
+        assets: AssetsRef,
+        more_vals: Vec<(String, LivecodeValue)>,
+    ) -> LivecodeResult<Self> {
+        let context = Self::clone_ctx_and_add_world(
+            evalexpr_func_ctx,
+            livecode_src,
+            Some(time),
+            Some(node),
+            more_vals,
+        )?;
 
         Ok(Self {
-            context,
+            context: Arc::new(context),
             stage: LivecodeWorldStateStage::World(time),
             assets: assets.clone(),
+            compiled_exprs: false,
         })
     }
 
@@ -315,12 +474,14 @@ impl LivecodeWorldStateInner {
         evalexpr_func_ctx: &HashMapContext,
         livecode_src: &LivecodeSrc,
     ) -> LivecodeResult<Self> {
-        let context = Self::clone_ctx_and_add_world(evalexpr_func_ctx, livecode_src, None, None)?;
+        let context =
+            Self::clone_ctx_and_add_world(evalexpr_func_ctx, livecode_src, None, None, vec![])?;
 
         Ok(Self {
-            context,
+            context: Arc::new(context),
             stage: LivecodeWorldStateStage::Timeless,
             assets: Assets::empty_ref(),
+            compiled_exprs: false,
         })
     }
 
@@ -355,7 +516,7 @@ impl LivecodeWorldStateInner {
     }
 
     pub(crate) fn ctx_mut(&mut self) -> &mut HashMapContext {
-        &mut self.context
+        Arc::make_mut(&mut self.context)
     }
 
     pub fn update_with_defs(&mut self, more_defs: &MixedEvalDefs) -> LivecodeResult<()> {
@@ -479,18 +640,28 @@ impl LiveCodeTimeInstantInfo {
         self.timing_config.seconds_from_config(self.system_timing)
     }
 
-    pub fn is_on_bar(&self) -> bool {
+    // the beat at the last frame
+    fn prev_beat(&self) -> f32 {
         // okay so, we want to know the prev beat.
         let prev_time = if self.timing_config.realtime {
             let render_time = self.system_timing.last_render_time;
             render_time.as_secs_f32()
         } else {
-            let prev_frame = self.system_timing.frame - 1;
+            // frame 0 doesn't have one, so it's the same frame
+            let prev_frame = self.system_timing.frame.saturating_sub(1);
             prev_frame as f32 / self.timing_config.fps
         };
 
+        self.timing_config.seconds_to_beats(prev_time)
+    }
+
+    pub fn is_on_beat(&self) -> bool {
+        self.beat().floor() as i32 > self.prev_beat().floor() as i32
+    }
+
+    pub fn is_on_bar(&self) -> bool {
         // check if this beat rounds differently than the curr one
-        let prev_beat = self.timing_config.seconds_to_beats(prev_time);
+        let prev_beat = self.prev_beat();
 
         let curr_beat_bar = self.bar().floor();
         let prev_beat_bar = (prev_beat / self.timing_config.beats_per_bar).floor();
@@ -532,7 +703,7 @@ impl LiveCodeTimeInstantInfo {
         }
     }
 
-    fn new_dummy() -> LiveCodeTimeInstantInfo {
+    pub(crate) fn new_dummy() -> LiveCodeTimeInstantInfo {
         LiveCodeTimeInstantInfo {
             timing_config: LivecodeTimingConfig {
                 bpm: 120.0,
@@ -628,15 +799,18 @@ impl Default for LiveCodeTiming {
 
 impl LiveCodeTiming {
     pub fn new() -> LiveCodeTiming {
+        // all the same time, otherwise prev_render_time can come after
+        // last_render_time and subtracting them overflows
+        let now = MurreletTime::now();
         LiveCodeTiming {
-            start: MurreletTime::now(),
+            start: now,
             frame: 0,
             start_frame: 0,
-            true_start: MurreletTime::now(),
-            last_config_update: MurreletTime::now(),
+            true_start: now,
+            last_config_update: now,
             last_config_update_frame: 0,
-            last_render_time: MurreletTime::now(),
-            prev_render_time: MurreletTime::now(),
+            last_render_time: now,
+            prev_render_time: now,
         }
     }
 
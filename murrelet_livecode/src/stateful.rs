@@ -0,0 +1,311 @@
+// functions that remember things between frames, like `lag(m3, 0.2)`.
+//
+// each call in the config gets its own state, for each repeat/unitcell item
+// it's in. when an expression is parsed, calls to these get two more
+// arguments, an id for that spot in the config and the `_item` variable,
+// which each item's world sets to its own key (see tag_call_sites and
+// LivecodeWorldState::push_item). reloading parses everything again, so it
+// starts over.
+//
+// state only moves forward once per frame, so evaluating the same thing a few
+// times in a frame gives the same answer. lazy fields are evaluated by the
+// sketch, so those share state across whatever the sketch evaluates them for.
+// dt comes from the LiveCoder, so it's 1/fps when not realtime.
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI64, Ordering},
+    },
+};
+
+use evalexpr::{
+    ContextWithMutableFunctions, ContextWithMutableVariables, EvalexprError, EvalexprResult,
+    Function, HashMapContext, Node, Operator, Value, build_operator_tree,
+};
+
+use crate::types::{LivecodeError, LivecodeResult};
+
+pub const STATEFUL_FUNCTIONS: &[&str] = &["lag", "slew", "sample_hold", "count"];
+
+// set in every world, 0 outside of repeats and unitcells
+pub const ITEM_VAR: &str = "_item";
+
+pub(crate) fn item_key(parent: i64, prefix: &str, idx: u64) -> i64 {
+    let mut hasher = DefaultHasher::new();
+    (parent, prefix, idx).hash(&mut hasher);
+    hasher.finish() as i64
+}
+
+static NEXT_CALL_SITE: AtomicI64 = AtomicI64::new(0);
+
+// adds the call site id and item to every call to a stateful function, so
+// `lag(m3, 0.2)` becomes `lag(m3, 0.2, 17, _item)`
+pub fn tag_call_sites(node: &mut Node) {
+    for child in node.children_mut() {
+        tag_call_sites(child);
+    }
+
+    let is_stateful = matches!(
+        node.operator(),
+        Operator::FunctionIdentifier { identifier } if STATEFUL_FUNCTIONS.contains(&identifier.as_str())
+    );
+    if !is_stateful {
+        return;
+    }
+
+    let site = NEXT_CALL_SITE.fetch_add(1, Ordering::Relaxed);
+    let site_nodes = [
+        first_child(build_operator_tree(&site.to_string()).unwrap()),
+        first_child(build_operator_tree(ITEM_VAR).unwrap()),
+    ];
+
+    // the argument is usually in parens, so look inside those
+    let mut arg = node.children()[0].clone();
+    if matches!(arg.operator(), Operator::RootNode) && arg.children().len() <= 1 {
+        arg = arg.children().first().cloned().unwrap_or(arg);
+    }
+
+    let mut args = match arg.operator() {
+        Operator::Tuple => arg.children().to_vec(),
+        Operator::RootNode => vec![], // nothing, `count()`
+        _ => vec![arg],
+    };
+    args.extend(site_nodes);
+
+    let mut tuple = first_child(build_operator_tree("0, 0").unwrap());
+    *tuple.children_mut() = args;
+    node.children_mut()[0] = tuple;
+}
+
+fn first_child(root: Node) -> Node {
+    root.children()[0].clone()
+}
+
+// pulls the call site and item back off
+fn split_site(argument: &Value) -> EvalexprResult<(Site, Vec<Value>)> {
+    match argument {
+        Value::Tuple(t) if t.len() >= 2 => {
+            let (site, args) = t.split_at(t.len() - 2);
+            Ok(((args[0].as_int()?, args[1].as_int()?), site.to_vec()))
+        }
+        _ => Err(EvalexprError::expected_tuple(argument.clone())),
+    }
+}
+
+fn expect_args(args: &[Value], n: usize) -> EvalexprResult<()> {
+    if args.len() == n {
+        Ok(())
+    } else {
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: n..=n,
+            actual: args.len(),
+        })
+    }
+}
+
+pub fn is_triggered(v: &Value) -> EvalexprResult<bool> {
+    match v {
+        Value::Boolean(b) => Ok(*b),
+        v => Ok(v.as_number()? > 0.0),
+    }
+}
+
+type Site = (i64, i64); // call site, item
+
+#[derive(Debug, Clone, Default)]
+struct CallSiteState {
+    last: Option<(u64, Value)>, // frame and what it returned
+    value: f64,
+    count: i64,
+    prev_trigger: bool,
+}
+
+#[derive(Debug, Default)]
+struct StatefulFuncsInner {
+    frame: u64,
+    dt: f64,
+    sites: HashMap<Site, CallSiteState>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StatefulFuncs(Arc<Mutex<StatefulFuncsInner>>);
+impl StatefulFuncs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // call once a frame, before evaluating anything
+    pub fn set_frame(&self, frame: u64, dt: f32) {
+        let mut inner = self.0.lock().unwrap();
+        inner.frame = frame;
+        inner.dt = dt as f64;
+    }
+
+    // the old call sites won't be used again after a reload
+    pub fn reset(&self) {
+        self.0.lock().unwrap().sites.clear();
+    }
+
+    // runs f at most once a frame for this call site and item. `first` is
+    // true the first time it's called
+    fn step<F>(&self, site: Site, f: F) -> Value
+    where
+        F: FnOnce(&mut CallSiteState, f64, bool) -> Value,
+    {
+        let mut inner = self.0.lock().unwrap();
+        let (frame, dt) = (inner.frame, inner.dt);
+        let state = inner.sites.entry(site).or_default();
+        match &state.last {
+            Some((last_frame, out)) if *last_frame == frame => out.clone(),
+            last => {
+                let first = last.is_none();
+                let out = f(state, dt, first);
+                state.last = Some((frame, out.clone()));
+                out
+            }
+        }
+    }
+
+    fn add<F>(&self, ctx: &mut HashMapContext, name: &str, f: F) -> LivecodeResult<()>
+    where
+        F: Fn(&StatefulFuncs, Site, &[Value]) -> EvalexprResult<Value>
+            + Send
+            + Sync
+            + Clone
+            + 'static,
+    {
+        // the lint goes by the list, so it needs to be on there
+        debug_assert!(
+            STATEFUL_FUNCTIONS.contains(&name),
+            "{} isn't in STATEFUL_FUNCTIONS",
+            name
+        );
+        let funcs = self.clone();
+        ctx.set_function(
+            name.to_owned(),
+            Function::new(move |argument| {
+                let (site, args) = split_site(argument)?;
+                f(&funcs, site, &args)
+            }),
+        )
+        .map_err(|err| LivecodeError::EvalExpr(format!("error adding function `{}`", name), err))
+    }
+
+    pub fn register(&self, ctx: &mut HashMapContext) -> LivecodeResult<()> {
+        ctx.set_value(ITEM_VAR.to_owned(), Value::Int(0))
+            .map_err(|err| LivecodeError::EvalExpr(format!("error adding `{}`", ITEM_VAR), err))?;
+
+        // lag(x, seconds), eases towards x, getting most of the way there in about `seconds`
+        self.add(ctx, "lag", |funcs, site, args| {
+            expect_args(args, 2)?;
+            let (x, seconds) = (args[0].as_number()?, args[1].as_number()?);
+            Ok(funcs.step(site, |s, dt, first| {
+                if first || seconds <= 0.0 {
+                    s.value = x;
+                } else {
+                    s.value += (x - s.value) * (1.0 - (-dt / seconds).exp());
+                }
+                Value::Float(s.value)
+            }))
+        })?;
+
+        // slew(x, rate), moves towards x by at most `rate` per second
+        self.add(ctx, "slew", |funcs, site, args| {
+            expect_args(args, 2)?;
+            let (x, rate) = (args[0].as_number()?, args[1].as_number()?);
+            Ok(funcs.step(site, |s, dt, first| {
+                if first || rate <= 0.0 {
+                    s.value = x;
+                } else {
+                    let max_step = rate * dt;
+                    s.value += (x - s.value).clamp(-max_step, max_step);
+                }
+                Value::Float(s.value)
+            }))
+        })?;
+
+        // sample_hold(x, trigger), grabs x when trigger goes from off to on
+        self.add(ctx, "sample_hold", |funcs, site, args| {
+            expect_args(args, 2)?;
+            let (x, trigger) = (args[0].as_number()?, is_triggered(&args[1])?);
+            Ok(funcs.step(site, |s, _dt, first| {
+                if first || (trigger && !s.prev_trigger) {
+                    s.value = x;
+                }
+                s.prev_trigger = trigger;
+                Value::Float(s.value)
+            }))
+        })?;
+
+        // count(trigger), how many times trigger has gone from off to on
+        self.add(ctx, "count", |funcs, site, args| {
+            expect_args(args, 1)?;
+            let trigger = is_triggered(&args[0])?;
+            Ok(funcs.step(site, |s, _dt, first| {
+                if !first && trigger && !s.prev_trigger {
+                    s.count += 1;
+                }
+                s.prev_trigger = trigger;
+                Value::Int(s.count)
+            }))
+        })?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stateful_funcs_step_once_per_frame() {
+        let funcs = StatefulFuncs::new();
+        let mut ctx = HashMapContext::new();
+        funcs.register(&mut ctx).unwrap();
+
+        let parse = |s: &str| {
+            let mut node = build_operator_tree(s).unwrap();
+            tag_call_sites(&mut node);
+            node
+        };
+        let slew = parse("slew(x, 2.0)");
+        let count = parse("count(k) + count(k)");
+        let hold = parse("sample_hold(x, k)");
+
+        let frames = [
+            (0.0, false),
+            (1.0, true),
+            (1.0, true),
+            (1.0, false),
+            (1.0, true),
+        ];
+        let mut results = vec![];
+        for (frame, (x, k)) in frames.into_iter().enumerate() {
+            funcs.set_frame(frame as u64, 0.25);
+            ctx.set_value("x".to_owned(), Value::Float(x)).unwrap();
+            ctx.set_value("k".to_owned(), Value::Boolean(k)).unwrap();
+
+            // evaluating twice in a frame doesn't move it along
+            slew.eval_with_context(&ctx).unwrap();
+            results.push((
+                slew.eval_number_with_context(&ctx).unwrap(),
+                count.eval_int_with_context(&ctx).unwrap(),
+                hold.eval_number_with_context(&ctx).unwrap(),
+            ));
+        }
+
+        assert_eq!(
+            results,
+            vec![
+                (0.0, 0, 0.0),
+                (0.5, 2, 1.0),
+                (1.0, 2, 1.0),
+                (1.0, 2, 1.0),
+                (1.0, 4, 1.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+// keyframed tracks, so you can choreograph things over bars instead of
+// writing `if(bar > 8, ...)` chains.
+//
+//   timeline:
+//     zoom:
+//       unit: bars          # or beats, seconds. defaults to bars
+//       keys:
+//         - { at: 0, value: 0.0, ease: cubic_in_out }
+//         - { at: 8, value: 1.0 }
+//         - { at: 16, value: 0.5, ease: hold }
+//         - { at: 20, value: 0.0 }
+//
+// `ease` is for the segment starting at that key: linear (the default), hold,
+// or any easing name like quad_out. before the first key it's the first value,
+// after the last it's the last value. the time comes from
+// LiveCodeTimeInstantInfo, so with realtime: false it goes by frame.
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use lerpable::{Lerpable, step};
+use murrelet_common::{Easing, LivecodeValue};
+use murrelet_gui::CanMakeGUI;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    livecode::{GetLivecodeIdentifiers, LivecodeFunction, LivecodeVariable},
+    nestedit::{NestEditable, NestedMod},
+    state::LiveCodeTimeInstantInfo,
+    types::{LivecodeError, LivecodeResult},
+};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineUnit {
+    #[default]
+    Bars,
+    Beats,
+    Seconds,
+}
+impl TimelineUnit {
+    fn now(&self, time: &LiveCodeTimeInstantInfo) -> f32 {
+        match self {
+            TimelineUnit::Bars => time.bar(),
+            TimelineUnit::Beats => time.beat(),
+            TimelineUnit::Seconds => time.seconds(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum TimelineEase {
+    #[default]
+    Linear,
+    Hold, // stay at this key's value until the next
+    Curve(Easing),
+}
+impl TimelineEase {
+    pub fn ease(&self, pct: f32) -> f32 {
+        match self {
+            TimelineEase::Linear => pct,
+            TimelineEase::Hold => 0.0,
+            TimelineEase::Curve(e) => e.ease(pct as f64) as f32,
+        }
+    }
+}
+
+impl Serialize for TimelineEase {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            TimelineEase::Linear => serializer.serialize_str("linear"),
+            TimelineEase::Hold => serializer.serialize_str("hold"),
+            TimelineEase::Curve(e) => serializer.serialize_str(&e.name()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TimelineEase {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        match name.as_str() {
+            "linear" => Ok(TimelineEase::Linear),
+            "hold" => Ok(TimelineEase::Hold),
+            _ => Easing::from_name(&name)
+                .map(TimelineEase::Curve)
+                .ok_or(serde::de::Error::custom(format!(
+                    "unknown ease `{}`, try linear, hold, or something like cubic_in_out",
+                    name
+                ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TimelineKey {
+    at: f32,
+    value: f32,
+    #[serde(default)]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    ease: TimelineEase,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TimelineTrack {
+    #[serde(default)]
+    unit: TimelineUnit,
+    keys: Vec<TimelineKey>,
+}
+impl TimelineTrack {
+    pub fn value_at(&self, t: f32) -> f32 {
+        let Some(first) = self.keys.first() else {
+            return 0.0;
+        };
+        if t <= first.at {
+            return first.value;
+        }
+
+        // the last key at or before t
+        let idx = self.keys.partition_point(|k| k.at <= t) - 1;
+        let curr = &self.keys[idx];
+        match self.keys.get(idx + 1) {
+            Some(next) => {
+                let pct = (t - curr.at) / (next.at - curr.at);
+                curr.value + (next.value - curr.value) * curr.ease.ease(pct)
+            }
+            None => curr.value,
+        }
+    }
+
+    pub fn value(&self, time: &LiveCodeTimeInstantInfo) -> f32 {
+        self.value_at(self.unit.now(time))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(transparent))]
+#[serde(transparent)]
+pub struct Timeline(BTreeMap<String, TimelineTrack>);
+impl Timeline {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.keys().cloned().collect_vec()
+    }
+
+    // the values to add to the world
+    pub fn vals(&self, time: &LiveCodeTimeInstantInfo) -> Vec<(String, LivecodeValue)> {
+        self.0
+            .iter()
+            .map(|(name, track)| (name.clone(), LivecodeValue::float(track.value(time))))
+            .collect_vec()
+    }
+}
+
+impl<'de> Deserialize<'de> for Timeline {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut tracks = BTreeMap::<String, TimelineTrack>::deserialize(deserializer)?;
+        for track in tracks.values_mut() {
+            track.keys.sort_by(|a, b| a.at.total_cmp(&b.at));
+        }
+        Ok(Self(tracks))
+    }
+}
+
+// the keys are just numbers, so there's nothing to look up
+impl GetLivecodeIdentifiers for Timeline {
+    fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
+        vec![]
+    }
+
+    fn function_identifiers(&self) -> Vec<LivecodeFunction> {
+        vec![]
+    }
+}
+
+impl CanMakeGUI for Timeline {
+    fn make_gui() -> murrelet_gui::MurreletGUISchema {
+        murrelet_gui::MurreletGUISchema::Val(murrelet_gui::ValueGUI::Defs)
+    }
+}
+
+impl NestEditable for Timeline {
+    fn nest_update(&self, _mods: NestedMod) -> Self {
+        self.clone()
+    }
+
+    fn nest_get(&self, _getter: &[&str]) -> LivecodeResult<String> {
+        Err(LivecodeError::NestGetExtra("Timeline".to_owned()))
+    }
+}
+
+impl Lerpable for Timeline {
+    fn lerpify<T: lerpable::IsLerpingMethod>(&self, other: &Self, pct: &T) -> Self {
+        step(self, other, pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeline_track_eases_between_keys() {
+        let timeline: Timeline = serde_yaml::from_str(
+            "
+zoom:
+  keys:
+    - { at: 16, value: 0.5, ease: hold }
+    - { at: 0, value: 0.0, ease: quad_in }
+    - { at: 8, value: 1.0 }
+    - { at: 20, value: 0.0 }
+",
+        )
+        .unwrap();
+        let track = &timeline.0["zoom"];
+
+        assert_eq!(track.value_at(-1.0), 0.0);
+        assert_eq!(track.value_at(4.0), 0.25);
+        assert_eq!(track.value_at(8.0), 1.0);
+        assert_eq!(track.value_at(12.0), 0.75);
+        assert_eq!(track.value_at(18.0), 0.5);
+        assert_eq!(track.value_at(20.0), 0.0);
+        assert_eq!(track.value_at(100.0), 0.0);
+
+        assert!(
+            serde_yaml::from_str::<Timeline>("a: { keys: [{ at: 0, value: 1, ease: nope }] }")
+                .is_err()
+        );
+    }
+}
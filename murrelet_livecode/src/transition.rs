@@ -0,0 +1,264 @@
+// how a reload moves from the old config to the new one.
+//
+//   transition:
+//     start: bar            # now (the default), beat or bar
+//     beats: 4              # how long it takes, 0 cuts
+//     ease: cubic_in_out    # linear (the default), hold, or an easing name
+//
+// fields can do their own thing:
+//
+//   #[livecode(transition = "snap")]    jump to the new value when it starts
+//   #[livecode(transition = "8")]       take 8 beats instead
+//   #[livecode(transition = "nested")]  use the settings inside this struct
+//
+// a reload in the middle of a transition starts a new one from wherever the
+// last one got to, so they stack instead of waiting.
+use lerpable::{Lerpable, step};
+use murrelet_gui::CanMakeGUI;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    livecode::{GetLivecodeIdentifiers, LivecodeFunction, LivecodeVariable},
+    nestedit::{NestEditable, NestedMod},
+    state::LiveCodeTimeInstantInfo,
+    timeline::TimelineEase,
+    types::{LivecodeError, LivecodeResult},
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionStart {
+    #[default]
+    Now,
+    Beat,
+    Bar,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Transition {
+    #[serde(default)]
+    start: TransitionStart,
+    #[serde(default)]
+    beats: f32,
+    #[serde(default)]
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    ease: TimelineEase,
+}
+impl Transition {
+    pub fn new(start: TransitionStart, beats: f32, ease: TimelineEase) -> Self {
+        Self { start, beats, ease }
+    }
+
+    pub fn beats(&self) -> f32 {
+        self.beats
+    }
+
+    pub fn with_beats(&self, beats: f32) -> Self {
+        Self { beats, ..*self }
+    }
+
+    pub fn with_start(&self, start: TransitionStart) -> Self {
+        Self { start, ..*self }
+    }
+
+    // nothing to wait for, so just swap the config in
+    pub fn is_cut(&self) -> bool {
+        self.beats <= 0.0 && self.start == TransitionStart::Now
+    }
+}
+
+// a transition that's going. `elapsed` is in beats, and is None while it's
+// waiting for the beat or bar
+#[derive(Debug, Clone, Copy)]
+pub struct TransitionState {
+    transition: Transition,
+    elapsed: Option<f32>,
+}
+impl TransitionState {
+    pub fn new(transition: Transition) -> Self {
+        Self {
+            transition,
+            elapsed: None,
+        }
+    }
+
+    // call once a frame, `beats` is how many beats since the last frame
+    pub fn update(&mut self, time: &LiveCodeTimeInstantInfo, beats: f32) {
+        match &mut self.elapsed {
+            Some(elapsed) => *elapsed += beats,
+            None => {
+                let can_start = match self.transition.start {
+                    TransitionStart::Now => true,
+                    TransitionStart::Beat => time.is_on_beat(),
+                    TransitionStart::Bar => time.is_on_bar(),
+                };
+                if can_start {
+                    self.elapsed = Some(0.0);
+                }
+            }
+        }
+    }
+
+    pub fn has_started(&self) -> bool {
+        self.elapsed.is_some()
+    }
+
+    pub fn beats(&self) -> f32 {
+        self.transition.beats
+    }
+
+    // eased, 0 to 1
+    pub fn pct(&self) -> f32 {
+        self.pct_over(self.transition.beats)
+    }
+
+    // same, but for a field that takes `beats`
+    pub fn pct_over(&self, beats: f32) -> f32 {
+        match self.elapsed {
+            None => 0.0,
+            Some(_) if beats <= 0.0 => 1.0,
+            Some(elapsed) => {
+                let pct = (elapsed / beats).clamp(0.0, 1.0);
+                if pct >= 1.0 {
+                    1.0 // so hold ends up at the new value
+                } else {
+                    self.transition.ease.ease(pct)
+                }
+            }
+        }
+    }
+
+    // `beats` should include the fields with their own timing, see
+    // Transitionable::transition_beats
+    pub fn is_done(&self, beats: f32) -> bool {
+        self.elapsed.is_some_and(|elapsed| elapsed >= beats)
+    }
+}
+
+// generated by the Livecode derive for the fields marked with
+// #[livecode(transition = "...")], the other fields are left alone
+pub trait Transitionable: Sized {
+    // `self` is already `from` lerped to `to` by the whole transition, this
+    // redoes the fields that have their own timing
+    fn transition(&self, from: &Self, to: &Self, t: &TransitionState) -> Self;
+
+    // how long until every field is done, if some take longer than `beats`
+    fn transition_beats(beats: f32) -> f32;
+}
+
+pub fn transition_snap<T: Clone>(from: &T, to: &T, t: &TransitionState) -> T {
+    if t.has_started() {
+        to.clone()
+    } else {
+        from.clone()
+    }
+}
+
+pub fn transition_over<T: Lerpable>(from: &T, to: &T, beats: f32, t: &TransitionState) -> T {
+    from.lerpify(to, &t.pct_over(beats))
+}
+
+// if the lengths change, this just uses the whole transition's lerp
+impl<T: Transitionable + Clone> Transitionable for Vec<T> {
+    fn transition(&self, from: &Self, to: &Self, t: &TransitionState) -> Self {
+        if self.len() == from.len() && self.len() == to.len() {
+            self.iter()
+                .zip(from.iter().zip(to.iter()))
+                .map(|(x, (f, to))| x.transition(f, to, t))
+                .collect()
+        } else {
+            self.clone()
+        }
+    }
+
+    fn transition_beats(beats: f32) -> f32 {
+        T::transition_beats(beats)
+    }
+}
+
+impl<T: Transitionable + Clone> Transitionable for Option<T> {
+    fn transition(&self, from: &Self, to: &Self, t: &TransitionState) -> Self {
+        match (self, from, to) {
+            (Some(x), Some(f), Some(to)) => Some(x.transition(f, to, t)),
+            _ => self.clone(),
+        }
+    }
+
+    fn transition_beats(beats: f32) -> f32 {
+        T::transition_beats(beats)
+    }
+}
+
+// the settings are just numbers and names, so there's nothing to look up
+impl GetLivecodeIdentifiers for Transition {
+    fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
+        vec![]
+    }
+
+    fn function_identifiers(&self) -> Vec<LivecodeFunction> {
+        vec![]
+    }
+}
+
+impl CanMakeGUI for Transition {
+    fn make_gui() -> murrelet_gui::MurreletGUISchema {
+        murrelet_gui::MurreletGUISchema::Val(murrelet_gui::ValueGUI::Defs)
+    }
+}
+
+impl NestEditable for Transition {
+    fn nest_update(&self, _mods: NestedMod) -> Self {
+        *self
+    }
+
+    fn nest_get(&self, _getter: &[&str]) -> LivecodeResult<String> {
+        Err(LivecodeError::NestGetExtra("Transition".to_owned()))
+    }
+}
+
+impl Lerpable for Transition {
+    fn lerpify<T: lerpable::IsLerpingMethod>(&self, other: &Self, pct: &T) -> Self {
+        step(self, other, pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_state_eases_once_started() {
+        let transition: Transition =
+            serde_yaml::from_str("{ start: bar, beats: 4, ease: quad_in }").unwrap();
+        let mut state = TransitionState::new(transition);
+
+        assert!(!transition.is_cut());
+        assert_eq!(state.pct(), 0.0);
+
+        // pretend it's on the bar
+        state.elapsed = Some(0.0);
+        state.update(&LiveCodeTimeInstantInfo::new_dummy(), 2.0);
+        assert!(state.has_started());
+        assert_eq!(state.pct(), 0.25);
+        assert_eq!(state.pct_over(2.0), 1.0);
+        assert_eq!(state.pct_over(0.0), 1.0);
+        assert!(!state.is_done(4.0));
+
+        state.update(&LiveCodeTimeInstantInfo::new_dummy(), 2.0);
+        assert_eq!(state.pct(), 1.0);
+        assert!(state.is_done(4.0));
+        assert!(!state.is_done(8.0));
+
+        let hold = Transition::new(TransitionStart::Now, 2.0, TimelineEase::Hold);
+        let mut state = TransitionState::new(hold);
+        state.update(&LiveCodeTimeInstantInfo::new_dummy(), 0.0);
+        state.update(&LiveCodeTimeInstantInfo::new_dummy(), 1.0);
+        assert_eq!(state.pct(), 0.0);
+        state.update(&LiveCodeTimeInstantInfo::new_dummy(), 1.0);
+        assert_eq!(state.pct(), 1.0);
+
+        assert!(Transition::default().is_cut());
+    }
+}
@@ -1,19 +1,22 @@
 use std::{collections::HashSet, fmt::Debug};
 
-use evalexpr::{EvalexprError, HashMapContext, Node, build_operator_tree};
+use evalexpr::{EvalexprError, HashMapContext};
 use itertools::Itertools;
 use lerpable::{Lerpable, step};
 use murrelet_common::{IdxInRange, IdxInRange2d, LivecodeValue, print_expect};
 use murrelet_gui::CanMakeGUI;
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 
 use crate::{
     expr::{IntoExprWorldContext, MixedEvalDefs, ToMixedDefs},
     lazy::{ControlLazyNodeF32, IsLazy, LazyNodeF32, WrappedLazyType},
+    lint::repeat_var_names,
     livecode::{
-        ControlF32, GetLivecodeIdentifiers, LivecodeFromWorld, LivecodeToControl, LivecodeVariable,
+        ControlF32, GetLivecodeIdentifiers, LivecodeExpr, LivecodeFromWorld, LivecodeFunction,
+        LivecodeToControl, LivecodeVariable,
     },
+    sourcemap::SourceLoc,
     state::LivecodeWorldState,
     unitcells::UnitCellIdx,
 };
@@ -36,17 +39,55 @@ pub enum LivecodeError {
     WGPU(String),
     #[error("parse: {0}")]
     JsonParse(String),
+    #[error("{1}: {0}: {2}")]
+    EvalExprAt(String, Box<SourceLoc>, EvalexprError),
+    #[error("{1} (in field {0})")]
+    Field(String, Box<LivecodeError>),
 }
 impl LivecodeError {
     pub fn raw(s: &str) -> Self {
         Self::Raw(s.to_string())
     }
 
+    // used on the way back up from LivecodeFromWorld::o, builds up the path
+    // to the field that broke
+    pub fn with_field_path(self, name: &str) -> Self {
+        match self {
+            LivecodeError::Field(path, err) => {
+                LivecodeError::Field(format!("{}.{}", name, path), err)
+            }
+            err => LivecodeError::Field(name.to_owned(), Box::new(err)),
+        }
+    }
+
+    pub fn field_path(&self) -> Option<&str> {
+        match self {
+            LivecodeError::Field(path, _) => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn source_loc(&self) -> Option<&SourceLoc> {
+        match self {
+            LivecodeError::EvalExprAt(_, loc, _) => Some(loc.as_ref()),
+            LivecodeError::Field(_, err) => err.source_loc(),
+            _ => None,
+        }
+    }
+
     pub fn rawr<T>(s: &str) -> LivecodeResult<T> {
         LivecodeResult::Err(Self::raw(s))
     }
 }
 
+// the derive wraps each field's `o` in this so errors know which field they came from
+pub fn in_field<T, F>(name: &str, f: F) -> LivecodeResult<T>
+where
+    F: FnOnce() -> LivecodeResult<T>,
+{
+    f().map_err(|e| e.with_field_path(name))
+}
+
 pub trait IterUnwrapOrPrint<T> {
     fn iter_unwrap<U, F>(&self, err: &str, f: F) -> Vec<U>
     where
@@ -89,10 +130,14 @@ impl<T> IterUnwrapOrPrint<T> for Vec<T> {
 
 pub type LivecodeResult<T> = Result<T, LivecodeError>;
 
-#[derive(Debug, Clone, Deserialize)]
+// keeps the source so it can be written back out
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(transparent))]
 #[serde(transparent)]
-pub struct AdditionalContextNode(#[cfg_attr(feature = "schemars", schemars(with = "String"))] Node);
+pub struct AdditionalContextNode(
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))] LivecodeExpr,
+);
 
 fn _default_ctx() -> AdditionalContextNode {
     AdditionalContextNode::new_dummy()
@@ -104,7 +149,7 @@ fn _default_ctx_lazy() -> AdditionalContextNode {
 
 impl Default for AdditionalContextNode {
     fn default() -> Self {
-        Self(build_operator_tree("").unwrap())
+        Self::new_dummy()
     }
 }
 
@@ -118,6 +163,37 @@ impl AdditionalContextNode {
             .collect_vec()
     }
 
+    // variables this sets, e.g. `a` in `a = t * 2;`
+    pub fn defined_vars(&self) -> Vec<String> {
+        self.0
+            .iter_write_variable_identifiers()
+            .sorted()
+            .dedup()
+            .map(|x| x.to_owned())
+            .collect_vec()
+    }
+
+    // variables this needs from somewhere else
+    pub fn used_vars(&self) -> Vec<String> {
+        let defined = self.defined_vars();
+        self.0
+            .iter_read_variable_identifiers()
+            .filter(|x| !defined.iter().any(|d| d == x))
+            .sorted()
+            .dedup()
+            .map(|x| x.to_owned())
+            .collect_vec()
+    }
+
+    pub fn funcs(&self) -> Vec<LivecodeFunction> {
+        self.0
+            .iter_function_identifiers()
+            .sorted()
+            .dedup()
+            .map(LivecodeFunction::from_str)
+            .collect_vec()
+    }
+
     pub fn eval_raw(&self, ctx: &mut HashMapContext) -> LivecodeResult<()> {
         self.0
             .eval_empty_with_context_mut(ctx)
@@ -125,7 +201,7 @@ impl AdditionalContextNode {
     }
 
     pub fn new_dummy() -> AdditionalContextNode {
-        AdditionalContextNode(build_operator_tree("").unwrap())
+        AdditionalContextNode(LivecodeExpr::new("").unwrap())
     }
 }
 
@@ -141,13 +217,29 @@ impl Lerpable for AdditionalContextNode {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ControlVecElementRepeatMethod {
     Single(ControlF32),
     Rect([ControlF32; 2]),
 }
+impl GetLivecodeIdentifiers for ControlVecElementRepeatMethod {
+    fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
+        match self {
+            ControlVecElementRepeatMethod::Single(s) => s.variable_identifiers(),
+            ControlVecElementRepeatMethod::Rect(r) => r.variable_identifiers(),
+        }
+    }
+
+    fn function_identifiers(&self) -> Vec<LivecodeFunction> {
+        match self {
+            ControlVecElementRepeatMethod::Single(s) => s.function_identifiers(),
+            ControlVecElementRepeatMethod::Rect(r) => r.function_identifiers(),
+        }
+    }
+}
+
 impl ControlVecElementRepeatMethod {
     fn len(&self, w: &LivecodeWorldState) -> LivecodeResult<usize> {
         let v = match self {
@@ -176,14 +268,14 @@ impl ControlVecElementRepeatMethod {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ControlLazyBlendRepeatMethod {
     count: ControlLazyNodeF32,
     blend: ControlLazyNodeF32,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum DeserLazyControlVecElementRepeatMethod {
@@ -216,7 +308,7 @@ impl DeserLazyControlVecElementRepeatMethod {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DeserLazyControlVecElementRepeat<DeserSource: Clone + Debug> {
     repeat: DeserLazyControlVecElementRepeatMethod,
@@ -250,7 +342,8 @@ impl<DeserSource: Clone + Debug> DeserLazyControlVecElementRepeat<DeserSource> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum DeserLazyControlVecElement<Source>
 where
     Source: Clone + Debug,
@@ -546,7 +639,7 @@ where
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ControlVecElementRepeat<Source: Clone + Debug> {
     repeat: ControlVecElementRepeatMethod,
@@ -566,13 +659,18 @@ where
     fn variable_identifiers(&self) -> Vec<crate::livecode::LivecodeVariable> {
         match self {
             ControlVecElement::Single(c) => c.variable_identifiers(),
-            ControlVecElement::Repeat(c) => c
-                .what
-                .iter()
-                .flat_map(|x| x.variable_identifiers())
-                .collect::<HashSet<_>>()
-                .into_iter()
-                .collect_vec(),
+            ControlVecElement::Repeat(c) => {
+                // the repeat provides these, so they aren't needed from outside
+                let scoped = repeat_var_names(&c.prefix_with_underscore());
+                c.what
+                    .iter()
+                    .flat_map(|x| x.variable_identifiers())
+                    .filter(|x| !scoped.contains(&x.name))
+                    .chain(c.repeat.variable_identifiers())
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect_vec()
+            }
         }
     }
 
@@ -583,6 +681,7 @@ where
                 .what
                 .iter()
                 .flat_map(|x| x.function_identifiers())
+                .chain(c.repeat.function_identifiers())
                 .collect::<HashSet<_>>()
                 .into_iter()
                 .collect_vec(),
@@ -654,8 +753,10 @@ where
     let mut result: Vec<Target> = Vec::new();
     let mut is_blending: Option<BlendWith> = None;
 
-    for item in items {
-        let expanded = item.eval_and_expand_vec(w)?;
+    for (i, item) in items.iter().enumerate() {
+        let expanded = item
+            .eval_and_expand_vec(w)
+            .map_err(|e| e.with_field_path(&i.to_string()))?;
         for elem in expanded {
             blend_with_list(&mut result, elem, &mut is_blending);
         }
@@ -695,6 +796,14 @@ where
 }
 
 impl<Source: Clone + Debug> ControlVecElementRepeat<Source> {
+    fn prefix_with_underscore(&self) -> String {
+        if self.prefix.is_empty() {
+            "i_".to_string()
+        } else {
+            format!("{}_", self.prefix)
+        }
+    }
+
     pub fn _eval_and_expand_vec<Target>(
         &self,
         w: &LivecodeWorldState,
@@ -706,17 +815,17 @@ impl<Source: Clone + Debug> ControlVecElementRepeat<Source> {
     {
         let mut result = Vec::with_capacity(self.repeat.len(w)? * self.what.len());
 
-        let prefix = if self.prefix.is_empty() {
-            "i_".to_string()
-        } else {
-            format!("{}_", self.prefix)
-        };
+        let prefix = self.prefix_with_underscore();
 
         let mut offset = offset;
 
         for idx in self.repeat.iter(w)? {
-            let expr = UnitCellIdx::from_idx2d(idx, 1.0).as_expr_world_context_values();
-            let mut new_w = w.clone_with_vals(expr, &prefix);
+            let unit_idx = UnitCellIdx::from_idx2d(idx, 1.0);
+            let mut new_w = w.clone_to_item(
+                unit_idx.as_expr_world_context_values(),
+                &prefix,
+                unit_idx.i(),
+            );
 
             let mut is_blending: Option<BlendWith> = None;
 
@@ -811,7 +920,8 @@ impl<Source: Clone + Debug> ControlVecElementRepeat<Source> {
 //     }
 // }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 // #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 // pub enum ControlVecElement<Sequencer, ControlSequencer, Source>
 pub enum ControlVecElement<Source>
@@ -1026,3 +1136,97 @@ where
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use murrelet_common::{Assets, LivecodeSrc};
+
+    use super::*;
+    use crate::expr::init_evalexpr_func_ctx;
+    use crate::lazy::ControlLazyNodeF32;
+    use crate::livecode::{ControlBool, ControlF32, LivecodeFromWorld};
+    use crate::state::LiveCodeTimeInstantInfo;
+    use crate::stateful::StatefulFuncs;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct RoundTrip {
+        size: ControlF32,
+        count: ControlF32,
+        on: ControlBool,
+        lazy: ControlLazyNodeF32,
+        ctx: AdditionalContextNode,
+        sizes: Vec<ControlVecElement<ControlF32>>,
+    }
+
+    #[test]
+    fn control_types_round_trip_through_yaml() {
+        let yaml = "
+size: sin(t) * (2 + 1)
+count: 3
+on: true
+lazy: x * 2
+ctx: |
+  q = 3;
+sizes:
+  - 1.5
+  - q + 1
+  - { repeat: 3, prefix: p, what: [p_i * 10] }
+";
+        let conf: RoundTrip = serde_yaml::from_str(yaml).unwrap();
+        let written = serde_yaml::to_string(&conf).unwrap();
+
+        // expressions are written as they were typed
+        assert!(written.contains("size: sin(t) * (2 + 1)\n"));
+        assert!(written.contains("count: 3\n"));
+
+        let again: RoundTrip = serde_yaml::from_str(&written).unwrap();
+        assert_eq!(serde_yaml::to_string(&again).unwrap(), written);
+
+        let w = LivecodeWorldState::new(
+            &init_evalexpr_func_ctx().unwrap(),
+            &LivecodeSrc::new(vec![]),
+            LiveCodeTimeInstantInfo::new_dummy(),
+            again.ctx.clone(),
+            Arc::new(Assets::empty()),
+        )
+        .unwrap();
+        assert_eq!(conf.size.o(&w).unwrap(), again.size.o(&w).unwrap());
+        let sizes: Vec<f32> = eval_and_expand_vec_list(&again.sizes, &w).unwrap();
+        assert_eq!(sizes, vec![1.5, 4.0, 0.0, 10.0, 20.0]);
+    }
+
+    #[test]
+    fn repeat_items_keep_their_own_stateful_state() {
+        let stateful = StatefulFuncs::new();
+        let mut ctx = init_evalexpr_func_ctx().unwrap();
+        stateful.register(&mut ctx).unwrap();
+
+        // same call site, but each item lags its own input
+        let sizes: Vec<ControlVecElement<ControlF32>> = serde_yaml::from_str(
+            "
+- repeat: 2
+  prefix: p
+  what: [\"lag(p_i * 10, 1.0)\"]
+",
+        )
+        .unwrap();
+
+        let mut results = vec![];
+        for frame in 0..2 {
+            stateful.set_frame(frame, 0.5);
+            let w = LivecodeWorldState::new(
+                &ctx,
+                &LivecodeSrc::new(vec![]),
+                LiveCodeTimeInstantInfo::new_dummy(),
+                AdditionalContextNode::default(),
+                Arc::new(Assets::empty()),
+            )
+            .unwrap();
+            let frame_sizes: Vec<f32> = eval_and_expand_vec_list(&sizes, &w).unwrap();
+            results.push(frame_sizes);
+        }
+        assert_eq!(results, vec![vec![0.0, 10.0], vec![0.0, 10.0]]);
+    }
+}
@@ -632,6 +632,11 @@ impl UnitCellContext {
         self.idx.to_idx2d()
     }
 
+    // which cell this is, in 1d
+    pub(crate) fn item_idx(&self) -> u64 {
+        self.idx.i()
+    }
+
     pub fn rect_bound(&self) -> Vec<Vec2> {
         let face = if let Some(tile_info) = &self.tile_info {
             tile_info.face()
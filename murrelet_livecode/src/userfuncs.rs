@@ -0,0 +1,314 @@
+// functions defined in the config, so you don't have to keep copy-pasting
+// the same clamp(s(...)) chain into every field.
+//
+//   functions:
+//     - wobble(x, amt) = clamp(x + amt * sin(x, 0.5), 0.0, 1.0)
+//     - half(x) = x * 0.5
+//
+// the body only sees its parameters, constants like PI, and functions
+// (including the ones defined above it), so pass in things like `t`.
+//
+// stateful functions like lag aren't allowed in the body. their state goes
+// with the spot they're written, so every call to the function would share
+// one lag.
+use std::sync::Arc;
+
+use evalexpr::{
+    Context, ContextWithMutableFunctions, EvalexprError, EvalexprResult, Function, HashMapContext,
+    Node, Value, build_operator_tree,
+};
+use itertools::Itertools;
+use lerpable::{Lerpable, step};
+use murrelet_gui::CanMakeGUI;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    livecode::{GetLivecodeIdentifiers, LivecodeFunction, LivecodeVariable},
+    nestedit::{NestEditable, NestedMod},
+    stateful::STATEFUL_FUNCTIONS,
+    types::{LivecodeError, LivecodeResult},
+};
+
+#[derive(Debug, Clone)]
+pub struct LivecodeUserFunction {
+    name: String,
+    params: Vec<String>,
+    body: Node,
+    src: String, // how it was written, for saving it back out
+}
+impl LivecodeUserFunction {
+    pub fn parse(s: &str) -> LivecodeResult<Self> {
+        let re = Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_]*)\s*\(([^()]*)\)\s*=([^=].*)$").unwrap();
+        let caps = re.captures(s).ok_or(LivecodeError::Raw(format!(
+            "function should look like `name(a, b) = a + b`, got `{}`",
+            s
+        )))?;
+
+        let name = caps[1].to_owned();
+        let params = caps[2]
+            .split(',')
+            .map(|x| x.trim().to_owned())
+            .filter(|x| !x.is_empty())
+            .collect_vec();
+        let body = build_operator_tree(caps[3].trim()).map_err(|err| {
+            LivecodeError::EvalExpr(format!("error parsing function `{}`", name), err)
+        })?;
+
+        if let Some(stateful) = body
+            .iter_function_identifiers()
+            .find(|x| STATEFUL_FUNCTIONS.contains(x))
+        {
+            return Err(LivecodeError::Raw(format!(
+                "function `{}` can't use `{}`, call it where you use the function instead",
+                name, stateful
+            )));
+        }
+
+        Ok(Self {
+            name,
+            params,
+            body,
+            src: s.trim().to_owned(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // evalexpr gives us nothing, a single value, or a tuple
+    fn bind_args(&self, argument: &Value) -> EvalexprResult<Vec<Value>> {
+        match self.params.len() {
+            0 => match argument {
+                Value::Empty => Ok(vec![]),
+                _ => Err(EvalexprError::WrongFunctionArgumentAmount {
+                    expected: 0..=0,
+                    actual: 1,
+                }),
+            },
+            1 => Ok(vec![argument.clone()]),
+            n => argument.as_fixed_len_tuple(n),
+        }
+    }
+
+    fn to_function(&self, ctx: Arc<HashMapContext>) -> Function {
+        let f = self.clone();
+        Function::new(move |argument| {
+            let args = f.bind_args(argument)?;
+            let params = f.params.iter().cloned().zip(args).collect_vec();
+            f.body.eval_with_context(&ParamsContext {
+                params: &params,
+                ctx: &ctx,
+            })
+        })
+    }
+}
+
+// the function's parameters on top of the function context, so we don't
+// need to clone the whole context every call
+struct ParamsContext<'a> {
+    params: &'a [(String, Value)],
+    ctx: &'a HashMapContext,
+}
+impl Context for ParamsContext<'_> {
+    fn get_value(&self, identifier: &str) -> Option<&Value> {
+        self.params
+            .iter()
+            .find(|(name, _)| name == identifier)
+            .map(|(_, v)| v)
+            .or_else(|| self.ctx.get_value(identifier))
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        self.ctx.call_function(identifier, argument)
+    }
+
+    fn are_builtin_functions_disabled(&self) -> bool {
+        self.ctx.are_builtin_functions_disabled()
+    }
+
+    fn set_builtin_functions_disabled(&mut self, _disabled: bool) -> EvalexprResult<()> {
+        Err(EvalexprError::ContextNotMutable)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(transparent))]
+pub struct UserFunctions(
+    #[cfg_attr(feature = "schemars", schemars(with = "Vec<String>"))] Vec<LivecodeUserFunction>,
+);
+impl UserFunctions {
+    pub fn new(funcs: Vec<LivecodeUserFunction>) -> Self {
+        Self(funcs)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.iter().map(|x| x.name.clone()).collect_vec()
+    }
+
+    // adds these to ctx (usually the one from init_evalexpr_func_ctx). each
+    // one can use the ones before it.
+    pub fn register(&self, ctx: &mut HashMapContext) -> LivecodeResult<()> {
+        for f in &self.0 {
+            let snapshot = Arc::new(ctx.clone());
+            ctx.set_function(f.name.clone(), f.to_function(snapshot))
+                .map_err(|err| {
+                    LivecodeError::EvalExpr(format!("error adding function `{}`", f.name), err)
+                })?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for UserFunctions {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(|f| &f.src))
+    }
+}
+
+impl<'de> Deserialize<'de> for UserFunctions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let srcs = Vec::<String>::deserialize(deserializer)?;
+        srcs.iter()
+            .map(|s| LivecodeUserFunction::parse(s))
+            .collect::<LivecodeResult<Vec<_>>>()
+            .map(UserFunctions)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl GetLivecodeIdentifiers for UserFunctions {
+    // anything the bodies use that isn't a parameter
+    fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
+        self.0
+            .iter()
+            .flat_map(|f| {
+                f.body
+                    .iter_read_variable_identifiers()
+                    .filter(|x| !f.params.iter().any(|p| p == x))
+                    .map(LivecodeVariable::from_str)
+                    .collect_vec()
+            })
+            .sorted()
+            .dedup()
+            .collect_vec()
+    }
+
+    fn function_identifiers(&self) -> Vec<LivecodeFunction> {
+        self.0
+            .iter()
+            .flat_map(|f| {
+                f.body
+                    .iter_function_identifiers()
+                    .map(LivecodeFunction::from_str)
+            })
+            .sorted()
+            .dedup()
+            .collect_vec()
+    }
+}
+
+impl CanMakeGUI for UserFunctions {
+    fn make_gui() -> murrelet_gui::MurreletGUISchema {
+        murrelet_gui::MurreletGUISchema::Val(murrelet_gui::ValueGUI::Defs)
+    }
+}
+
+impl NestEditable for UserFunctions {
+    fn nest_update(&self, _mods: NestedMod) -> Self {
+        self.clone()
+    }
+
+    fn nest_get(&self, _getter: &[&str]) -> LivecodeResult<String> {
+        Err(LivecodeError::NestGetExtra("UserFunctions".to_owned()))
+    }
+}
+
+impl Lerpable for UserFunctions {
+    fn lerpify<T: lerpable::IsLerpingMethod>(&self, other: &Self, pct: &T) -> Self {
+        step(self, other, pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evalexpr::ContextWithMutableVariables;
+
+    use super::*;
+    use crate::{
+        expr::init_evalexpr_func_ctx,
+        stateful::{StatefulFuncs, tag_call_sites},
+    };
+
+    #[test]
+    fn user_functions_can_call_each_other() {
+        let funcs = UserFunctions::new(vec![
+            LivecodeUserFunction::parse("half(x) = x * 0.5").unwrap(),
+            LivecodeUserFunction::parse("wobble(x, amt) = clamp(half(x) + amt, 0.0, 1.0)").unwrap(),
+            LivecodeUserFunction::parse("two() = 2.0").unwrap(),
+        ]);
+
+        let mut ctx = init_evalexpr_func_ctx().unwrap();
+        funcs.register(&mut ctx).unwrap();
+        ctx.set_value("t".to_owned(), Value::Float(0.5)).unwrap();
+
+        let eval = |s: &str| {
+            build_operator_tree(s)
+                .unwrap()
+                .eval_number_with_context(&ctx)
+                .unwrap()
+        };
+        assert_eq!(eval("wobble(t, 0.25)"), 0.5);
+        assert_eq!(eval("wobble(t, 2.0)"), 1.0);
+        assert_eq!(eval("half(two())"), 1.0);
+
+        assert!(LivecodeUserFunction::parse("nope = 3").is_err());
+    }
+
+    #[test]
+    fn stateful_calls_stay_out_of_function_bodies() {
+        assert!(LivecodeUserFunction::parse("smoothed(x) = lag(x, 0.2)").is_err());
+        assert!(LivecodeUserFunction::parse("bumps(k) = count(k) * 2").is_err());
+
+        // written where it's used instead, each call keeps its own lag
+        let funcs = UserFunctions::new(vec![
+            LivecodeUserFunction::parse("half(x) = x * 0.5").unwrap(),
+        ]);
+        let stateful = StatefulFuncs::new();
+        let mut ctx = init_evalexpr_func_ctx().unwrap();
+        stateful.register(&mut ctx).unwrap();
+        funcs.register(&mut ctx).unwrap();
+
+        let parse = |s: &str| {
+            let mut node = build_operator_tree(s).unwrap();
+            tag_call_sites(&mut node);
+            node
+        };
+        let a = parse("half(lag(m3, 0.5))");
+        let b = parse("half(lag(m4, 0.5))");
+
+        for frame in 0..3 {
+            stateful.set_frame(frame, 0.1);
+            ctx.set_value("m3".to_owned(), Value::Float(frame as f64))
+                .unwrap();
+            ctx.set_value("m4".to_owned(), Value::Float(10.0)).unwrap();
+
+            let a = a.eval_number_with_context(&ctx).unwrap();
+            let b = b.eval_number_with_context(&ctx).unwrap();
+            assert_eq!(b, 5.0);
+            assert!(a < 1.0, "{}", a);
+        }
+    }
+}
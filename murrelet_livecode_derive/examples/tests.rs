@@ -65,6 +65,48 @@ enum EnumTest {
 #[derive(Debug, Clone, Livecode, Lerpable, Default)]
 struct TestNewType(Vec<EnumTest>);
 
+#[derive(Debug, Clone, Livecode, Lerpable, Default)]
+struct BoopTest {
+    #[livecode(boop)]
+    a_number: f32,
+    #[livecode(boop)]
+    c_vec2: Vec2,
+    #[livecode(boop)]
+    c_vec3: Vec3,
+    #[livecode(boop)]
+    b_color: MurreletColor,
+    #[livecode(boop)]
+    list_of_vec2: Vec<Vec2>,
+    not_booped: f32,
+}
+
+#[derive(Debug, Clone, Livecode, Lerpable, Default)]
+struct BoopNestedTest {
+    #[livecode(boop)]
+    inner: BoopTest,
+    #[livecode(boop)]
+    inners: Vec<BoopTest>,
+}
+
+#[derive(Debug, Clone, Livecode, Lerpable, Default)]
+struct TransitionTest {
+    #[livecode(transition = "snap")]
+    a_number: f32,
+    #[livecode(transition = "8")]
+    c_vec2: Vec2,
+    #[livecode(transition = "nested")]
+    inner: TransitionInnerTest,
+    #[livecode(transition = "nested")]
+    inners: Vec<TransitionInnerTest>,
+    not_special: f32,
+}
+
+#[derive(Debug, Clone, Livecode, Lerpable, Default)]
+struct TransitionInnerTest {
+    #[livecode(transition = "0.5")]
+    quick: f32,
+}
+
 // #[derive(Debug, Clone, Livecode, Lerpable, Default)]
 // struct SequencerTest {
 //     sequencer: SimpleSquareSequence,
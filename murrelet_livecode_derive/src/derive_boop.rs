@@ -0,0 +1,182 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use crate::parser::*;
+
+// fields with #[livecode(boop)] get smoothed, everything else is just copied over.
+// structs need to be marked too if you want to boop things inside of them
+pub(crate) struct FieldTokensBoop {
+    pub(crate) for_boop: TokenStream2,
+}
+impl FieldTokensBoop {
+    fn for_field(idents: &StructIdents) -> FieldTokensBoop {
+        let name = idents.name();
+        let yaml_name = name.to_string();
+
+        let for_boop = if idents.data.boop {
+            quote! { #name: murrelet_livecode::boop::Boopable::boop(&self.#name, &conf.copy_with_new_current_boop(#yaml_name), states) }
+        } else {
+            quote! { #name: self.#name.clone() }
+        };
+
+        FieldTokensBoop { for_boop }
+    }
+
+    fn for_newtype_field(idents: &StructIdents) -> FieldTokensBoop {
+        let for_boop = if idents.data.boop {
+            quote! { murrelet_livecode::boop::Boopable::boop(&self.0, conf, states) }
+        } else {
+            quote! { self.0.clone() }
+        };
+
+        FieldTokensBoop { for_boop }
+    }
+}
+
+impl GenFinal for FieldTokensBoop {
+    fn make_struct_final(idents: ParsedFieldIdent, variants: Vec<FieldTokensBoop>) -> TokenStream2 {
+        let name = idents.name;
+        let for_boop = variants.iter().map(|x| x.for_boop.clone());
+
+        quote! {
+            impl murrelet_livecode::boop::Boopable for #name {
+                #[allow(unused_variables)]
+                fn boop(&self, conf: &murrelet_livecode::boop::BoopConf, states: &mut murrelet_livecode::boop::BoopStates) -> Self {
+                    #name {
+                        #(#for_boop,)*
+                    }
+                }
+            }
+        }
+    }
+
+    fn make_enum_final(idents: ParsedFieldIdent, variants: Vec<FieldTokensBoop>) -> TokenStream2 {
+        let name = idents.name;
+        let for_boop = variants.iter().map(|x| x.for_boop.clone());
+
+        quote! {
+            impl murrelet_livecode::boop::Boopable for #name {
+                #[allow(unused_variables)]
+                fn boop(&self, conf: &murrelet_livecode::boop::BoopConf, states: &mut murrelet_livecode::boop::BoopStates) -> Self {
+                    match self {
+                        #(#for_boop,)*
+                    }
+                }
+            }
+        }
+    }
+
+    fn make_newtype_struct_final(
+        idents: ParsedFieldIdent,
+        variants: Vec<FieldTokensBoop>,
+    ) -> TokenStream2 {
+        let name = idents.name;
+        let for_boop = variants.iter().map(|x| x.for_boop.clone());
+
+        quote! {
+            impl murrelet_livecode::boop::Boopable for #name {
+                #[allow(unused_variables)]
+                fn boop(&self, conf: &murrelet_livecode::boop::BoopConf, states: &mut murrelet_livecode::boop::BoopStates) -> Self {
+                    #name(#(#for_boop,)*)
+                }
+            }
+        }
+    }
+
+    fn new_ident(name: syn::Ident) -> syn::Ident {
+        name.clone()
+    }
+
+    fn from_newtype_struct(idents: StructIdents, _parent_ident: syn::Ident) -> FieldTokensBoop {
+        Self::for_newtype_field(&idents)
+    }
+
+    fn from_newtype_struct_struct(
+        idents: StructIdents,
+        _parent_ident: syn::Ident,
+    ) -> FieldTokensBoop {
+        Self::for_newtype_field(&idents)
+    }
+
+    fn from_newtype_struct_lazy(
+        idents: StructIdents,
+        _parent_ident: syn::Ident,
+    ) -> FieldTokensBoop {
+        Self::for_newtype_field(&idents)
+    }
+
+    fn from_newtype_recurse_struct_vec(idents: StructIdents) -> FieldTokensBoop {
+        Self::for_newtype_field(&idents)
+    }
+
+    // e.g. TileAxisLocs::V(TileAxisVs)
+    fn from_unnamed_enum(idents: EnumIdents) -> FieldTokensBoop {
+        let variant_ident = idents.variant_ident();
+        let variant_ident_str = variant_ident.to_string();
+        let name = idents.enum_ident();
+
+        let unnamed = idents.data.fields.fields;
+        if unnamed.len() != 1 {
+            panic!("multiple fields not supported")
+        };
+
+        let for_boop = if unnamed.first().unwrap().boop {
+            quote! {
+                #name::#variant_ident(e) => #name::#variant_ident(murrelet_livecode::boop::Boopable::boop(e, &conf.copy_with_new_current_boop(#variant_ident_str), states))
+            }
+        } else {
+            quote! { #name::#variant_ident(e) => #name::#variant_ident(e.clone()) }
+        };
+
+        FieldTokensBoop { for_boop }
+    }
+
+    // e.g. TileAxis::Diag
+    fn from_unit_enum(idents: EnumIdents) -> FieldTokensBoop {
+        let variant_ident = idents.variant_ident();
+        let name = idents.enum_ident();
+
+        FieldTokensBoop {
+            for_boop: quote! { #name::#variant_ident => #name::#variant_ident },
+        }
+    }
+
+    fn from_noop_struct(idents: StructIdents) -> FieldTokensBoop {
+        let name = idents.name();
+        FieldTokensBoop {
+            for_boop: quote! { #name: self.#name.clone() },
+        }
+    }
+
+    fn from_type_struct(idents: StructIdents) -> FieldTokensBoop {
+        Self::for_field(&idents)
+    }
+
+    fn from_option(idents: StructIdents) -> FieldTokensBoop {
+        Self::for_field(&idents)
+    }
+
+    fn from_recurse_struct_vec(idents: StructIdents) -> FieldTokensBoop {
+        Self::for_field(&idents)
+    }
+
+    fn from_recurse_struct_struct(idents: StructIdents) -> FieldTokensBoop {
+        Self::for_field(&idents)
+    }
+
+    // unitcells get rebuilt every frame, so there isn't a good place to keep state
+    fn from_recurse_struct_unitcell(idents: StructIdents) -> FieldTokensBoop {
+        let name = idents.name();
+        FieldTokensBoop {
+            for_boop: quote! { #name: self.#name.clone() },
+        }
+    }
+
+    // lazy things aren't evaluated yet, so there's nothing to boop
+    fn from_recurse_struct_lazy(idents: StructIdents) -> FieldTokensBoop {
+        let name = idents.name();
+        FieldTokensBoop {
+            for_boop: quote! { #name: self.#name.clone() },
+        }
+    }
+}
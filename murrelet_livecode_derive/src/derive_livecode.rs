@@ -7,6 +7,14 @@ pub(crate) fn update_to_control_ident(name: syn::Ident) -> syn::Ident {
     prefix_ident("Control", name)
 }
 
+// `name: value` for a struct field, with errors saying which field they're from
+fn field_world(name: &syn::Ident, value: TokenStream2) -> TokenStream2 {
+    let name_str = name.to_string();
+    quote! {
+        #name: murrelet_livecode::types::in_field(#name_str, || Ok(#value))?
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct LivecodeFieldType(pub ControlType);
 
@@ -136,7 +144,7 @@ impl LivecodeFieldType {
             idents.data.f32min,
             idents.data.f32max,
         );
-        quote! {#name: #rest}
+        field_world(&name, rest)
     }
 
     pub(crate) fn for_world_option(&self, idents: StructIdents) -> TokenStream2 {
@@ -147,7 +155,7 @@ impl LivecodeFieldType {
             idents.data.f32min,
             idents.data.f32max,
         );
-        quote! {#name: #rest}
+        field_world(&name, rest)
     }
 
     pub(crate) fn for_newtype_world(&self, idents: StructIdents) -> TokenStream2 {
@@ -220,13 +228,14 @@ impl GenFinal for FieldTokensLivecode {
             (quote! {}, quote! {})
         };
         quote! {
-            #[derive(Debug, Clone, serde::Deserialize #maybe_cfg_attr)]
+            #[derive(Debug, Clone, serde::Serialize, serde::Deserialize #maybe_cfg_attr)]
             #additional
             #vis struct #new_ident {
                 #(#for_struct,)*
             }
 
             impl murrelet_livecode::livecode::LivecodeFromWorld<#name> for #new_ident {
+                #[allow(clippy::needless_question_mark)]
                 fn o(&self, w: &murrelet_livecode::state::LivecodeWorldState) -> murrelet_livecode::types::LivecodeResult<#name> {
                     Ok(#name {
                         #(#for_world,)*
@@ -289,7 +298,7 @@ impl GenFinal for FieldTokensLivecode {
             (quote! {}, quote! {})
         };
         quote! {
-            #[derive(Debug, Clone, serde::Deserialize #maybe_cfg_attr)]
+            #[derive(Debug, Clone, serde::Serialize, serde::Deserialize #maybe_cfg_attr)]
             #additional
             #[allow(non_camel_case_types)]
             #enum_tag
@@ -352,7 +361,7 @@ impl GenFinal for FieldTokensLivecode {
         };
 
         quote! {
-            #[derive(Debug, Clone, serde::Deserialize #maybe_cfg_attr)]
+            #[derive(Debug, Clone, serde::Serialize, serde::Deserialize #maybe_cfg_attr)]
             #additional
             #vis struct #new_ident(#(#for_struct,)*);
 
@@ -418,7 +427,14 @@ impl GenFinal for FieldTokensLivecode {
     }
 
     fn from_newtype_struct_lazy(idents: StructIdents, parent_ident: syn::Ident) -> Self {
-        Self::from_newtype_struct_struct(idents, parent_ident)
+        let tokens = Self::from_newtype_struct_struct(idents, parent_ident);
+        let for_variable_idents = tokens.for_variable_idents;
+        FieldTokensLivecode {
+            for_variable_idents: quote! {
+                murrelet_livecode::livecode::deferred_identifiers(#for_variable_idents)
+            },
+            ..tokens
+        }
     }
 
     fn from_newtype_struct_struct(
@@ -485,8 +501,14 @@ impl GenFinal for FieldTokensLivecode {
         };
 
         // for world
-        let for_world =
-            quote! { #new_ident::#variant_ident(s) => Ok(#name::#variant_ident(s.o(w)?)) };
+        let for_world = {
+            let variant_str = variant_ident.to_string();
+            quote! {
+                #new_ident::#variant_ident(s) => Ok(#name::#variant_ident(
+                    s.o(w).map_err(|e| e.with_field_path(#variant_str))?
+                ))
+            }
+        };
 
         let for_to_control =
             quote! { #name::#variant_ident(s) => #new_ident::#variant_ident(s.to_control()) };
@@ -541,9 +563,7 @@ impl GenFinal for FieldTokensLivecode {
         let for_struct = {
             quote! {#serde #name: #new_ty}
         };
-        let for_world: TokenStream2 = {
-            quote! {#name: self.#name.clone()}
-        };
+        let for_world = field_world(&name, quote! {self.#name.clone()});
         let for_to_control = quote! {#name: self.#name.clone()};
 
         let for_variable_idents = quote! { self.#name.variable_identifiers() };
@@ -695,27 +715,27 @@ impl GenFinal for FieldTokensLivecode {
             quote! {#serde #name: #new_ty}
         };
 
-        let for_world = {
+        let for_world = field_world(&name, {
             if how_to_control_internal.needs_to_be_evaluated() {
                 match wrapper {
                     VecDepth::NotAVec => unreachable!("not a vec in a vec?"),
                     VecDepth::Vec => {
                         if inner_is_lazy_struct {
                             quote! {
-                                #name: self.#name.iter()
+                                self.#name.iter()
                                     .map(|x| x.o(w))
                                     .collect::<Result<Vec<_>, _>>()?
 
                             }
                         } else {
                             quote! {
-                                #name: murrelet_livecode::types::eval_and_expand_vec_list(&self.#name, w)?
+                                murrelet_livecode::types::eval_and_expand_vec_list(&self.#name, w)?
                             }
                         }
                     }
                     VecDepth::VecVec => {
                         quote! {
-                            #name: {
+                            {
                                 let mut result = Vec::with_capacity(self.#name.len());
                                 for internal_row in &self.#name {
                                     result.push(
@@ -729,7 +749,7 @@ impl GenFinal for FieldTokensLivecode {
 
                     VecDepth::VecControlVec => {
                         quote! {
-                            #name: {
+                            {
                                 let mut result = Vec::with_capacity(self.#name.len());
                                 for internal_row in &self.#name {
                                     // DeserLazyControlVecElement -> LazyControlVecElement
@@ -742,9 +762,9 @@ impl GenFinal for FieldTokensLivecode {
                     }
                 }
             } else {
-                quote! {#name: self.#name.clone()}
+                quote! {self.#name.clone()}
             }
-        };
+        });
 
         let for_to_control = {
             if how_to_control_internal.needs_to_be_evaluated() {
@@ -901,6 +921,12 @@ impl GenFinal for FieldTokensLivecode {
             }
         };
 
+        // lazy things get their variables when they're evaluated
+        let for_variable_idents = if how_to_control_internal.is_lazy() || inner_is_lazy_struct {
+            quote! { murrelet_livecode::livecode::deferred_identifiers(#for_variable_idents) }
+        } else {
+            for_variable_idents
+        };
         FieldTokensLivecode {
             for_struct,
             for_world,
@@ -924,9 +950,7 @@ impl GenFinal for FieldTokensLivecode {
 
             quote! {#serde #name: #new_ty}
         };
-        let for_world = {
-            quote! {#name: self.#name.o(w)?}
-        };
+        let for_world = field_world(&name, quote! {self.#name.o(w)?});
         let for_to_control = {
             quote! {#name: self.#name.to_control()}
         };
@@ -994,10 +1018,10 @@ impl GenFinal for FieldTokensLivecode {
             })
             .unwrap_or(quote! {""});
 
-        let for_world = {
+        let for_world = field_world(&name, {
             // todo, these look like the same
             if how_to_control_internal.is_lazy() {
-                quote! {#name: {
+                quote! {{
                     murrelet_livecode::unitcells::TmpUnitCells::new(
                         self.#target.o(w)?,
                         Box::new(self.#name.clone()),
@@ -1006,7 +1030,7 @@ impl GenFinal for FieldTokensLivecode {
                     ).o(&w)? // maybe switch this?
                 }}
             } else {
-                quote! {#name: {
+                quote! {{
                     murrelet_livecode::unitcells::TmpUnitCells::new(
                         self.#target.o(w)?,
                         Box::new(self.#name.clone()),
@@ -1015,7 +1039,7 @@ impl GenFinal for FieldTokensLivecode {
                     ).o(&w)?
                 }}
             }
-        };
+        });
 
         let for_to_control = {
             if how_to_control_internal.is_lazy() {
@@ -1032,10 +1056,15 @@ impl GenFinal for FieldTokensLivecode {
         };
 
         // we just need to grab
+        // the unitcell provides some variables itself, so only keep the ones it needs
         let for_variable_idents = quote! {
             vec![
                 self.#target.variable_identifiers(),
-                self.#name.variable_identifiers()
+                murrelet_livecode::lint::without_unitcell_vars(
+                    self.#name.variable_identifiers(),
+                    #prefix,
+                    #maybe_more_ctx
+                )
             ].concat()
         };
         let for_function_idents = quote! {
@@ -1167,14 +1196,14 @@ impl GenFinal for FieldTokensLivecode {
 
             quote! {#serde #name: #new_ty}
         };
-        let for_world = {
-            quote! {#name: self.#name.o(w)?}
-        };
+        let for_world = field_world(&name, quote! {self.#name.o(w)?});
         let for_to_control = {
             quote! {#name: self.#name.to_control()}
         };
 
-        let for_variable_idents = quote! { self.#name.variable_identifiers() };
+        let for_variable_idents = quote! {
+            murrelet_livecode::livecode::deferred_identifiers(self.#name.variable_identifiers())
+        };
         let for_function_idents = quote! { self.#name.function_identifiers() };
 
         FieldTokensLivecode {
@@ -0,0 +1,270 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use crate::parser::*;
+
+// fields with #[livecode(transition = "...")] get their own timing when the
+// config reloads, everything else keeps the whole transition's lerp.
+// structs need to be marked "nested" if you want to use the settings inside them
+pub(crate) struct FieldTokensTransition {
+    pub(crate) for_transition: TokenStream2,
+    pub(crate) for_beats: TokenStream2, // updates `beats` if this field takes longer
+}
+
+enum TransitionKind {
+    Snap,
+    Nested,
+    Beats(f32),
+}
+impl TransitionKind {
+    fn parse(data: &LivecodeFieldReceiver) -> Option<TransitionKind> {
+        data.transition.as_ref().map(|t| match t.as_str() {
+            "snap" => TransitionKind::Snap,
+            "nested" => TransitionKind::Nested,
+            beats => TransitionKind::Beats(beats.parse().unwrap_or_else(|_| {
+                panic!(
+                    "transition should be snap, nested, or a number of beats, not {}",
+                    beats
+                )
+            })),
+        })
+    }
+
+    // how to get the new value, given how to get this, from, and to
+    fn transition(&self, this: TokenStream2, from: TokenStream2, to: TokenStream2) -> TokenStream2 {
+        match self {
+            TransitionKind::Snap => {
+                quote! { murrelet_livecode::transition::transition_snap(#from, #to, t) }
+            }
+            TransitionKind::Nested => {
+                quote! { murrelet_livecode::transition::Transitionable::transition(#this, #from, #to, t) }
+            }
+            TransitionKind::Beats(beats) => {
+                quote! { murrelet_livecode::transition::transition_over(#from, #to, #beats, t) }
+            }
+        }
+    }
+
+    fn beats(&self, ty: &syn::Type) -> TokenStream2 {
+        match self {
+            TransitionKind::Snap => quote! { beats },
+            TransitionKind::Nested => {
+                quote! { <#ty as murrelet_livecode::transition::Transitionable>::transition_beats(beats) }
+            }
+            TransitionKind::Beats(b) => quote! { beats.max(#b) },
+        }
+    }
+}
+
+impl FieldTokensTransition {
+    fn for_field(idents: &StructIdents) -> FieldTokensTransition {
+        let name = idents.name();
+
+        match TransitionKind::parse(&idents.data) {
+            Some(kind) => FieldTokensTransition {
+                for_transition: {
+                    let t = kind.transition(
+                        quote! { &self.#name },
+                        quote! { &from.#name },
+                        quote! { &to.#name },
+                    );
+                    quote! { #name: #t }
+                },
+                for_beats: kind.beats(&idents.orig_ty()),
+            },
+            None => FieldTokensTransition {
+                for_transition: quote! { #name: self.#name.clone() },
+                for_beats: quote! { beats },
+            },
+        }
+    }
+
+    fn for_newtype_field(idents: &StructIdents) -> FieldTokensTransition {
+        match TransitionKind::parse(&idents.data) {
+            Some(kind) => FieldTokensTransition {
+                for_transition: kind.transition(
+                    quote! { &self.0 },
+                    quote! { &from.0 },
+                    quote! { &to.0 },
+                ),
+                for_beats: kind.beats(&idents.orig_ty()),
+            },
+            None => FieldTokensTransition {
+                for_transition: quote! { self.0.clone() },
+                for_beats: quote! { beats },
+            },
+        }
+    }
+}
+
+impl GenFinal for FieldTokensTransition {
+    fn make_struct_final(
+        idents: ParsedFieldIdent,
+        variants: Vec<FieldTokensTransition>,
+    ) -> TokenStream2 {
+        let name = idents.name;
+        let for_transition = variants.iter().map(|x| x.for_transition.clone());
+        let for_beats = variants.iter().map(|x| x.for_beats.clone());
+
+        quote! {
+            impl murrelet_livecode::transition::Transitionable for #name {
+                #[allow(unused_variables)]
+                fn transition(&self, from: &Self, to: &Self, t: &murrelet_livecode::transition::TransitionState) -> Self {
+                    #name {
+                        #(#for_transition,)*
+                    }
+                }
+
+                fn transition_beats(beats: f32) -> f32 {
+                    #(let beats = #for_beats;)*
+                    beats
+                }
+            }
+        }
+    }
+
+    fn make_enum_final(
+        idents: ParsedFieldIdent,
+        variants: Vec<FieldTokensTransition>,
+    ) -> TokenStream2 {
+        let name = idents.name;
+        let for_transition = variants.iter().map(|x| x.for_transition.clone());
+        let for_beats = variants.iter().map(|x| x.for_beats.clone());
+
+        quote! {
+            impl murrelet_livecode::transition::Transitionable for #name {
+                #[allow(unused_variables)]
+                fn transition(&self, from: &Self, to: &Self, t: &murrelet_livecode::transition::TransitionState) -> Self {
+                    match (self, from, to) {
+                        #(#for_transition,)*
+                    }
+                }
+
+                fn transition_beats(beats: f32) -> f32 {
+                    #(let beats = #for_beats;)*
+                    beats
+                }
+            }
+        }
+    }
+
+    fn make_newtype_struct_final(
+        idents: ParsedFieldIdent,
+        variants: Vec<FieldTokensTransition>,
+    ) -> TokenStream2 {
+        let name = idents.name;
+        let for_transition = variants.iter().map(|x| x.for_transition.clone());
+        let for_beats = variants.iter().map(|x| x.for_beats.clone());
+
+        quote! {
+            impl murrelet_livecode::transition::Transitionable for #name {
+                #[allow(unused_variables)]
+                fn transition(&self, from: &Self, to: &Self, t: &murrelet_livecode::transition::TransitionState) -> Self {
+                    #name(#(#for_transition,)*)
+                }
+
+                fn transition_beats(beats: f32) -> f32 {
+                    #(let beats = #for_beats;)*
+                    beats
+                }
+            }
+        }
+    }
+
+    fn new_ident(name: syn::Ident) -> syn::Ident {
+        name.clone()
+    }
+
+    fn from_newtype_struct(
+        idents: StructIdents,
+        _parent_ident: syn::Ident,
+    ) -> FieldTokensTransition {
+        Self::for_newtype_field(&idents)
+    }
+
+    fn from_newtype_struct_struct(
+        idents: StructIdents,
+        _parent_ident: syn::Ident,
+    ) -> FieldTokensTransition {
+        Self::for_newtype_field(&idents)
+    }
+
+    fn from_newtype_struct_lazy(
+        idents: StructIdents,
+        _parent_ident: syn::Ident,
+    ) -> FieldTokensTransition {
+        Self::for_newtype_field(&idents)
+    }
+
+    fn from_newtype_recurse_struct_vec(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_newtype_field(&idents)
+    }
+
+    // e.g. TileAxisLocs::V(TileAxisVs), only if all three are the same variant
+    fn from_unnamed_enum(idents: EnumIdents) -> FieldTokensTransition {
+        let variant_ident = idents.variant_ident();
+        let name = idents.enum_ident();
+
+        let unnamed = idents.data.fields.fields;
+        if unnamed.len() != 1 {
+            panic!("multiple fields not supported")
+        };
+        let field = unnamed.first().unwrap();
+
+        match TransitionKind::parse(field) {
+            Some(kind) => {
+                let t = kind.transition(quote! { e }, quote! { f }, quote! { o });
+                FieldTokensTransition {
+                    for_transition: quote! {
+                        (#name::#variant_ident(e), #name::#variant_ident(f), #name::#variant_ident(o)) => #name::#variant_ident(#t),
+                        (#name::#variant_ident(e), _, _) => #name::#variant_ident(e.clone())
+                    },
+                    for_beats: kind.beats(&field.ty),
+                }
+            }
+            None => FieldTokensTransition {
+                for_transition: quote! { (#name::#variant_ident(e), _, _) => #name::#variant_ident(e.clone()) },
+                for_beats: quote! { beats },
+            },
+        }
+    }
+
+    // e.g. TileAxis::Diag
+    fn from_unit_enum(idents: EnumIdents) -> FieldTokensTransition {
+        let variant_ident = idents.variant_ident();
+        let name = idents.enum_ident();
+
+        FieldTokensTransition {
+            for_transition: quote! { (#name::#variant_ident, _, _) => #name::#variant_ident },
+            for_beats: quote! { beats },
+        }
+    }
+
+    fn from_noop_struct(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_field(&idents)
+    }
+
+    fn from_type_struct(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_field(&idents)
+    }
+
+    fn from_option(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_field(&idents)
+    }
+
+    fn from_recurse_struct_vec(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_field(&idents)
+    }
+
+    fn from_recurse_struct_struct(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_field(&idents)
+    }
+
+    fn from_recurse_struct_unitcell(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_field(&idents)
+    }
+
+    fn from_recurse_struct_lazy(idents: StructIdents) -> FieldTokensTransition {
+        Self::for_field(&idents)
+    }
+}
@@ -5,19 +5,23 @@
 
 extern crate proc_macro;
 
+mod derive_boop;
 mod derive_cached;
 mod derive_graphics_trait;
 mod derive_lazy;
 mod derive_livecode;
 mod derive_nestedit;
+mod derive_transition;
 mod parser;
 mod toplevel;
 
 use darling::FromDeriveInput;
+use derive_boop::FieldTokensBoop;
 use derive_graphics_trait::impl_graphics_trait;
 use derive_lazy::FieldTokensLazy;
 use derive_livecode::FieldTokensLivecode;
 use derive_nestedit::FieldTokensNestEdit;
+use derive_transition::FieldTokensTransition;
 use parser::{GenFinal, LivecodeReceiver};
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
@@ -40,6 +44,14 @@ fn nestedit_parse_ast(rec: LivecodeReceiver) -> TokenStream2 {
     FieldTokensNestEdit::from_ast(rec)
 }
 
+fn boop_parse_ast(rec: LivecodeReceiver) -> TokenStream2 {
+    FieldTokensBoop::from_ast(rec)
+}
+
+fn transition_parse_ast(rec: LivecodeReceiver) -> TokenStream2 {
+    FieldTokensTransition::from_ast(rec)
+}
+
 // derives all of the macros I usually need
 #[proc_macro_derive(Livecode, attributes(livecode))]
 pub fn murrelet_livecode_derive_all(input: TokenStream) -> TokenStream {
@@ -49,11 +61,15 @@ pub fn murrelet_livecode_derive_all(input: TokenStream) -> TokenStream {
     let livecode = livecode_parse_ast(ast_receiver.clone());
     let nested = nestedit_parse_ast(ast_receiver.clone());
     let lazy = lazy_parse_ast(ast_receiver.clone());
+    let boop = boop_parse_ast(ast_receiver.clone());
+    let transition = transition_parse_ast(ast_receiver.clone());
 
     quote!(
         #livecode
         #nested
         #lazy
+        #boop
+        #transition
     )
     .into()
 }
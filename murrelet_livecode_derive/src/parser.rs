@@ -280,6 +280,9 @@ pub(crate) struct LivecodeFieldReceiver {
     pub(crate) prefix: Option<String>, // what to prefix the src with
     pub(crate) f32min: Option<f32>,    // only used if it's a f32
     pub(crate) f32max: Option<f32>,
+    #[darling(default)]
+    pub(crate) boop: bool, // smooth this field between frames
+    pub(crate) transition: Option<String>, // "snap", "nested", or a number of beats
 }
 impl LivecodeFieldReceiver {
     fn back_to_quote_for_lazy(&self) -> TokenStream2 {
@@ -18,7 +18,7 @@ pub(crate) fn top_level_livecode(ident: syn::Ident) -> TokenStream2 {
             fn parse(text: &str) -> murrelet_livecode::types::LivecodeResult<Self> {
                 serde_yaml::from_str(&text).map_err(|err| {
                     let line = if let Some(location) = err.location() {
-                        format!("{},{}", location.line(), location.column())
+                        murrelet_livecode::sourcemap::yaml_loc_string(location.line(), location.column())
                     } else {
                         "".to_owned()
                     };
@@ -33,7 +33,11 @@ pub(crate) fn top_level_livecode(ident: syn::Ident) -> TokenStream2 {
 
         impl murrelet_perform::perform::CommonTrait for #conf_ident {}
         impl murrelet_perform::perform::CommonTrait for #control_ident {}
-        impl murrelet_perform::perform::LiveCodeCommon<#conf_ident> for #control_ident {}
+        impl murrelet_perform::perform::LiveCodeCommon<#conf_ident> for #control_ident {
+            fn from_conf(conf: &#conf_ident) -> Self {
+                murrelet_livecode::livecode::LivecodeToControl::to_control(conf)
+            }
+        }
     }
 }
 
@@ -61,7 +65,11 @@ pub(crate) fn top_level_livecode_json(ident: syn::Ident) -> TokenStream2 {
 
         impl murrelet_perform::perform::CommonTrait for #conf_ident {}
         impl murrelet_perform::perform::CommonTrait for #control_ident {}
-        impl murrelet_perform::perform::LiveCodeCommon<#conf_ident> for #control_ident {}
+        impl murrelet_perform::perform::LiveCodeCommon<#conf_ident> for #control_ident {
+            fn from_conf(conf: &#conf_ident) -> Self {
+                murrelet_livecode::livecode::LivecodeToControl::to_control(conf)
+            }
+        }
 
 
     }
@@ -78,6 +86,10 @@ pub(crate) fn impl_all_the_traits(ident: syn::Ident) -> TokenStream2 {
             fn config_app_loc(&self) -> &murrelet_perform::perform::AppConfig { &self.app }
         }
         impl murrelet_perform::perform::CommonTrait for #control_ident {}
-        impl murrelet_perform::perform::LiveCodeCommon<#conf_ident> for #control_ident {}
+        impl murrelet_perform::perform::LiveCodeCommon<#conf_ident> for #control_ident {
+            fn from_conf(conf: &#conf_ident) -> Self {
+                murrelet_livecode::livecode::LivecodeToControl::to_control(conf)
+            }
+        }
     }
 }
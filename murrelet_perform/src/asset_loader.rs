@@ -1,4 +1,10 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use itertools::Itertools;
 use lerpable::Lerpable;
@@ -6,16 +12,46 @@ use murrelet_common::{
     Assets, JsonAssetLookup, RasterAsset, RasterAssetLookup, VectorAsset, VectorLayersAssetLookup,
 };
 use murrelet_gui::CanMakeGUI;
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
 use murrelet_livecode_derive::Livecode;
 
+use crate::watch::watch_key;
+
 pub trait VectorAssetLoader {
     fn is_match(&self, file_extension: &str) -> bool;
     fn load(&self, layers: &[&str], filename: &Path) -> VectorAsset;
+
+    // used when reloading, so a file that doesn't load keeps the old version
+    // instead of stopping the sketch. by default, catches a panic in load
+    fn try_load(&self, layers: &[&str], filename: &Path) -> LivecodeResult<VectorAsset> {
+        catch_load(filename, || self.load(layers, filename))
+    }
 }
 
 pub trait RasterAssetLoader {
     fn is_match(&self, file_extension: &str) -> bool;
     fn load(&self, filename: &Path) -> RasterAsset;
+
+    // same as VectorAssetLoader::try_load
+    fn try_load(&self, filename: &Path) -> LivecodeResult<RasterAsset> {
+        catch_load(filename, || self.load(filename))
+    }
+}
+
+fn catch_load<T>(filename: &Path, load: impl FnOnce() -> T) -> LivecodeResult<T> {
+    // the usual one, the file is gone for a moment while it's saved
+    fs::metadata(filename)
+        .map_err(|err| LivecodeError::Io(format!("couldn't read {:?}", filename), err))?;
+    std::panic::catch_unwind(AssertUnwindSafe(load))
+        .map_err(|_| LivecodeError::Raw(format!("couldn't load {:?}", filename)))
+}
+
+// assets are looked up by the filename without the extension
+fn asset_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
 }
 
 #[derive(Livecode, Lerpable, Clone, Debug)]
@@ -76,9 +112,11 @@ pub fn _empty_filenames_lazy() -> ControlLazyAssetFilenames {
     }
 }
 
+// cloned so the LiveCoder can keep them around for reloading
+#[derive(Clone)]
 pub struct AssetLoaders {
-    vector: Vec<Box<dyn VectorAssetLoader>>,
-    raster: Vec<Box<dyn RasterAssetLoader>>,
+    vector: Vec<Arc<dyn VectorAssetLoader>>,
+    raster: Vec<Arc<dyn RasterAssetLoader>>,
 }
 
 impl AssetLoaders {
@@ -86,7 +124,10 @@ impl AssetLoaders {
         vector: Vec<Box<dyn VectorAssetLoader>>,
         raster: Vec<Box<dyn RasterAssetLoader>>,
     ) -> Self {
-        Self { vector, raster }
+        Self {
+            vector: vector.into_iter().map(Arc::from).collect(),
+            raster: raster.into_iter().map(Arc::from).collect(),
+        }
     }
 
     pub fn empty() -> AssetLoaders {
@@ -114,6 +155,16 @@ impl AssetFilenames {
         }
     }
 
+    // the files load_polylines reads
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.vector_files
+            .iter()
+            .map(|f| f.path())
+            .chain(self.raster_files.iter().map(|f| f.path()))
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
     pub fn load_polylines(&self, load_funcs: &AssetLoaders) -> Assets {
         let mut m = HashMap::new();
         for filename in &self.vector_files {
@@ -126,13 +177,8 @@ impl AssetFilenames {
                 let ext_str = ext.to_str();
                 for func in &load_funcs.vector {
                     if func.is_match(ext_str.unwrap()) {
-                        let filename_stem = path
-                            .file_stem()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .into_owned();
                         let layers: Vec<&str> = filename.layers.split(",").collect_vec();
-                        m.insert(filename_stem, func.load(&layers, path));
+                        m.insert(asset_name(path), func.load(&layers, path));
                     }
                 }
             }
@@ -149,12 +195,7 @@ impl AssetFilenames {
                 let ext_str = ext.to_str();
                 for func in &load_funcs.raster {
                     if func.is_match(ext_str.unwrap()) {
-                        let filename_stem = path
-                            .file_stem()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .into_owned();
-                        raster.insert(filename_stem, func.load(path));
+                        raster.insert(asset_name(path), func.load(path));
                     }
                 }
             }
@@ -167,6 +208,70 @@ impl AssetFilenames {
 
         Assets::new(polylines, raster, json)
     }
+
+    // loads the files in `changed` again. if one doesn't load, the version
+    // that's already in `assets` stays, and the error is returned
+    pub fn reload(
+        &self,
+        changed: &[PathBuf],
+        load_funcs: &AssetLoaders,
+        assets: &mut Assets,
+    ) -> Vec<LivecodeError> {
+        let is_changed = |path: &Path| changed.contains(&watch_key(path));
+        let ext = |path: &Path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default()
+                .to_owned()
+        };
+
+        let mut errors = vec![];
+        for filename in &self.vector_files {
+            let path = filename.path();
+            if !is_changed(path) {
+                continue;
+            }
+            // the last one that matches, same as load_polylines
+            let Some(func) = load_funcs
+                .vector
+                .iter()
+                .rev()
+                .find(|f| f.is_match(&ext(path)))
+            else {
+                continue;
+            };
+
+            println!("reloading vector file {:?}", path);
+            let layers: Vec<&str> = filename.layers.split(",").collect_vec();
+            match func.try_load(&layers, path) {
+                Ok(asset) => assets.insert_vector(asset_name(path), asset),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        for filename in &self.raster_files {
+            let path = filename.path();
+            if !is_changed(path) {
+                continue;
+            }
+            let Some(func) = load_funcs
+                .raster
+                .iter()
+                .rev()
+                .find(|f| f.is_match(&ext(path)))
+            else {
+                continue;
+            };
+
+            println!("reloading raster file {:?}", path);
+            match func.try_load(path) {
+                Ok(asset) => assets.insert_raster(asset_name(path), asset),
+                Err(err) => errors.push(err),
+            }
+        }
+
+        errors
+    }
 }
 
 impl CanMakeGUI for AssetFilenames {
@@ -174,3 +279,80 @@ impl CanMakeGUI for AssetFilenames {
         murrelet_gui::MurreletGUISchema::Skip
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec2;
+
+    // reads a number from the file, and panics if it isn't one
+    struct NumberLoader;
+    impl VectorAssetLoader for NumberLoader {
+        fn is_match(&self, file_extension: &str) -> bool {
+            file_extension == "num"
+        }
+
+        fn load(&self, layers: &[&str], filename: &Path) -> VectorAsset {
+            let x: f32 = fs::read_to_string(filename)
+                .unwrap()
+                .trim()
+                .parse()
+                .unwrap();
+            let m = layers
+                .iter()
+                .map(|l| (l.to_string(), vec![vec![vec2(x, 0.0)]]))
+                .collect();
+            VectorAsset::from_data(layers.iter().map(|l| l.to_string()).collect(), m)
+        }
+    }
+
+    fn first_x(assets: &Assets) -> f32 {
+        assets.asset_layer("shape", 0).unwrap()[0]
+            .first()
+            .unwrap()
+            .x
+    }
+
+    #[test]
+    fn reload_keeps_the_old_version_on_errors() {
+        let dir = std::env::temp_dir().join(format!("murrelet_assets_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("shape.num");
+        fs::write(&path, "1").unwrap();
+
+        let filenames = AssetFilenames {
+            vector_files: vec![PolylineLayerFile::new(
+                path.to_string_lossy().into_owned(),
+                "a".to_owned(),
+            )],
+            raster_files: vec![],
+            json_files: vec![],
+        };
+        let load_funcs = AssetLoaders::new(vec![Box::new(NumberLoader)], vec![]);
+        let mut assets = filenames.load_polylines(&load_funcs);
+        assert_eq!(first_x(&assets), 1.0);
+
+        // only files that changed are loaded again
+        fs::write(&path, "2").unwrap();
+        let errors = filenames.reload(&[dir.join("other.num")], &load_funcs, &mut assets);
+        assert!(errors.is_empty());
+        assert_eq!(first_x(&assets), 1.0);
+
+        let changed = vec![watch_key(&path)];
+        let errors = filenames.reload(&changed, &load_funcs, &mut assets);
+        assert!(errors.is_empty());
+        assert_eq!(first_x(&assets), 2.0);
+
+        fs::write(&path, "not a number").unwrap();
+        let errors = filenames.reload(&changed, &load_funcs, &mut assets);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(first_x(&assets), 2.0);
+
+        fs::remove_file(&path).unwrap();
+        let errors = filenames.reload(&changed, &load_funcs, &mut assets);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(first_x(&assets), 2.0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
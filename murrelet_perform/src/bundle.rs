@@ -0,0 +1,227 @@
+// what's needed to remake a capture, written into the capture folder next to
+// the frames.
+//
+//   <capture folder>/bundle/run.yaml          cli args and timing
+//   <capture folder>/bundle/config_<id>.yaml  each config used, templates filled in
+//   <capture folder>/bundle/templates/        the template files they used
+//   <capture folder>/bundle/shaders/          shaders the sketch added
+//   <capture folder>/<frame>.frame.yaml       next to each frame
+//
+// the frame file has the inputs in the same format as --record-inputs, so
+// `--replay-inputs <frame>.frame.yaml` with the bundle's config remakes that
+// frame (e.g. with Headless and --frames N..N+1).
+//
+// replaying goes by frame, so this only remakes the frame exactly if it was
+// captured with app.time.realtime off. otherwise the time is whatever the
+// clock said, and the frame file's `seconds` and `timing.realtime` are there
+// to tell you how far off the replay will be.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use murrelet_common::{LivecodeValue, MurreletAppInput};
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+use serde::Serialize;
+
+use crate::cli::BaseConfigArgs;
+use crate::load::template_files;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BundleTiming {
+    pub bpm: f32,
+    pub beats_per_bar: f32,
+    pub fps: f32,
+    pub realtime: bool,
+}
+
+#[derive(Serialize)]
+struct BundleRun<'a> {
+    args: Option<&'a BaseConfigArgs>,
+    timing: BundleTiming,
+}
+
+// app and vals are the same as a RecordedFrame, so it can be replayed
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameSidecar {
+    pub config: String, // in the bundle folder
+    pub seed: f32,
+    pub timing: BundleTiming,
+    pub seconds: f32,
+    pub beat: f32,
+    pub app: MurreletAppInput,
+    pub vals: Vec<(String, LivecodeValue)>, // from the livecode sources
+    pub world: Vec<(String, LivecodeValue)>, // everything the config could use
+}
+
+fn write_file<P: AsRef<Path>>(path: P, contents: &str) -> LivecodeResult<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| LivecodeError::Io(format!("couldn't make {:?}", parent), err))?;
+    }
+    std::fs::write(path, contents)
+        .map_err(|err| LivecodeError::Io(format!("couldn't write {:?}", path), err))
+}
+
+fn to_yaml<T: Serialize>(t: &T) -> LivecodeResult<String> {
+    serde_yaml::to_string(t)
+        .map_err(|err| LivecodeError::Raw(format!("couldn't write the bundle: {}", err)))
+}
+
+// keeps track of what's already been written, so each capture only adds the
+// frame file and anything new
+#[derive(Debug, Clone)]
+pub struct CaptureBundle {
+    capture_folder: PathBuf,
+    wrote_run: bool,
+    written_configs: HashSet<u64>,
+    shaders: Vec<(String, String)>, // name, source
+    written_shaders: usize,
+}
+impl CaptureBundle {
+    pub fn new(capture_folder: PathBuf) -> Self {
+        Self {
+            capture_folder,
+            wrote_run: false,
+            written_configs: HashSet::new(),
+            shaders: vec![],
+            written_shaders: 0,
+        }
+    }
+
+    fn folder(&self) -> PathBuf {
+        self.capture_folder.join("bundle")
+    }
+
+    pub fn add_shader(&mut self, name: &str, source: &str) {
+        self.shaders.push((name.to_owned(), source.to_owned()));
+    }
+
+    pub fn write_run(
+        &mut self,
+        args: Option<&BaseConfigArgs>,
+        timing: BundleTiming,
+    ) -> LivecodeResult<()> {
+        if !self.wrote_run {
+            write_file(
+                self.folder().join("run.yaml"),
+                &to_yaml(&BundleRun { args, timing })?,
+            )?;
+            self.wrote_run = true;
+        }
+
+        for (name, source) in &self.shaders[self.written_shaders..] {
+            write_file(self.folder().join("shaders").join(name), source)?;
+        }
+        self.written_shaders = self.shaders.len();
+        Ok(())
+    }
+
+    // `raw_config` gives the file before the templates were filled in, to find
+    // which ones it used. it's only called the first time we see `id`.
+    // returns the config's name in the bundle
+    pub fn write_config<P, F>(
+        &mut self,
+        id: u64,
+        yaml: &str,
+        raw_config: F,
+        includes_dir: P,
+    ) -> LivecodeResult<String>
+    where
+        P: AsRef<Path>,
+        F: FnOnce() -> String,
+    {
+        let name = format!("config_{}.yaml", id);
+        if self.written_configs.insert(id) {
+            write_file(self.folder().join(&name), yaml)?;
+
+            let templates = self.folder().join("templates");
+            for template in template_files(&raw_config(), includes_dir) {
+                if let Some(file_name) = template.file_name() {
+                    let dest = templates.join(file_name);
+                    std::fs::create_dir_all(&templates).map_err(|err| {
+                        LivecodeError::Io(format!("couldn't make {:?}", templates), err)
+                    })?;
+                    std::fs::copy(&template, &dest).map_err(|err| {
+                        LivecodeError::Io(format!("couldn't copy {:?}", template), err)
+                    })?;
+                }
+            }
+        }
+        Ok(format!("bundle/{}", name))
+    }
+
+    // `frame_path` is the frame's capture name, without the extension
+    pub fn write_frame(&self, frame_path: &Path, sidecar: &FrameSidecar) -> LivecodeResult<()> {
+        write_file(frame_path.with_extension("frame.yaml"), &to_yaml(sidecar)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::InputReplay;
+    use std::cell::Cell;
+
+    fn temp_folder(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("murrelet_bundle_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn configs_are_only_read_once() {
+        let folder = temp_folder("configs");
+        let mut bundle = CaptureBundle::new(folder.clone());
+        let reads = Cell::new(0);
+        let raw_config = || {
+            reads.set(reads.get() + 1);
+            "a: 1\n".to_owned()
+        };
+
+        for _ in 0..3 {
+            let name = bundle
+                .write_config(4, "a: 1\n", raw_config, &folder)
+                .unwrap();
+            assert_eq!(name, "bundle/config_4.yaml");
+        }
+        assert_eq!(reads.get(), 1);
+        bundle
+            .write_config(5, "a: 2\n", raw_config, &folder)
+            .unwrap();
+        assert_eq!(reads.get(), 2);
+        assert_eq!(
+            std::fs::read_to_string(folder.join("bundle/config_5.yaml")).unwrap(),
+            "a: 2\n"
+        );
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn frame_files_replay() {
+        let folder = temp_folder("frames");
+        let bundle = CaptureBundle::new(folder.clone());
+        let vals = vec![("knob".to_owned(), LivecodeValue::Float(0.25))];
+        let sidecar = FrameSidecar {
+            config: "bundle/config_0.yaml".to_owned(),
+            seed: 3.0,
+            timing: BundleTiming {
+                bpm: 120.0,
+                beats_per_bar: 4.0,
+                fps: 60.0,
+                realtime: false,
+            },
+            seconds: 0.5,
+            beat: 1.0,
+            app: MurreletAppInput::default_with_frames(30),
+            vals: vals.clone(),
+            world: vec![],
+        };
+        bundle.write_frame(&folder.join("30"), &sidecar).unwrap();
+
+        let replay = InputReplay::load(folder.join("30.frame.yaml")).unwrap();
+        let frame = replay.get(30).unwrap();
+        assert_eq!(frame.frame(), 30);
+        assert_eq!(frame.vals(), vals.as_slice());
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,347 @@
+// checks that configs load and evaluate, without a window, for pre-commit
+// hooks and CI.
+//
+//   cargo run --bin check -- config.yaml other.yaml --templates templates/ --frames 0,60,600
+//
+// each config is preprocessed and parsed, then evaluated at each frame in a
+// world without live inputs (like new_dummy_with_funcs, but with the
+// config's own functions, ctx, timeline and envelopes). variables from live
+// inputs (midi, osc...) aren't there, so they're 0.
+//
+// evaluating stops at the first error, so after one the field is swapped for
+// 0 and it tries again, to find the rest.
+//
+// lazy fields aren't evaluated, since the sketch does that with its own
+// variables, so errors in those only show up when it runs.
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+use murrelet_livecode::types::LivecodeError;
+
+use crate::load::preprocess_yaml_with_source_map;
+use crate::perform::{ConfCommon, LiveCodeCommon};
+
+// if a config has more than this many errors, it's probably not worth listing
+const MAX_ERRORS_PER_FRAME: usize = 100;
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct CheckArgs {
+    #[arg(required = true)]
+    pub config_paths: Vec<PathBuf>,
+    #[arg(long, default_value = "templates")]
+    pub templates: PathBuf,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "0,1,60,600",
+        help = "which frames to evaluate at, like 0,60,600"
+    )]
+    pub frames: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckError {
+    pub config: PathBuf,
+    pub frames: Vec<u64>,     // empty if it didn't parse
+    pub path: Option<String>, // the field, like circles.size
+    pub message: String,
+}
+impl CheckError {
+    fn new(config: &Path, frame: Option<u64>, err: &LivecodeError) -> Self {
+        let (path, message) = match err {
+            LivecodeError::Field(path, err) => (Some(path.clone()), err.to_string()),
+            err => (None, err.to_string()),
+        };
+        Self {
+            config: config.to_owned(),
+            frames: frame.into_iter().collect(),
+            path,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.config.display())?;
+        if !self.frames.is_empty() {
+            let frames = self
+                .frames
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>();
+            write!(f, " (frame {})", frames.join(", "))?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, ": {}", path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+pub struct ConfigCheck<ConfType, ControlConfType> {
+    args: CheckArgs,
+    _conf: PhantomData<(ConfType, ControlConfType)>,
+}
+impl<ConfType, ControlConfType> ConfigCheck<ConfType, ControlConfType>
+where
+    ConfType: ConfCommon,
+    ControlConfType: LiveCodeCommon<ConfType>,
+{
+    pub fn new() -> Self {
+        Self::new_with_args(CheckArgs::parse())
+    }
+
+    pub fn new_with_args(args: CheckArgs) -> Self {
+        Self {
+            args,
+            _conf: PhantomData,
+        }
+    }
+
+    // prints the errors, and exits with 1 if there were any. use as main
+    pub fn run(&self) -> ExitCode {
+        let errors = self.check();
+        for err in &errors {
+            eprintln!("error: {}", err);
+        }
+
+        let count = self.args.config_paths.len();
+        if errors.is_empty() {
+            println!("checked {} config(s), no errors", count);
+            ExitCode::SUCCESS
+        } else {
+            eprintln!("checked {} config(s), {} error(s)", count, errors.len());
+            ExitCode::FAILURE
+        }
+    }
+
+    pub fn check(&self) -> Vec<CheckError> {
+        self.args
+            .config_paths
+            .iter()
+            .flat_map(|path| self.check_file(path))
+            .collect()
+    }
+
+    pub fn check_file(&self, path: &Path) -> Vec<CheckError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => self.check_text(path, &text),
+            Err(err) => vec![CheckError::new(
+                path,
+                None,
+                &LivecodeError::Io(format!("couldn't read {:?}", path), err),
+            )],
+        }
+    }
+
+    // `path` is just used for messages and the source map
+    pub fn check_text(&self, path: &Path, text: &str) -> Vec<CheckError> {
+        let (preprocessed, source_map) = match preprocess_yaml_with_source_map(
+            text,
+            &path.to_string_lossy(),
+            &self.args.templates,
+        ) {
+            Ok(x) => x,
+            Err(err) => return vec![CheckError::new(path, None, &err)],
+        };
+
+        let conf = match ControlConfType::parse_with_source(&preprocessed, source_map) {
+            Ok(conf) => conf,
+            Err(err) => return vec![CheckError::new(path, None, &err)],
+        };
+        // fields get swapped out of this to find more errors
+        let yaml = serde_yaml::from_str::<serde_yaml::Value>(&preprocessed).ok();
+
+        // the same error at a few frames is listed once
+        let mut errors: BTreeMap<(Option<String>, String), CheckError> = BTreeMap::new();
+        for &frame in &self.args.frames {
+            for err in check_frame::<ConfType, ControlConfType>(&conf, yaml.as_ref(), frame) {
+                let err = CheckError::new(path, Some(frame), &err);
+                errors
+                    .entry((err.path.clone(), err.message.clone()))
+                    .and_modify(|e| e.frames.push(frame))
+                    .or_insert(err);
+            }
+        }
+        errors.into_values().collect()
+    }
+}
+
+impl<ConfType, ControlConfType> Default for ConfigCheck<ConfType, ControlConfType>
+where
+    ConfType: ConfCommon,
+    ControlConfType: LiveCodeCommon<ConfType>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// every error evaluating at `frame`, swapping out each broken field to get
+// to the next one
+fn check_frame<ConfType, ControlConfType>(
+    conf: &ControlConfType,
+    yaml: Option<&serde_yaml::Value>,
+    frame: u64,
+) -> Vec<LivecodeError>
+where
+    ConfType: ConfCommon,
+    ControlConfType: LiveCodeCommon<ConfType>,
+{
+    let mut errors = vec![];
+    let mut conf = conf.clone();
+    let mut yaml = yaml.cloned();
+    let mut seen_paths = HashSet::new();
+
+    while errors.len() < MAX_ERRORS_PER_FRAME {
+        let used_vars = conf
+            .variable_identifiers()
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+        let result = conf
+            ._app_config()
+            .world_without_inputs(frame, &used_vars)
+            .and_then(|w| conf.o(&w));
+        let Err(err) = result else {
+            break;
+        };
+
+        // try again without that field, if we can find it
+        let next = match (err.field_path(), yaml.as_mut()) {
+            (Some(path), Some(yaml)) if seen_paths.insert(path.to_owned()) => knock_out(yaml, path)
+                .then(|| serde_yaml::to_string(yaml).ok())
+                .flatten()
+                .and_then(|s| ControlConfType::parse(&s).ok()),
+            _ => None,
+        };
+        errors.push(err);
+
+        match next {
+            Some(next) => conf = next,
+            None => break,
+        }
+    }
+    errors
+}
+
+// sets the value at the field path to 0. enum variants in the path might
+// not be in the yaml (e.g. with a `type:` tag), so those get skipped
+fn knock_out(yaml: &mut serde_yaml::Value, path: &str) -> bool {
+    let mut curr = yaml;
+    for key in path.split('.') {
+        let found = match curr {
+            serde_yaml::Value::Sequence(s) => key.parse::<usize>().is_ok_and(|i| i < s.len()),
+            serde_yaml::Value::Mapping(m) => m.contains_key(key),
+            _ => false,
+        };
+        if found {
+            curr = match curr {
+                serde_yaml::Value::Sequence(s) => &mut s[key.parse::<usize>().unwrap()],
+                serde_yaml::Value::Mapping(m) => m.get_mut(key).unwrap(),
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    match curr {
+        serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_) => false,
+        leaf => {
+            *leaf = serde_yaml::Value::Number(0.into());
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perform::tests::{APP, Conf, ControlConf};
+    use crate::reload::LiveCoderLoader;
+
+    fn yaml(s: &str) -> serde_yaml::Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn knock_out_finds_fields() {
+        let mut conf = yaml("circles:\n  size: nope(t)\n  points: [1, 2 +]\n");
+        assert!(knock_out(&mut conf, "circles.size"));
+        assert!(knock_out(&mut conf, "circles.points.1"));
+        assert_eq!(conf, yaml("circles:\n  size: 0\n  points: [1, 0]\n"));
+    }
+
+    #[test]
+    fn knock_out_skips_variants() {
+        let mut conf = yaml("shape:\n  type: Circle\n  r: 1 +\n");
+        assert!(knock_out(&mut conf, "shape.Circle.r"));
+        assert_eq!(conf, yaml("shape:\n  type: Circle\n  r: 0\n"));
+
+        // can't swap out a whole list
+        assert!(!knock_out(&mut yaml("points: [1, 2]"), "points"));
+    }
+
+    #[test]
+    fn finds_every_broken_field() {
+        let dir = std::env::temp_dir().join(format!("murrelet_check_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.yaml");
+        let text = format!("{}size: nope(t)\n", APP).replace("bg_alpha: 0.0", "bg_alpha: 1 +");
+        std::fs::write(&config, &text).unwrap();
+
+        let check = ConfigCheck::<Conf, ControlConf>::new_with_args(
+            CheckArgs::try_parse_from([
+                "check".as_ref(),
+                config.as_os_str(),
+                "--templates".as_ref(),
+                dir.as_os_str(),
+                "--frames".as_ref(),
+                "0,60".as_ref(),
+            ])
+            .unwrap(),
+        );
+
+        // each one is found, even though the first stops evaluating, and
+        // they're listed once for both frames
+        let errors = check.check_text(&config, &text);
+        let found = errors
+            .iter()
+            .map(|e| (e.path.as_deref(), e.frames.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (Some("app.bg_alpha"), vec![0, 60]),
+                (Some("size"), vec![0, 60])
+            ],
+            "{:?}",
+            errors
+        );
+        assert!(errors.iter().all(|e| e.config == config));
+
+        // one frame on its own
+        assert_eq!(
+            check_frame::<Conf, ControlConf>(
+                &ControlConf::parse(&text).unwrap(),
+                Some(&yaml(&text)),
+                0
+            )
+            .len(),
+            2
+        );
+
+        assert_eq!(check.check(), errors);
+        assert_eq!(check.run(), ExitCode::FAILURE);
+
+        std::fs::write(&config, format!("{}size: t\n", APP)).unwrap();
+        assert_eq!(check.run(), ExitCode::SUCCESS);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,8 +1,9 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
 
 use clap::Parser;
+use serde::Serialize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct TextureDimensions {
     pub width: u32,
     pub height: u32,
@@ -53,11 +54,29 @@ impl Default for TextureDimensions {
     }
 }
 
-#[derive(Parser, Debug, Clone)]
+// `--set circles.size=3`
+fn parse_set(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(path, value)| (path.trim().to_owned(), value.trim().to_owned()))
+        .ok_or_else(|| format!("expected path=value, got {:?}", s))
+}
+
+#[derive(Parser, Debug, Clone, Serialize)]
 #[command(author, version, about, long_about = None, allow_hyphen_values = true)]
 pub struct BaseConfigArgs {
     pub config_path: PathBuf,
     pub template_path: PathBuf, // todo, i probably should drop this
+    #[arg(
+        long = "overlay",
+        help = "a config to merge on top of the first one, can be given more than once"
+    )]
+    pub overlays: Vec<PathBuf>,
+    #[arg(
+        long = "set",
+        value_parser = parse_set,
+        help = "sets a field after evaluating, like circles.size=3, can be given more than once"
+    )]
+    pub sets: Vec<(String, String)>,
     #[arg(long, help = "record video")]
     pub capture: bool,
 
@@ -69,6 +88,11 @@ pub struct BaseConfigArgs {
     #[arg(long)]
     pub earlystop: Option<u64>,
 
+    #[arg(long, help = "write the live inputs to this file every frame")]
+    pub record_inputs: Option<PathBuf>,
+    #[arg(long, help = "use the live inputs recorded in this file")]
+    pub replay_inputs: Option<PathBuf>,
+
     #[arg(trailing_var_arg = true)]
     pub sketch_args: Vec<String>,
 }
@@ -80,6 +104,18 @@ impl BaseConfigArgs {
         }
     }
 
+    // the config and its overlays, in the order they're merged
+    pub fn config_paths(&self) -> Vec<PathBuf> {
+        std::iter::once(self.config_path.clone())
+            .chain(self.overlays.iter().cloned())
+            .collect()
+    }
+
+    // for nest_update, later ones win
+    pub fn overrides(&self) -> HashMap<String, String> {
+        self.sets.iter().cloned().collect()
+    }
+
     #[allow(dead_code)]
     pub(crate) fn config_path(&self) -> PathBuf {
         self.config_path.clone()
@@ -90,3 +126,38 @@ impl BaseConfigArgs {
         self.capture
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlays_and_sets() {
+        let args = BaseConfigArgs::try_parse_from([
+            "sketch",
+            "base.yaml",
+            "templates",
+            "--overlay",
+            "venue.yaml",
+            "--set",
+            "circles.size=3",
+            "--overlay",
+            "print.yaml",
+            "--set",
+            "circles.size = 4",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.config_paths(),
+            vec![
+                PathBuf::from("base.yaml"),
+                PathBuf::from("venue.yaml"),
+                PathBuf::from("print.yaml")
+            ]
+        );
+        assert_eq!(args.overrides()["circles.size"], "4");
+
+        assert!(BaseConfigArgs::try_parse_from(["sketch", "a", "b", "--set", "nope"]).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+// renders without a window, e.g. for print runs or CI.
+//
+//   cargo run -- config.yaml templates/ --frames 100..120 --seed 3
+//
+// it steps through every frame from 0 (so stateful things like lag line up)
+// with time going by frame, and calls the draw function for the ones in
+// `--frames`. murrelet_svg has one that saves the svg for each.
+use std::ops::Range;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Parser;
+use glam::vec2;
+use lerpable::Lerpable;
+use murrelet_common::{LivecodeSrc, MurreletAppInput};
+use murrelet_livecode::boop::Boopable;
+use murrelet_livecode::nestedit::NestEditable;
+use murrelet_livecode::transition::Transitionable;
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+
+use crate::asset_loader::AssetLoaders;
+use crate::cli::BaseConfigArgs;
+use crate::load::{merge_yaml, read_layered_yaml_with_sources};
+use crate::perform::{ConfCommon, LiveCodeCommon, LiveCoder};
+use crate::record::InputReplay;
+
+// `30` is the first 30 frames, `100..120` is 100 up to 119
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRange(Range<u64>);
+impl FrameRange {
+    pub fn new(range: Range<u64>) -> Self {
+        Self(range)
+    }
+
+    pub fn range(&self) -> Range<u64> {
+        self.0.clone()
+    }
+}
+
+impl FromStr for FrameRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |x: &str| {
+            x.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("invalid frame: {}", x))
+        };
+        match s.split_once("..") {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("frames start after they end: {}", s));
+                }
+                Ok(FrameRange(start..end))
+            }
+            None => Ok(FrameRange(0..parse(s)?)),
+        }
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct HeadlessArgs {
+    #[command(flatten)]
+    pub base: BaseConfigArgs,
+
+    #[arg(
+        long,
+        default_value = "1",
+        help = "how many frames, or a range like 100..120"
+    )]
+    pub frames: FrameRange,
+    #[arg(long, help = "use this instead of app.seed")]
+    pub seed: Option<f32>,
+}
+impl HeadlessArgs {
+    // the frames that get drawn, cut short by --earlystop
+    pub fn frames(&self) -> Range<u64> {
+        let range = self.frames.range();
+        match self.base.earlystop {
+            Some(earlystop) => range.start..range.end.min(earlystop),
+            None => range,
+        }
+    }
+}
+
+pub struct Headless<ConfType, ControlConfType>
+where
+    ConfType: ConfCommon + Send + Sync,
+    ControlConfType: LiveCodeCommon<ConfType>,
+{
+    livecoder: LiveCoder<ConfType, ControlConfType>,
+    args: HeadlessArgs,
+}
+impl<ConfType, ControlConfType> Headless<ConfType, ControlConfType>
+where
+    ConfType: ConfCommon + Send + Sync + Lerpable + Boopable + Transitionable + NestEditable,
+    ControlConfType: LiveCodeCommon<ConfType>,
+{
+    pub fn new(
+        save_path: PathBuf,
+        livecode_src: LivecodeSrc,
+        load_funcs: &AssetLoaders,
+    ) -> LivecodeResult<Self> {
+        Self::new_with_args(HeadlessArgs::parse(), save_path, livecode_src, load_funcs)
+    }
+
+    pub fn new_with_args(
+        args: HeadlessArgs,
+        save_path: PathBuf,
+        livecode_src: LivecodeSrc,
+        load_funcs: &AssetLoaders,
+    ) -> LivecodeResult<Self> {
+        let (controlconfig, yaml) = Self::load_config(&args)?;
+
+        let mut livecoder = LiveCoder::new_full(
+            controlconfig.clone(),
+            Some(save_path),
+            livecode_src,
+            load_funcs,
+            Some(args.base.clone()),
+        )?;
+        livecoder.use_frame_time();
+        // the capture bundle writes out the config from the history
+        livecoder.remember_config(controlconfig, yaml);
+
+        if let Some(path) = &args.base.replay_inputs {
+            livecoder.replay_inputs(InputReplay::load(path)?);
+        }
+
+        Ok(Self { livecoder, args })
+    }
+
+    // the config, and the yaml it came from
+    fn load_config(args: &HeadlessArgs) -> LivecodeResult<(ControlConfType, String)> {
+        let (yaml, sources) =
+            read_layered_yaml_with_sources(&args.base.config_paths(), &args.base.template_path)?;
+        match args.seed {
+            None => Ok((ControlConfType::parse_merged(&yaml, &sources)?, yaml)),
+            Some(seed) => {
+                let yaml_err = |err: serde_yaml::Error| {
+                    LivecodeError::Raw(format!("error setting the seed: {}", err))
+                };
+                let mut conf: serde_yaml::Value = serde_yaml::from_str(&yaml).map_err(yaml_err)?;
+                let overlay = serde_yaml::from_str(&format!("{{ app: {{ seed: {} }} }}", seed))
+                    .map_err(yaml_err)?;
+                merge_yaml(&mut conf, &overlay);
+                let yaml = serde_yaml::to_string(&conf).map_err(yaml_err)?;
+                Ok((ControlConfType::parse_merged(&yaml, &sources)?, yaml))
+            }
+        }
+    }
+
+    pub fn livecoder(&self) -> &LiveCoder<ConfType, ControlConfType> {
+        &self.livecoder
+    }
+
+    pub fn frames(&self) -> Range<u64> {
+        self.args.frames()
+    }
+
+    // steps through the frames, calling `draw_frame` on the ones to render
+    pub fn run<F>(&mut self, mut draw_frame: F) -> LivecodeResult<()>
+    where
+        F: FnMut(&LiveCoder<ConfType, ControlConfType>) -> LivecodeResult<()>,
+    {
+        let frames = self.frames();
+        let dims = self.args.base.resolution;
+        let window_dims = vec2(dims.width as f32, dims.height as f32);
+
+        for frame in 0..frames.end {
+            let app = MurreletAppInput {
+                window_dims,
+                ..MurreletAppInput::default_with_frames(frame)
+            };
+            self.livecoder.update(&app, false)?;
+
+            if frames.contains(&frame) {
+                draw_frame(&self.livecoder)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::fs;
+
+    use super::*;
+    use crate::perform::tests::{APP, Conf, ControlConf};
+    use crate::reload::LiveCoderLoader;
+
+    #[test]
+    fn frame_ranges() {
+        assert_eq!("30".parse(), Ok(FrameRange::new(0..30)));
+        assert_eq!("100..120".parse(), Ok(FrameRange::new(100..120)));
+        assert_eq!(" 5 .. 6 ".parse(), Ok(FrameRange::new(5..6)));
+
+        assert!("".parse::<FrameRange>().is_err());
+        assert!("1.5".parse::<FrameRange>().is_err());
+        assert!("-3".parse::<FrameRange>().is_err());
+        assert!("10..".parse::<FrameRange>().is_err());
+        assert!("120..100".parse::<FrameRange>().is_err());
+    }
+
+    #[test]
+    fn earlystop_cuts_the_frames_short() {
+        let args = |extra: &[&str]| {
+            let mut cmd = vec!["sketch", "config.yaml", "templates"];
+            cmd.extend_from_slice(extra);
+            HeadlessArgs::try_parse_from(cmd).unwrap()
+        };
+
+        assert_eq!(args(&[]).frames(), 0..1);
+        assert_eq!(args(&["--frames", "100..120"]).frames(), 100..120);
+        assert_eq!(
+            args(&["--frames", "100..120", "--earlystop", "110"]).frames(),
+            100..110
+        );
+        assert_eq!(
+            args(&["--frames", "100..120", "--earlystop", "200"]).frames(),
+            100..120
+        );
+        // stopping before the range starts draws nothing
+        assert!(
+            args(&["--frames", "100..120", "--earlystop", "50"])
+                .frames()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn frames_write_the_capture_bundle() {
+        let dir = std::env::temp_dir().join(format!("murrelet_headless_{}", std::process::id()));
+        let templates = dir.join("templates");
+        fs::create_dir_all(&templates).unwrap();
+        let config = dir.join("config.yaml");
+        fs::write(&config, format!("{}size: f * 2.0\n", APP)).unwrap();
+
+        let args = HeadlessArgs::try_parse_from([
+            "sketch".as_ref(),
+            config.as_os_str(),
+            templates.as_os_str(),
+            "--frames".as_ref(),
+            "2..3".as_ref(),
+        ])
+        .unwrap();
+        let save_path = dir.join("captures");
+        let mut headless: Headless<Conf, ControlConf> = Headless::new_with_args(
+            args,
+            save_path.clone(),
+            LivecodeSrc::new(vec![]),
+            &AssetLoaders::empty(),
+        )
+        .unwrap();
+        headless.run(|lc| lc.write_capture_bundle()).unwrap();
+
+        let frame = headless.livecoder().capture_frame_name(2, "").unwrap();
+        let folder = frame.parent().unwrap();
+        assert!(folder.join("bundle/run.yaml").exists());
+        assert!(folder.join("bundle/config_0.yaml").exists());
+        let sidecar = fs::read_to_string(frame.with_extension("frame.yaml")).unwrap();
+        assert!(sidecar.contains("elapsed_frames: 2"), "{}", sidecar);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn overlays_keep_their_locations() {
+        let dir = std::env::temp_dir().join(format!("murrelet_overlay_{}", std::process::id()));
+        let templates = dir.join("templates");
+        fs::create_dir_all(&templates).unwrap();
+        let (config, overlay) = (dir.join("config.yaml"), dir.join("overlay.yaml"));
+        fs::write(&config, format!("{}size: t\n", APP)).unwrap();
+        fs::write(&overlay, "size: nope(t)\n").unwrap();
+        let at = format!("{}:1:7", overlay.to_string_lossy());
+
+        for seed in [None, Some("3")] {
+            let mut cmd = vec![
+                "sketch".as_ref(),
+                config.as_os_str(),
+                templates.as_os_str(),
+                "--overlay".as_ref(),
+                overlay.as_os_str(),
+            ];
+            if let Some(seed) = seed {
+                cmd.extend([OsStr::new("--seed"), OsStr::new(seed)]);
+            }
+            let args = HeadlessArgs::try_parse_from(cmd).unwrap();
+            let loaded = Headless::<Conf, ControlConf>::new_with_args(
+                args,
+                dir.join("captures"),
+                LivecodeSrc::new(vec![]),
+                &AssetLoaders::empty(),
+            );
+
+            let Err(err) = loaded else {
+                panic!("nope(t) shouldn't evaluate");
+            };
+            assert!(err.to_string().contains(&at), "{}", err);
+        }
+
+        // the same when the sketch loads (and reloads) them
+        let conf = ControlConf::fs_parse_layered(&[&config, &overlay], &templates).unwrap();
+        let loaded = LiveCoder::<Conf, ControlConf>::new_full(
+            conf,
+            None,
+            LivecodeSrc::new(vec![]),
+            &AssetLoaders::empty(),
+            None,
+        );
+        let Err(err) = loaded else {
+            panic!("nope(t) shouldn't evaluate");
+        };
+        assert!(err.to_string().contains(&at), "{}", err);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
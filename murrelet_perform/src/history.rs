@@ -0,0 +1,451 @@
+// the last few configs that loaded, so you can step back to one that looked
+// good (and forward again) in the middle of a set.
+//
+//   app:
+//     history:
+//       back: kZf      # go back one
+//       forward: kXf   # and forward
+//       size: 32       # how many to keep
+//       save: true     # also write each one's yaml into the capture folder
+//
+// stepping around doesn't add to the history, the next reload is added to
+// the end.
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+
+use lerpable::{Lerpable, step};
+use murrelet_common::MurreletTime;
+use murrelet_gui::CanMakeGUI;
+use murrelet_livecode::livecode::{
+    GetLivecodeIdentifiers, LivecodeExpr, LivecodeFunction, LivecodeVariable,
+};
+use murrelet_livecode::nestedit::{NestEditable, NestedMod};
+use murrelet_livecode::state::LivecodeWorldState;
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+use serde::{Deserialize, Serialize};
+
+use crate::setlist::check_trigger;
+
+fn _default_history_size() -> usize {
+    32
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HistoryConf {
+    #[serde(default = "_default_history_size")]
+    size: usize,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    back: Option<LivecodeExpr>,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    forward: Option<LivecodeExpr>,
+    #[serde(default)]
+    save: bool,
+}
+impl Default for HistoryConf {
+    fn default() -> Self {
+        Self {
+            size: _default_history_size(),
+            back: None,
+            forward: None,
+            save: false,
+        }
+    }
+}
+impl HistoryConf {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn should_save(&self) -> bool {
+        self.save
+    }
+
+    fn triggers(&self) -> impl Iterator<Item = &LivecodeExpr> {
+        self.back.iter().chain(self.forward.iter())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryEntry<C> {
+    id: u64, // counts up from the first config, so it stays the same as old ones drop off
+    loaded_at: MurreletTime,
+    conf: C,
+    yaml: String, // with the templates filled in
+}
+impl<C> HistoryEntry<C> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn loaded_at(&self) -> MurreletTime {
+        self.loaded_at
+    }
+
+    pub fn conf(&self) -> &C {
+        &self.conf
+    }
+
+    pub fn yaml(&self) -> &str {
+        &self.yaml
+    }
+
+    // where to save this in the capture folder
+    pub fn filename(&self) -> String {
+        format!(
+            "config_{}_{}.yaml",
+            self.id,
+            self.loaded_at.as_millis_u128()
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigHistory<C> {
+    entries: VecDeque<HistoryEntry<C>>,
+    cursor: usize, // the one that's showing
+    next_id: u64,
+    back_was_on: bool,
+    forward_was_on: bool,
+}
+impl<C: Clone> Default for ConfigHistory<C> {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            cursor: 0,
+            next_id: 0,
+            back_was_on: false,
+            forward_was_on: false,
+        }
+    }
+}
+impl<C: Clone> ConfigHistory<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, conf: C, yaml: String, size: usize) -> &HistoryEntry<C> {
+        self.entries.push_back(HistoryEntry {
+            id: self.next_id,
+            loaded_at: MurreletTime::now(),
+            conf,
+            yaml,
+        });
+        self.next_id += 1;
+        while self.entries.len() > size.max(1) {
+            self.entries.pop_front();
+        }
+        self.cursor = self.entries.len() - 1;
+        self.entries.back().unwrap()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry<C>> {
+        self.entries.iter()
+    }
+
+    pub fn current(&self) -> Option<&HistoryEntry<C>> {
+        self.entries.get(self.cursor)
+    }
+
+    pub fn get(&self, id: u64) -> Option<&HistoryEntry<C>> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn back(&mut self) -> Option<&HistoryEntry<C>> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        self.current()
+    }
+
+    pub fn forward(&mut self) -> Option<&HistoryEntry<C>> {
+        if self.cursor + 1 >= self.entries.len() {
+            return None;
+        }
+        self.cursor += 1;
+        self.current()
+    }
+
+    // steps when back or forward turns on, and returns the config to use
+    pub fn check_triggers(
+        &mut self,
+        conf: &HistoryConf,
+        w: &LivecodeWorldState,
+    ) -> LivecodeResult<Option<C>> {
+        let back = check_trigger(&conf.back, w)?;
+        let forward = check_trigger(&conf.forward, w)?;
+
+        let stepped = if back && !self.back_was_on {
+            self.back()
+        } else if forward && !self.forward_was_on {
+            self.forward()
+        } else {
+            None
+        };
+        let stepped = stepped.map(|e| e.conf.clone());
+
+        self.back_was_on = back;
+        self.forward_was_on = forward;
+        Ok(stepped)
+    }
+
+    // what changed going from one entry to another
+    pub fn diff(&self, from: u64, to: u64) -> LivecodeResult<Vec<ConfigDiff>> {
+        let parse = |id| -> LivecodeResult<serde_yaml::Value> {
+            let entry = self.get(id).ok_or(LivecodeError::Raw(format!(
+                "no config {} in the history",
+                id
+            )))?;
+            serde_yaml::from_str(&entry.yaml)
+                .map_err(|err| LivecodeError::Raw(format!("couldn't parse config {}: {}", id, err)))
+        };
+        Ok(yaml_diff(&parse(from)?, &parse(to)?))
+    }
+}
+
+// one field that's different. `before` or `after` is None if it was added or
+// removed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiff {
+    pub path: String, // like app.time.bpm or shapes.2.size
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => write!(f, "{}: {} -> {}", self.path, before, after),
+            (None, Some(after)) => write!(f, "+ {}: {}", self.path, after),
+            (Some(before), None) => write!(f, "- {}: {}", self.path, before),
+            (None, None) => write!(f, "{}", self.path),
+        }
+    }
+}
+
+fn yaml_to_string(v: &serde_yaml::Value) -> String {
+    match v {
+        serde_yaml::Value::String(s) => s.clone(),
+        _ => serde_yaml::to_string(v)
+            .map(|s| s.trim().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+pub fn yaml_diff(before: &serde_yaml::Value, after: &serde_yaml::Value) -> Vec<ConfigDiff> {
+    let mut diffs = vec![];
+    yaml_diff_at("", before, after, &mut diffs);
+    diffs
+}
+
+fn yaml_diff_at(
+    path: &str,
+    before: &serde_yaml::Value,
+    after: &serde_yaml::Value,
+    diffs: &mut Vec<ConfigDiff>,
+) {
+    use serde_yaml::Value;
+    match (before, after) {
+        (Value::Mapping(b), Value::Mapping(a)) => {
+            for (k, bv) in b {
+                let p = join_path(path, &yaml_to_string(k));
+                match a.get(k) {
+                    Some(av) => yaml_diff_at(&p, bv, av, diffs),
+                    None => diffs.push(ConfigDiff {
+                        path: p,
+                        before: Some(yaml_to_string(bv)),
+                        after: None,
+                    }),
+                }
+            }
+            for (k, av) in a {
+                if !b.contains_key(k) {
+                    diffs.push(ConfigDiff {
+                        path: join_path(path, &yaml_to_string(k)),
+                        before: None,
+                        after: Some(yaml_to_string(av)),
+                    });
+                }
+            }
+        }
+        (Value::Sequence(b), Value::Sequence(a)) => {
+            for i in 0..b.len().max(a.len()) {
+                let p = join_path(path, &i.to_string());
+                match (b.get(i), a.get(i)) {
+                    (Some(bv), Some(av)) => yaml_diff_at(&p, bv, av, diffs),
+                    (bv, av) => diffs.push(ConfigDiff {
+                        path: p,
+                        before: bv.map(yaml_to_string),
+                        after: av.map(yaml_to_string),
+                    }),
+                }
+            }
+        }
+        (b, a) => {
+            if b != a {
+                diffs.push(ConfigDiff {
+                    path: path.to_owned(),
+                    before: Some(yaml_to_string(b)),
+                    after: Some(yaml_to_string(a)),
+                });
+            }
+        }
+    }
+}
+
+// writes the entry's yaml into `folder`
+pub fn save_history_entry<C>(folder: &Path, entry: &HistoryEntry<C>) -> LivecodeResult<()> {
+    std::fs::create_dir_all(folder)
+        .map_err(|err| LivecodeError::Io(format!("couldn't make {:?}", folder), err))?;
+    let path = folder.join(entry.filename());
+    std::fs::write(&path, &entry.yaml)
+        .map_err(|err| LivecodeError::Io(format!("couldn't write {:?}", path), err))
+}
+
+impl GetLivecodeIdentifiers for HistoryConf {
+    fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
+        self.triggers()
+            .flat_map(|e| {
+                e.iter_read_variable_identifiers()
+                    .map(LivecodeVariable::from_str)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn function_identifiers(&self) -> Vec<LivecodeFunction> {
+        self.triggers()
+            .flat_map(|e| {
+                e.iter_function_identifiers()
+                    .map(LivecodeFunction::from_str)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl CanMakeGUI for HistoryConf {
+    fn make_gui() -> murrelet_gui::MurreletGUISchema {
+        murrelet_gui::MurreletGUISchema::Val(murrelet_gui::ValueGUI::Defs)
+    }
+}
+
+impl NestEditable for HistoryConf {
+    fn nest_update(&self, _mods: NestedMod) -> Self {
+        self.clone()
+    }
+
+    fn nest_get(&self, _getter: &[&str]) -> LivecodeResult<String> {
+        Err(LivecodeError::NestGetExtra("HistoryConf".to_owned()))
+    }
+}
+
+impl Lerpable for HistoryConf {
+    fn lerpify<T: lerpable::IsLerpingMethod>(&self, other: &Self, pct: &T) -> Self {
+        step(self, other, pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use murrelet_common::LivecodeValue;
+    use murrelet_livecode::expr::ExprWorldContextValues;
+    use murrelet_livecode::state::LivecodeWorldStateInner;
+
+    fn world(back: bool, forward: bool) -> LivecodeWorldState {
+        let vals = ExprWorldContextValues::new(vec![
+            ("go_back".to_owned(), LivecodeValue::Bool(back)),
+            ("go_forward".to_owned(), LivecodeValue::Bool(forward)),
+        ]);
+        LivecodeWorldState::new_legacy(LivecodeWorldStateInner::new_dummy())
+            .unwrap()
+            .clone_with_vals(vals, "")
+    }
+
+    #[test]
+    fn push_trims_and_moves_the_cursor() {
+        let mut history = ConfigHistory::new();
+        for i in 0..5 {
+            history.push(i, format!("a: {}", i), 3);
+        }
+        let ids = history.entries().map(|e| e.id()).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3, 4]);
+        assert_eq!(history.current().unwrap().conf(), &4);
+
+        assert_eq!(history.back().unwrap().conf(), &3);
+        assert_eq!(history.back().unwrap().conf(), &2);
+        assert!(history.back().is_none());
+        assert_eq!(history.current().unwrap().conf(), &2);
+        assert_eq!(history.forward().unwrap().conf(), &3);
+
+        // a new one goes on the end, even after stepping back
+        history.push(5, "a: 5".to_owned(), 3);
+        assert_eq!(history.current().unwrap().id(), 5);
+        assert!(history.forward().is_none());
+        assert!(history.get(2).is_none());
+    }
+
+    #[test]
+    fn triggers_step_once_when_they_turn_on() {
+        let conf: HistoryConf =
+            serde_yaml::from_str("back: go_back\nforward: go_forward\n").unwrap();
+        let mut history = ConfigHistory::new();
+        for i in 0..3 {
+            history.push(i, String::new(), conf.size());
+        }
+
+        let mut step = |back, forward| {
+            history
+                .check_triggers(&conf, &world(back, forward))
+                .unwrap()
+        };
+        assert_eq!(step(true, false), Some(1));
+        assert_eq!(step(true, false), None); // still held
+        assert_eq!(step(false, false), None);
+        assert_eq!(step(true, false), Some(0));
+        assert_eq!(step(false, false), None);
+        assert_eq!(step(true, false), None); // nothing before the first
+        assert_eq!(step(false, true), Some(1));
+    }
+
+    #[test]
+    fn diffs_configs() {
+        let before = serde_yaml::from_str(
+            "app:\n  time:\n    bpm: 120\nshapes:\n  - size: 1\n  - size: 2\nold: true\n",
+        )
+        .unwrap();
+        let after =
+            serde_yaml::from_str("app:\n  time:\n    bpm: 90\nshapes:\n  - size: 1\nnew: m3\n")
+                .unwrap();
+        let diffs = yaml_diff(&before, &after)
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diffs,
+            vec![
+                "app.time.bpm: 120 -> 90",
+                "- shapes.1: size: 2",
+                "- old: true",
+                "+ new: m3",
+            ]
+        );
+
+        let mut history = ConfigHistory::new();
+        history.push((), "a: 1".to_owned(), 4);
+        history.push((), "a: [".to_owned(), 4);
+        assert_eq!(history.diff(0, 0).unwrap(), vec![]);
+        assert!(history.diff(0, 1).is_err());
+        assert!(history.diff(0, 7).is_err());
+    }
+}
@@ -1,8 +1,17 @@
 pub mod asset_loader;
+pub mod bundle;
+pub mod check;
 pub mod cli;
+pub mod headless;
+pub mod history;
 pub mod load;
 pub mod perform;
+pub mod record;
 pub mod reload;
+pub mod server;
+pub mod setlist;
+pub mod sweep;
+pub mod watch;
 
 pub use perform::AppConfig;
 pub use perform::ControlAppConfig;
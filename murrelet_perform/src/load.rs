@@ -1,50 +1,386 @@
+use std::collections::HashSet;
 use std::fs;
+use std::path::{Path, PathBuf};
 
+use murrelet_livecode::sourcemap::YamlSourceMap;
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
 use regex::Regex;
 
-fn leftpad(text: &str, padding_count: usize) -> String {
-    let re = Regex::new(r"(?m)^").unwrap();
-    let padding = " ".repeat(padding_count);
-    re.replace_all(text, format!("{}$0", padding).as_str())
-        .to_string()
+// `loc` can be a few template folders, separated like $PATH (e.g.
+// `templates:../shared/templates`), the first one with the template wins
+pub fn template_dirs<P: AsRef<Path>>(loc: P) -> Vec<PathBuf> {
+    std::env::split_paths(loc.as_ref().as_os_str()).collect()
 }
 
-fn loadfile<P: AsRef<std::path::Path>>(loc: P, filename: &str) -> Option<String> {
-    let file_to_check = loc.as_ref().join(filename).with_extension("yaml");
-    let file = fs::File::open(&file_to_check);
+fn find_template(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    dirs.iter()
+        .map(|dir| dir.join(name).with_extension("yaml"))
+        .find(|f| f.is_file())
+}
+
+// lines starting with [[something]] get the template `something` put in
+fn template_regex() -> Regex {
+    Regex::new(r"^( *)\[\[([^\[\]]+)\]\](.*)$").unwrap()
+}
+
+// the name and args in `grid(rows=4, color=m3)`. if it doesn't look like a
+// template (like a nested list `[[0, 1]]`), it's None
+fn template_call(s: &str) -> Option<(String, Vec<(String, String)>)> {
+    let re = Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_./-]*)\s*(?:\((.*)\))?\s*$").unwrap();
+    let cap = re.captures(s)?;
+    let name = cap[1].to_owned();
+
+    let mut args = vec![];
+    if let Some(arg_list) = cap.get(2) {
+        for arg in split_args(arg_list.as_str()) {
+            let (k, v) = arg.split_once('=')?;
+            args.push((k.trim().to_owned(), v.trim().to_owned()));
+        }
+    }
+    Some((name, args))
+}
+
+// splits on the commas that aren't inside brackets, so args can be
+// expressions like `max(m3, 0.5)`
+fn split_args(s: &str) -> Vec<&str> {
+    let mut args = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&s[start..]);
+    args.into_iter().filter(|a| !a.trim().is_empty()).collect()
+}
+
+// swaps each {{arg}} in the template for its value
+fn fill_template_args(
+    contents: &str,
+    name: &str,
+    args: &[(String, String)],
+) -> LivecodeResult<String> {
+    let re = Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap();
+
+    let mut missing = vec![];
+    let filled = re.replace_all(contents, |cap: &regex::Captures| {
+        match args.iter().rev().find(|(k, _)| k == &cap[1]) {
+            Some((_, v)) => v.clone(),
+            None => {
+                missing.push(cap[1].to_owned());
+                cap[0].to_owned()
+            }
+        }
+    });
+    if !missing.is_empty() {
+        missing.sort();
+        missing.dedup();
+        return Err(LivecodeError::Raw(format!(
+            "template `{}` needs {}",
+            name,
+            missing.join(", ")
+        )));
+    }
 
-    if let Ok(mut f) = file {
-        let mut data = String::new();
-        std::io::Read::read_to_string(&mut f, &mut data).unwrap();
-        Some(data)
-    } else {
-        println!("template file not found {:?}", file_to_check);
-        None
+    let used = re
+        .captures_iter(contents)
+        .map(|cap| cap[1].to_owned())
+        .collect::<HashSet<_>>();
+    if let Some((k, _)) = args.iter().find(|(k, _)| !used.contains(k)) {
+        return Err(LivecodeError::Raw(format!(
+            "template `{}` doesn't use `{}`",
+            name, k
+        )));
     }
+    Ok(filled.into_owned())
+}
+
+struct Expanded {
+    lines: Vec<String>,
+    source_map: YamlSourceMap,
+    files: Vec<PathBuf>, // every template used, including nested ones
+}
+
+// fills in the templates in `text`, and the templates in those. `stack` is
+// the templates we're inside of, to catch one that includes itself
+fn expand_templates(
+    text: &str,
+    filename: &str,
+    dirs: &[PathBuf],
+    stack: &mut Vec<String>,
+) -> LivecodeResult<Expanded> {
+    let re = template_regex();
+    let mut expanded = Expanded {
+        lines: vec![],
+        source_map: YamlSourceMap::new(),
+        files: vec![],
+    };
+
+    for (line_idx, line) in text.lines().enumerate() {
+        let Some((cap, (name, args))) = re
+            .captures(line)
+            .and_then(|cap| template_call(&cap[2]).map(|call| (cap, call)))
+        else {
+            expanded.lines.push(line.to_owned());
+            expanded.source_map.push_line(filename, line_idx + 1, 0);
+            continue;
+        };
+        let at =
+            |msg: String| LivecodeError::Raw(format!("{}:{}: {}", filename, line_idx + 1, msg));
+
+        let Some(template_path) = find_template(dirs, &name) else {
+            let dirs = dirs.iter().map(|d| format!("{:?}", d)).collect::<Vec<_>>();
+            return Err(at(format!(
+                "couldn't find template `{}` in {}",
+                name,
+                dirs.join(", ")
+            )));
+        };
+        if stack.contains(&name) {
+            return Err(at(format!(
+                "templates include each other: {} -> {}",
+                stack.join(" -> "),
+                name
+            )));
+        }
+
+        let contents = fs::read_to_string(&template_path)
+            .map_err(|err| LivecodeError::Io(format!("couldn't read {:?}", template_path), err))?;
+        let contents =
+            fill_template_args(&contents, &name, &args).map_err(|err| at(err.to_string()))?;
+
+        stack.push(name.clone());
+        let inner = expand_templates(&contents, &format!("{}.yaml", name), dirs, stack)?;
+        stack.pop();
+
+        // insert it, matching the indentation of the [[ ]], and anything
+        // after it goes on the end
+        let (spaces, rest) = (cap[1].len(), &cap[3]);
+        let padding = " ".repeat(spaces);
+        let count = inner.lines.len();
+        for (i, template_line) in inner.lines.into_iter().enumerate() {
+            let mut padded = format!("{}{}", padding, template_line);
+            if i + 1 == count {
+                padded.push_str(rest);
+            }
+            expanded.lines.push(padded);
+        }
+        expanded.source_map.extend(&inner.source_map, spaces);
+        expanded.files.push(template_path);
+        expanded.files.extend(inner.files);
+    }
+    Ok(expanded)
+}
+
+// the template files a config uses, including ones used by other templates
+pub fn template_files<P: AsRef<Path>>(text: &str, loc: P) -> Vec<PathBuf> {
+    let mut files = expand_templates(text, "", &template_dirs(loc), &mut vec![])
+        .map(|e| e.files)
+        .unwrap_or_default();
+    files.sort();
+    files.dedup();
+    files
+}
+
+pub fn preprocess_yaml<P: AsRef<Path>>(text: &str, loc: P) -> LivecodeResult<String> {
+    Ok(preprocess_yaml_with_source_map(text, "", loc)?.0)
 }
 
-pub fn preprocess_yaml<P: AsRef<std::path::Path>>(text: &str, loc: P) -> String {
-    // find all lines starting with <<something>>, these will go through the
+// reads a config file and fills in its templates
+pub fn read_yaml<P: AsRef<Path>, P2: AsRef<Path>>(filename: P, loc: P2) -> LivecodeResult<String> {
+    let text = fs::read_to_string(&filename)
+        .map_err(|err| LivecodeError::Io(format!("couldn't read {:?}", filename.as_ref()), err))?;
+    Ok(preprocess_yaml_with_source_map(&text, &filename.as_ref().to_string_lossy(), loc)?.0)
+}
+
+// same as preprocess_yaml, but also keeps track of which file and line each
+// line of the output came from, so errors can point at the right place
+pub fn preprocess_yaml_with_source_map<P: AsRef<Path>>(
+    text: &str,
+    filename: &str,
+    loc: P,
+) -> LivecodeResult<(String, YamlSourceMap)> {
+    // find all lines starting with [[something]], these will go through the
     // configs/prebuilt. it'll insert it, matching the indentation of the input.
+    let expanded = expand_templates(text, filename, &template_dirs(loc), &mut vec![])?;
+
+    let mut new_text = expanded.lines.join("\n");
+    if text.ends_with('\n') {
+        new_text.push('\n');
+    }
+    Ok((new_text, expanded.source_map))
+}
+
+// reads each config and merges them in order, so later ones win. with one,
+// it's just read_yaml
+pub fn read_layered_yaml<P: AsRef<std::path::Path>, P2: AsRef<std::path::Path>>(
+    filenames: &[P],
+    loc: P2,
+) -> LivecodeResult<String> {
+    Ok(read_layered_yaml_with_sources(filenames, loc)?.0)
+}
 
-    let pattern = r"(?m)^( *)\[\[([^\[\]]+)\]\]";
+// same as read_layered_yaml, but also gives back each file's preprocessed
+// text and source map, for with_merged_yaml_source
+pub fn read_layered_yaml_with_sources<P: AsRef<std::path::Path>, P2: AsRef<std::path::Path>>(
+    filenames: &[P],
+    loc: P2,
+) -> LivecodeResult<(String, Vec<(String, YamlSourceMap)>)> {
+    let mut sources = vec![];
+    for filename in filenames {
+        let text = fs::read_to_string(filename).map_err(|err| {
+            LivecodeError::Io(format!("couldn't read {:?}", filename.as_ref()), err)
+        })?;
+        sources.push(preprocess_yaml_with_source_map(
+            &text,
+            &filename.as_ref().to_string_lossy(),
+            &loc,
+        )?);
+    }
 
-    let re = Regex::new(pattern).unwrap();
+    let [(first, _), rest @ ..] = sources.as_slice() else {
+        return Err(LivecodeError::Raw("no config to load".to_owned()));
+    };
+    if rest.is_empty() {
+        return Ok((first.clone(), sources));
+    }
 
-    let mut new_text = text.to_owned();
+    let yaml_err = |filename: &P, err: serde_yaml::Error| {
+        LivecodeError::Raw(format!("error merging {:?}: {}", filename.as_ref(), err))
+    };
+    let mut conf = serde_yaml::Value::Null;
+    for (filename, (text, _)) in filenames.iter().zip(&sources) {
+        let overlay: serde_yaml::Value =
+            serde_yaml::from_str(text).map_err(|err| yaml_err(filename, err))?;
+        merge_yaml(&mut conf, &overlay);
+    }
+    let merged = serde_yaml::to_string(&conf).map_err(|err| yaml_err(&filenames[0], err))?;
+    Ok((merged, sources))
+}
 
-    for cap in re.captures_iter(text) {
-        // println!("cap {:?}", cap);
-        let spaces = cap[1].len();
-        let filename = cap[2].to_string();
+// lays `overlay` over `base`, going into maps so only the keys in the overlay change
+pub fn merge_yaml(base: &mut serde_yaml::Value, overlay: &serde_yaml::Value) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overlay)) => {
+            for (k, v) in overlay {
+                match base.get_mut(k) {
+                    Some(existing) => merge_yaml(existing, v),
+                    None => {
+                        base.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
 
-        // open up the file
-        let contents = loadfile(loc.as_ref(), &filename);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if let Some(c) = contents {
-            let padded = leftpad(&c, spaces);
-            new_text = new_text.replace(&format!("{}[[{}]]", &cap[1], filename), &padded);
+    // a folder of templates, removed when it's dropped
+    struct Templates(PathBuf);
+    impl Templates {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("murrelet_{}_{}", name, std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            for (file, contents) in files {
+                fs::write(dir.join(file).with_extension("yaml"), contents).unwrap();
+            }
+            Self(dir)
+        }
+    }
+    impl Drop for Templates {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
         }
     }
-    new_text
+
+    #[test]
+    fn templates_take_args_and_nest() {
+        let t = Templates::new(
+            "nest",
+            &[
+                (
+                    "grid",
+                    "rows: {{rows}}\ncell:\n  [[cell(color={{color}})]]\n",
+                ),
+                ("cell", "color: {{ color }}\nsize: 1"),
+            ],
+        );
+        let (yaml, source_map) = preprocess_yaml_with_source_map(
+            "grid:\n  [[grid(rows=4, color=max(m3, 0.5))]]\nother: [[0, 1]]\n",
+            "config.yaml",
+            &t.0,
+        )
+        .unwrap();
+        assert_eq!(
+            yaml,
+            "grid:\n  rows: 4\n  cell:\n    color: max(m3, 0.5)\n    size: 1\nother: [[0, 1]]\n"
+        );
+        assert_eq!(source_map.to_source(4, 12).to_string(), "cell.yaml:1:8");
+
+        let files = template_files("[[grid(rows=1, color=2)]]", &t.0);
+        assert_eq!(files.len(), 2);
+    }
+
+    #[test]
+    fn templates_used_twice_keep_their_locations() {
+        use murrelet_livecode::livecode::LivecodeExpr;
+        use murrelet_livecode::sourcemap::with_yaml_source;
+
+        #[derive(serde::Deserialize)]
+        struct Shape {
+            size: LivecodeExpr,
+            x: LivecodeExpr,
+        }
+        #[derive(serde::Deserialize)]
+        struct Conf {
+            a: Shape,
+            b: Shape,
+            speed: LivecodeExpr,
+        }
+
+        let t = Templates::new("twice", &[("shape", "size: t * 2.0\nx: m3")]);
+        let (yaml, source_map) = preprocess_yaml_with_source_map(
+            "a:\n  [[shape]]\nb:\n  [[shape]]\nspeed: t * 2.0\n",
+            "config.yaml",
+            &t.0,
+        )
+        .unwrap();
+        let conf: Conf =
+            with_yaml_source(&yaml, source_map, || serde_yaml::from_str(&yaml)).unwrap();
+
+        let loc = |e: &LivecodeExpr| e.loc().map(|l| l.to_string());
+        for shape in [&conf.a, &conf.b] {
+            assert_eq!(loc(&shape.size).as_deref(), Some("shape.yaml:1:7"));
+            assert_eq!(loc(&shape.x).as_deref(), Some("shape.yaml:2:4"));
+        }
+        assert_eq!(loc(&conf.speed).as_deref(), Some("config.yaml:5:8"));
+    }
+
+    #[test]
+    fn template_errors() {
+        let shared = Templates::new("shared", &[("a", "[[b]]"), ("b", "[[a]]"), ("c", "x: 1")]);
+        let local = Templates::new("local", &[("c", "x: {{x}}")]);
+        let dirs = std::env::join_paths([&local.0, &shared.0]).unwrap();
+
+        let err = |text: &str| preprocess_yaml(text, &dirs).unwrap_err().to_string();
+        assert!(err("\n[[nope]]").starts_with(":2: couldn't find template `nope` in"));
+        assert!(err("[[a]]").contains("templates include each other: a -> b -> a"));
+        assert!(err("[[c]]").contains("template `c` needs x"));
+        assert!(err("[[c(x=1, y=2)]]").contains("template `c` doesn't use `y`"));
+
+        // the first folder wins
+        assert_eq!(preprocess_yaml("[[c(x=2)]]", &dirs).unwrap(), "x: 2");
+    }
 }
@@ -4,34 +4,51 @@ use lerpable::Lerpable;
 use murrelet_common::{
     Assets, AssetsRef, LivecodeUsage, LivecodeValue, SimpleTransform2d, SimpleTransform2dStep,
 };
-use murrelet_common::{LivecodeSrc, LivecodeSrcUpdateInput, MurreletAppInput};
+use murrelet_common::{LivecodeSrc, LivecodeSrcUpdateInput, MurreletAppInput, MurreletTime};
 use murrelet_common::{MurreletColor, TransformVec2};
 use murrelet_gui::MurreletGUI;
+use murrelet_livecode::boop::{BoopConf, BoopStates, Boopable};
+use murrelet_livecode::envelope::{EnvelopeStates, Envelopes};
 use murrelet_livecode::expr::{MixedEvalDefs, MixedEvalDefsRef};
 use murrelet_livecode::lazy::{ControlLazyMurreletColor, ControlLazyNodeF32, LazyNodeF32};
+use murrelet_livecode::lint::{IdentifierLint, LintWarning};
+use murrelet_livecode::nestedit::{NestEditable, NestedMod};
 use murrelet_livecode::state::{LivecodeTimingConfig, LivecodeWorldState};
-use murrelet_livecode::types::{AdditionalContextNode, LivecodeResult};
+use murrelet_livecode::timeline::Timeline;
+use murrelet_livecode::transition::{Transition, TransitionStart, TransitionState, Transitionable};
+use murrelet_livecode::types::{AdditionalContextNode, LivecodeError, LivecodeResult};
+use murrelet_livecode::userfuncs::UserFunctions;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 
 use murrelet_common::run_id;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use murrelet_livecode::livecode::LivecodeFromWorld;
 use murrelet_livecode::livecode::*;
 use murrelet_livecode_derive::Livecode;
 
 use crate::asset_loader::*;
+use crate::bundle::{BundleTiming, CaptureBundle, FrameSidecar};
 use crate::cli::{BaseConfigArgs, TextureDimensions};
+use crate::history::{ConfigDiff, ConfigHistory, HistoryConf, save_history_entry};
+use crate::load::{read_layered_yaml, read_yaml, template_dirs, template_files};
+use crate::record::{InputRecorder, InputReplay};
 use crate::reload::*;
+use crate::setlist::{Setlist, SetlistState};
+use crate::watch::{FileChanges, FileWatcher, WatchedKind};
 use clap::Parser;
 
 pub trait CommonTrait: std::fmt::Debug + Clone {}
 
 // requirements for the control conf
 pub trait LiveCodeCommon<T>:
-    GetLivecodeIdentifiers + LivecodeFromWorld<T> + LiveCoderLoader + CommonTrait
+    GetLivecodeIdentifiers + LivecodeFromWorld<T> + LiveCoderLoader + CommonTrait + serde::Serialize
 {
+    // back to the control version, with the evaluated numbers
+    fn from_conf(conf: &T) -> Self;
 }
 
 // requirements for the conf
@@ -219,6 +236,21 @@ fn _default_capture_frame_lazy() -> ControlLazyNodeF32 {
     ControlLazyNodeF32::Bool(false)
 }
 
+fn _default_freeze() -> ControlBool {
+    #[cfg(feature = "for_the_web")]
+    {
+        ControlBool::Raw(false)
+    }
+    #[cfg(not(feature = "for_the_web"))]
+    {
+        ControlBool::force_from_str("kCf")
+    }
+}
+
+fn _default_freeze_lazy() -> ControlLazyNodeF32 {
+    ControlLazyNodeF32::Bool(false)
+}
+
 fn _default_clear_bg() -> ControlBool {
     #[cfg(feature = "for_the_web")]
     {
@@ -310,17 +342,70 @@ impl AppConfigTiming {
 fn _default_dyn_f() -> ControlF32 {
     ControlF32::Raw(1.0)
 }
+fn _default_dyn_f_lazy() -> ControlLazyNodeF32 {
+    ControlLazyNodeF32::Float(1.0)
+}
 
 fn _default_dyn_z() -> ControlF32 {
     ControlF32::Raw(1.0)
 }
+fn _default_dyn_z_lazy() -> ControlLazyNodeF32 {
+    ControlLazyNodeF32::Float(1.0)
+}
 
 fn _default_dyn_r() -> ControlF32 {
     ControlF32::Raw(1.0)
 }
+fn _default_dyn_r_lazy() -> ControlLazyNodeF32 {
+    ControlLazyNodeF32::Float(1.0)
+}
 
 fn _default_dyn_reset() -> ControlBool {
-    ControlBool::Raw(true)
+    ControlBool::Raw(false)
+}
+fn _default_dyn_reset_lazy() -> ControlLazyNodeF32 {
+    ControlLazyNodeF32::Bool(false)
+}
+
+impl Default for ControlAppConfigBoop {
+    fn default() -> Self {
+        Self {
+            f: _default_dyn_f(),
+            z: _default_dyn_z(),
+            r: _default_dyn_r(),
+            reset: _default_dyn_reset(),
+        }
+    }
+}
+
+impl Default for ControlLazyAppConfigBoop {
+    fn default() -> Self {
+        Self {
+            f: _default_dyn_f_lazy(),
+            z: _default_dyn_z_lazy(),
+            r: _default_dyn_r_lazy(),
+            reset: _default_dyn_reset_lazy(),
+        }
+    }
+}
+
+// settings for the fields marked with #[livecode(boop)], they act like a spring
+#[allow(dead_code)]
+#[derive(Debug, Clone, Livecode, MurreletGUI, Lerpable)]
+pub struct AppConfigBoop {
+    #[livecode(serde_default = "_default_dyn_f")]
+    pub f: f32, // frequency, how fast it responds
+    #[livecode(serde_default = "_default_dyn_z")]
+    pub z: f32, // damping, < 1 will wobble, > 1 will slowly settle
+    #[livecode(serde_default = "_default_dyn_r")]
+    pub r: f32, // response, < 0 anticipates, > 1 overshoots
+    #[livecode(serde_default = "_default_dyn_reset")]
+    pub reset: bool, // jump straight to the targets
+}
+impl AppConfigBoop {
+    pub fn to_boop_conf(&self) -> BoopConf {
+        BoopConf::ode(self.reset, self.f, self.z, self.r)
+    }
 }
 
 fn _reset_b() -> ControlBool {
@@ -489,6 +574,54 @@ fn _default_ctx_lazy() -> AdditionalContextNode {
     AdditionalContextNode::new_dummy()
 }
 
+fn _default_functions() -> UserFunctions {
+    UserFunctions::default()
+}
+
+fn _default_functions_lazy() -> UserFunctions {
+    UserFunctions::default()
+}
+
+fn _default_envelopes() -> Envelopes {
+    Envelopes::default()
+}
+
+fn _default_envelopes_lazy() -> Envelopes {
+    Envelopes::default()
+}
+
+fn _default_timeline() -> Timeline {
+    Timeline::default()
+}
+
+fn _default_timeline_lazy() -> Timeline {
+    Timeline::default()
+}
+
+fn _default_transition() -> Transition {
+    Transition::default()
+}
+
+fn _default_transition_lazy() -> Transition {
+    Transition::default()
+}
+
+fn _default_history() -> HistoryConf {
+    HistoryConf::default()
+}
+
+fn _default_history_lazy() -> HistoryConf {
+    HistoryConf::default()
+}
+
+fn _default_setlist() -> Setlist {
+    Setlist::default()
+}
+
+fn _default_setlist_lazy() -> Setlist {
+    Setlist::default()
+}
+
 fn _default_svg() -> ControlSvgConfig {
     ControlSvgConfig::default()
 }
@@ -505,20 +638,68 @@ impl Default for ControlAppConfig {
             clear_bg: _default_clear_bg(),
             bg_color: _default_bg_color(),
             capture_frame: _default_capture_frame(),
+            freeze: _default_freeze(),
             redraw: _default_redraw(),
             reload: _default_reload(),
             reload_rate: _default_reload_rate(),
             time: _default_time(),
             ctx: _default_ctx(),
+            functions: _default_functions(),
+            envelopes: _default_envelopes(),
+            timeline: _default_timeline(),
+            setlist: _default_setlist(),
+            transition: _default_transition(),
+            history: _default_history(),
             svg: _default_svg(),
             gpu: _default_gpu(),
             reload_on_bar: _default_reload_on_bar(),
             assets: _default_assets(),
             lerp_rate: _default_lerp_rate(),
+            boop: _default_boop(),
+            compiled_exprs: ControlBool::Raw(false),
         }
     }
 }
 
+impl ControlAppConfig {
+    // the world at `frame` with no live inputs, time going by frame, and
+    // `used_vars` that it doesn't have set to 0. for checking configs
+    pub(crate) fn world_without_inputs(
+        &self,
+        frame: u64,
+        used_vars: &HashSet<String>,
+    ) -> LivecodeResult<LivecodeWorldState> {
+        let src = LivecodeSrc::new(vec![]);
+
+        let mut util = LiveCodeUtil::new()?;
+        util.set_user_functions(std::slice::from_ref(&self.functions))?;
+
+        let timeless = util.timeless_world(&src)?;
+        // before the world, so noise in ctx uses it
+        util.set_noise_seed(self.seed.o(&timeless)?);
+        let mut timing = self.time.o(&timeless)?.to_livecode();
+        timing.realtime = false;
+        util.update_with_frame(frame);
+        util.update_stateful_funcs(frame, 1.0 / timing.fps);
+        let time = util.time(&timing);
+
+        let mut more_vals = self.timeline.vals(&time);
+        let triggers = vec![false; self.envelopes.names().len()];
+        more_vals.extend(EnvelopeStates::new().update(&self.envelopes, &triggers, &time));
+
+        let mut world =
+            util.world_with_vals(&src, &timing, &self.ctx, Assets::empty_ref(), more_vals)?;
+
+        let mut md = MixedEvalDefs::new();
+        for name in used_vars.difference(&world.vars()) {
+            md.set_val(name, LivecodeValue::Float(0.0));
+        }
+        world.update_with_defs(MixedEvalDefsRef::new(md));
+
+        Ok(world)
+    }
+}
+
 fn _default_lerp_rate() -> ControlF32 {
     ControlF32::Int(0)
 }
@@ -531,6 +712,10 @@ fn _default_reload_on_bar() -> ControlBool {
     ControlBool::Raw(false)
 }
 
+fn _default_boop() -> ControlAppConfigBoop {
+    ControlAppConfigBoop::default()
+}
+
 fn _default_gpu() -> ControlGpuConfig {
     ControlGpuConfig::default()
 }
@@ -556,6 +741,9 @@ pub struct AppConfig {
     pub bg_color: MurreletColor,
     #[livecode(serde_default = "_default_capture_frame")]
     pub capture_frame: bool,
+    // writes the evaluated config to a yaml, see LiveCoder::freeze
+    #[livecode(serde_default = "_default_freeze")]
+    pub freeze: bool,
     #[livecode(serde_default = "1")]
     pub redraw: u64, // controls should_redraw, how many frames between redraw
     #[livecode(serde_default = "true")]
@@ -567,6 +755,30 @@ pub struct AppConfig {
     #[livecode(kind = "none")]
     #[livecode(serde_default = "_default_ctx")]
     pub ctx: AdditionalContextNode,
+    // e.g. `- wobble(x, amt) = clamp(x + amt, 0.0, 1.0)`, usable in any expression
+    #[livecode(kind = "none")]
+    #[livecode(serde_default = "_default_functions")]
+    pub functions: UserFunctions,
+    // adsr envelopes, e.g. `hit: { trigger: kAf, attack: 0.1, release: 0.5 }` adds `hit`
+    #[livecode(kind = "none")]
+    #[livecode(serde_default = "_default_envelopes")]
+    pub envelopes: Envelopes,
+    // keyframed tracks, e.g. `zoom: { keys: [{ at: 0, value: 0.0 }, { at: 8, value: 1.0 }] }` adds `zoom`
+    #[livecode(kind = "none")]
+    #[livecode(serde_default = "_default_timeline")]
+    pub timeline: Timeline,
+    // scenes to switch between on the bar, see crate::setlist
+    #[livecode(kind = "none")]
+    #[livecode(serde_default = "_default_setlist")]
+    pub setlist: Setlist,
+    // how reloads move to this config, e.g. `{ start: bar, beats: 4, ease: cubic_in_out }`
+    #[livecode(kind = "none")]
+    #[livecode(serde_default = "_default_transition")]
+    pub transition: Transition,
+    // step back through configs that loaded, e.g. `{ back: kZf, forward: kXf }`
+    #[livecode(kind = "none")]
+    #[livecode(serde_default = "_default_history")]
+    pub history: HistoryConf,
     #[livecode(serde_default = "default")]
     pub svg: SvgConfig,
     #[livecode(serde_default = "default")]
@@ -579,7 +791,11 @@ pub struct AppConfig {
     #[livecode(serde_default = "_empty_filenames")]
     pub assets: AssetFilenames, // for svg files!
     #[livecode(serde_default = "0")] // if 0, it won't run at all
-    pub lerp_rate: f32,
+    pub lerp_rate: f32, // per second, only used if transition.beats is 0
+    #[livecode(serde_default = "default")]
+    pub boop: AppConfigBoop,
+    #[livecode(serde_default = "false")]
+    pub compiled_exprs: bool, // faster expressions, see murrelet_livecode::compiled
 }
 impl AppConfig {
     pub fn should_clear_bg(&self) -> bool {
@@ -609,10 +825,6 @@ impl AppConfig {
     fn reload_on_bar(&self) -> bool {
         self.reload_on_bar
     }
-
-    fn should_lerp(&self) -> bool {
-        self.lerp_rate > 0.0
-    }
 }
 
 // todo, this is all a little weird (svg save path), i should revisit it..
@@ -663,31 +875,76 @@ fn capture_folder(save_path: &Path, run_id: u64) -> PathBuf {
     save_path.join(format!("{}", run_id))
 }
 
+// nest_update skips paths it doesn't know and values it can't parse, so this
+// checks that `conf`, which had `path` set to `value`, has it now. `flag` is
+// for the error, like "--set"
+pub(crate) fn check_nest_update<T: NestEditable>(
+    conf: &T,
+    path: &str,
+    value: &str,
+    flag: &str,
+) -> LivecodeResult<()> {
+    let got = conf
+        .nest_getter(path)
+        .map_err(|err| LivecodeError::Raw(format!("{} {}: {}", flag, path, err)))?;
+    let same_number = matches!(
+        (got.parse::<f32>(), value.parse::<f32>()),
+        (Ok(a), Ok(b)) if a == b
+    );
+    if got != value && !same_number {
+        return Err(LivecodeError::Raw(format!(
+            "{} {}={} didn't take, it's still {}",
+            flag, path, value, got
+        )));
+    }
+    Ok(())
+}
+
+fn control_to_yaml<T: serde::Serialize>(conf: &T) -> LivecodeResult<String> {
+    serde_yaml::to_string(conf)
+        .map_err(|err| LivecodeError::Raw(format!("couldn't write the config: {}", err)))
+}
+
 pub struct LiveCoder<ConfType, ControlConfType>
 where
     ConfType: ConfCommon + Send + Sync,
     ControlConfType: LiveCodeCommon<ConfType>,
 {
     run_id: u64,
-    pub controlconfig: ControlConfType,            // latest one
-    queued_configcontrol: Option<ControlConfType>, // if a new one comes in before we're done, queue it!
+    pub controlconfig: ControlConfType, // latest one
     util: LiveCodeUtil,
     livecode_src: LivecodeSrc, // get info from outside world
     save_path: Option<PathBuf>,
-    pub prev_controlconfig: ControlConfType, // last one
+    pub prev_controlconfig: ControlConfType, // where the transitions start from
+    // moving from prev_controlconfig through each of these, the last is controlconfig
+    transitions: Vec<(ControlConfType, TransitionState)>,
     curr_conf: Option<ConfType>,
     // sorry, the cache is mixed between curr_conf, but sometimes we need this
     cached_timeless_app_config: Option<AppConfigTiming>,
     cached_world: Option<LivecodeWorldState>,
     assets: AssetsRef,
+    load_funcs: AssetLoaders,           // for reloading assets that changed
     maybe_args: Option<BaseConfigArgs>, // should redesign this...
-    lerp_pct: f32,                      // moving between things
     used_variable_names: HashSet<String>,
     outgoing_msgs: Vec<(String, String, LivecodeValue)>, // addr, name, value
+    boop_states: BoopStates,                             // for the fields that are booped
+    envelope_states: EnvelopeStates,                     // for app.envelopes
+    lint_warnings: Vec<LintWarning>,                     // from the last config that loaded
+    setlist: Option<SetlistState>,                       // if the config has app.setlist
+    history: ConfigHistory<ControlConfType>,             // configs that loaded, for app.history
+    recorder: Option<InputRecorder>,                     // if recording the live inputs
+    replay: Option<InputReplay>,                         // if using recorded inputs instead
+    frame_time: bool, // time goes by frame, even if app.time.realtime is on
+    last_app: MurreletAppInput, // for the capture bundle's frame files
+    bundle: Option<RefCell<CaptureBundle>>, // if there's a save path
+    freeze_was_on: bool, // only freeze once per press
+    overrides: HashMap<String, String>, // from --set, applied after evaluating
+    watcher: Option<FileWatcher>, // if the files are watched, instead of polled
+    file_changes: FileChanges, // seen by the watcher, waiting to be used
 }
 impl<ConfType, ControlConfType> LiveCoder<ConfType, ControlConfType>
 where
-    ConfType: ConfCommon + Send + Sync + Lerpable,
+    ConfType: ConfCommon + Send + Sync + Lerpable + Boopable + Transitionable + NestEditable,
     ControlConfType: LiveCodeCommon<ConfType>,
 {
     pub fn new_web(
@@ -696,7 +953,9 @@ where
         load_funcs: &AssetLoaders,
     ) -> LivecodeResult<LiveCoder<ConfType, ControlConfType>> {
         let controlconfig = ControlConfType::parse(&conf)?;
-        Self::new_full(controlconfig, None, livecode_src, load_funcs, None)
+        let mut s = Self::new_full(controlconfig.clone(), None, livecode_src, load_funcs, None)?;
+        s.remember_config(controlconfig, conf);
+        Ok(s)
     }
 
     // this one panics if something goes wrong
@@ -705,18 +964,40 @@ where
         livecode_src: LivecodeSrc,
         load_funcs: &AssetLoaders,
     ) -> LiveCoder<ConfType, ControlConfType> {
-        let controlconfig = ControlConfType::fs_load();
-
         let args = BaseConfigArgs::parse();
 
-        let result = Self::new_full(
-            controlconfig,
+        let controlconfig =
+            ControlConfType::fs_parse_layered(&args.config_paths(), &args.template_path)
+                .unwrap_or_else(|err| panic!("{}", err));
+
+        let recorder = args
+            .record_inputs
+            .as_ref()
+            .map(|path| InputRecorder::new(path).expect("error starting the recording!"));
+        let replay = args
+            .replay_inputs
+            .as_ref()
+            .map(|path| InputReplay::load(path).expect("error loading the recording!"));
+
+        let mut result = Self::new_full(
+            controlconfig.clone(),
             Some(save_path),
             livecode_src,
             load_funcs,
             Some(args),
-        );
-        result.expect("error loading!")
+        )
+        .expect("error loading!");
+        result.recorder = recorder;
+        if let Some(replay) = replay {
+            result.replay_inputs(replay);
+        }
+        result.update_setlist(&controlconfig);
+        match result.loaded_yaml() {
+            Ok(yaml) => result.remember_config(controlconfig, yaml),
+            Err(e) => eprintln!("Error {}", e),
+        }
+        result.start_watching();
+        result
     }
 
     pub fn new_full(
@@ -736,28 +1017,55 @@ where
             .map(|x| x.name)
             .collect();
 
+        let overrides = maybe_args
+            .as_ref()
+            .map(|args| args.overrides())
+            .unwrap_or_default();
+
+        let bundle = save_path
+            .as_ref()
+            .map(|save_path| RefCell::new(CaptureBundle::new(capture_folder(save_path, run_id))));
+
         let mut s = LiveCoder {
             run_id,
             controlconfig: controlconfig.clone(),
-            queued_configcontrol: None,
             livecode_src,
             util,
             save_path,
             prev_controlconfig: controlconfig,
+            transitions: vec![],
             curr_conf: None,
             cached_timeless_app_config: None, // uninitialized
             cached_world: None,
             assets: Assets::empty_ref(),
+            load_funcs: load_funcs.clone(),
             maybe_args,
-            lerp_pct: 1.0, // start in the done state!
             used_variable_names,
             outgoing_msgs: vec![],
+            boop_states: BoopStates::new(),
+            envelope_states: EnvelopeStates::new(),
+            lint_warnings: vec![],
+            setlist: None,
+            history: ConfigHistory::new(),
+            recorder: None,
+            replay: None,
+            frame_time: false,
+            last_app: MurreletAppInput::default(),
+            bundle,
+            freeze_was_on: false,
+            overrides,
+            watcher: None,
+            file_changes: FileChanges::default(),
         };
 
+        s.update_user_functions()?;
+
         // hrm, before doing most things, load the assets (but we'll do this line again...)
 
         s.cached_timeless_app_config = Some(s._timing_config().o(&s._timeless_world()?)?);
         s._update_world()?;
+        s.lint_warnings = s.lint_config(&s.controlconfig);
+        s.print_lint_warnings();
 
         let w = s.world();
         let app_conf = s.controlconfig._app_config().o(w)?;
@@ -766,10 +1074,19 @@ where
 
         // use the object to create a world and generate the configs
         s.set_processed_config()?;
+        s.check_overrides()?;
 
         Ok(s)
     }
 
+    // make sure each --set actually did something
+    fn check_overrides(&self) -> LivecodeResult<()> {
+        for (path, value) in &self.overrides {
+            check_nest_update(self.config(), path, value, "--set")?;
+        }
+        Ok(())
+    }
+
     // experimental...
     pub fn set_control_config(&mut self, control_config: ControlConfType) {
         self.controlconfig = control_config;
@@ -787,38 +1104,65 @@ where
     // with initially loading it, you might just not start the program
     pub fn set_processed_config(&mut self) -> LivecodeResult<()> {
         // set this one first, so we can use it to get the world
+        let timeless = self._timeless_world()?;
+        self.cached_timeless_app_config = Some(self._timing_config().o(&timeless)?);
 
-        self.cached_timeless_app_config = Some(self._timing_config().o(&self._timeless_world()?)?);
+        // set this before building the world, so noise (even in ctx) uses this
+        // config's seed
+        let seed = self.controlconfig._app_config().seed.o(&timeless)?;
+        self.util.set_noise_seed(seed);
         self._update_world()?;
 
-        let w = self.world();
+        // move the transitions along, they count in beats
+        let time = self.world().time();
+        let bpm = self.cached_timeless_app_config.as_ref().unwrap().bpm;
+        let beats = self.time_delta() * bpm / 60.0;
+        for (_, state) in self.transitions.iter_mut() {
+            state.update(&time, beats);
+        }
 
-        let mut target = self.controlconfig.o(w)?;
-        let mut lerp_change = 0.0;
+        let w = self.world();
 
-        // todo, make this optional
-        if target.config_app_loc().should_lerp() {
-            if self.lerp_pct < 1.0 {
-                let old_target = self.prev_controlconfig.o(w)?;
-                target = old_target.lerpify(&target, &self.lerp_pct);
+        // each transition starts from wherever the ones before it got to
+        let mut target = if self.transitions.is_empty() {
+            self.controlconfig.o(w)?
+        } else {
+            let mut target = self.prev_controlconfig.o(w)?;
+            for (conf, state) in &self.transitions {
+                let next = conf.o(w)?;
+                let lerped = target.lerpify(&next, &state.pct());
+                target = lerped.transition(&target, &next, state);
             }
-
-            // prepare this for next time
-            lerp_change = self.time_delta() * target.config_app_loc().lerp_rate;
+            target
         };
 
-        let _t = w.time().bar();
+        // smooth out anything marked boop, this happens after lerping so it
+        // also smooths the jump when a config reloads
+        let boop_conf = target.config_app_loc().boop.to_boop_conf();
+        let seconds = w.time().seconds();
+        target = self.boop_states.boop_all(&boop_conf, seconds, &target);
+
+        // this rebuilds the whole config every frame, so it's skipped unless
+        // there's a --set
+        if !self.overrides.is_empty() {
+            target = target.nest_update(NestedMod::from_dict(&self.overrides));
+        }
 
         // set the current config
         self.curr_conf = Some(target);
 
-        self.lerp_pct += lerp_change;
-
-        if self.lerp_pct >= 1.0 {
-            // todo, just move it out...
-            if let Some(new_target) = &self.queued_configcontrol {
-                self.update_config_directly(new_target.clone())?;
-            }
+        // the world for this frame is already made, so this kicks in next frame
+        self.util
+            .set_compiled_exprs(self.app_config().compiled_exprs);
+
+        // once one is done, nothing before it shows anymore
+        let done = self
+            .transitions
+            .iter()
+            .rposition(|(_, state)| state.is_done(ConfType::transition_beats(state.beats())));
+        if let Some(done) = done {
+            let mut finished = self.transitions.drain(..=done);
+            self.prev_controlconfig = finished.next_back().unwrap().0;
         }
 
         Ok(())
@@ -843,55 +1187,389 @@ where
         svg_save_path_with_prefix(&self.to_lil_liveconfig().unwrap(), prefix)
     }
 
+    // checks the variables and functions the config uses against the current
+    // world, so typos show up now instead of when the frame fails to evaluate.
+    pub fn lint_config(&self, conf: &ControlConfType) -> Vec<LintWarning> {
+        let known_vars = self
+            .cached_world
+            .as_ref()
+            .map(|w| w.vars())
+            .unwrap_or_default();
+        let mut lint = IdentifierLint::new(known_vars);
+
+        let ctx = &conf._app_config().ctx;
+        lint.add_vars(ctx.defined_vars());
+        lint.add_funcs(conf._app_config().functions.names());
+        lint.add_vars(conf._app_config().envelopes.names());
+        lint.add_vars(conf._app_config().timeline.names());
+
+        let mut warnings = lint.check_ctx(ctx);
+        warnings.extend(lint.check(conf));
+        warnings.sort();
+        warnings.dedup();
+        warnings
+    }
+
+    pub fn lint_warnings(&self) -> &[LintWarning] {
+        &self.lint_warnings
+    }
+
+    fn print_lint_warnings(&self) {
+        for w in &self.lint_warnings {
+            eprintln!("warning: {}", w);
+        }
+    }
+
+    fn set_lint_warnings(&mut self, conf: &ControlConfType) {
+        self.lint_warnings = self.lint_config(conf);
+        self.print_lint_warnings();
+    }
+
     // sorry i'm near getting this to work so leaving this hacky and confusing
     // there's one for filesystems and one for callback..
     // filesystem one (watching folders)
     fn reload_config(&mut self) {
-        let result = ControlConfType::fs_load_if_needed_and_update_info(&mut self.util);
+        // with a watcher, only load when one of the config's files changed.
+        // otherwise, check the times every so often
+        let watching = self.watcher.is_some();
+        if watching && !std::mem::take(&mut self.file_changes.config) {
+            return;
+        }
+        let util = &mut self.util;
+        let mut load_with =
+            |files: &[PathBuf], load: &dyn Fn() -> LivecodeResult<ControlConfType>| {
+                if watching {
+                    ControlConfType::fs_load_and_update_info_with(util, load).map(Some)
+                } else {
+                    ControlConfType::fs_load_if_needed_and_update_info_with(util, files, load)
+                }
+            };
+
+        // with a setlist, watch the current scene instead
+        let result = match &self.setlist {
+            Some(setlist) => load_with(&[setlist.watched_file()], &|| {
+                setlist.load(ControlConfType::fs_template_foldername())
+            }),
+            None => match &self.maybe_args {
+                Some(args) => {
+                    let config_paths = args.config_paths();
+                    load_with(&config_paths, &|| {
+                        ControlConfType::fs_parse_layered(&config_paths, &args.template_path)
+                    })
+                }
+                None => load_with(&[ControlConfType::fs_config_filename().into()], &|| {
+                    ControlConfType::_fs_load()
+                }),
+            },
+        };
         if let Ok(Some(d)) = result {
-            // if we're in the middle of something, put this in the queue
-            if self.lerp_pct < 1.0 && self.lerp_pct > 0.0 {
-                self.queued_configcontrol = Some(d);
-            } else {
-                self.prev_controlconfig = self.controlconfig.clone();
-                self.controlconfig = d;
-                self.lerp_pct = 0.0; // reloaded, so time to reload it!
+            self.update_setlist(&d);
+            match self.loaded_yaml() {
+                Ok(yaml) => self.remember_config(d.clone(), yaml),
+                Err(e) => eprintln!("Error {}", e),
             }
-
-            // set the current vars
-            let variables_iter = self
-                .controlconfig
-                .variable_identifiers()
-                .into_iter()
-                .chain(self.prev_controlconfig.variable_identifiers());
-            let variables = if let Some(queued) = &self.queued_configcontrol {
-                variables_iter
-                    .chain(queued.variable_identifiers())
-                    .map(|x| x.name)
-                    .collect::<HashSet<String>>()
-            } else {
-                variables_iter.map(|x| x.name).collect::<HashSet<String>>()
-            };
-            self.used_variable_names = variables;
+            let transition = self.transition_for(&d);
+            self.use_new_config(d, transition);
         } else if let Err(e) = result {
             eprintln!("Error {}", e);
+            self.watch_template_folders();
+        }
+    }
+
+    // a freshly loaded config, starts a transition to it
+    fn use_new_config(&mut self, d: ControlConfType, transition: Transition) {
+        let new_conf = d.clone();
+        self.transition_to(d, transition);
+
+        // set the current vars
+        self.used_variable_names = self
+            .configs()
+            .flat_map(|c| c.variable_identifiers())
+            .map(|x| x.name)
+            .collect::<HashSet<String>>();
+
+        if let Err(e) = self.update_user_functions() {
+            eprintln!("Error {}", e);
+        }
+        self.util.reset_stateful_funcs();
+        self.set_lint_warnings(&new_conf);
+        self.update_watched_files();
+    }
+
+    // watch the files we loaded from, so changes reload right away. if that
+    // doesn't work, it goes back to checking the times every so often
+    fn start_watching(&mut self) {
+        match FileWatcher::new() {
+            Ok(watcher) => {
+                self.watcher = Some(watcher);
+                self.update_watched_files();
+            }
+            Err(e) => eprintln!("Error {}, checking for changes on a timer instead", e),
+        }
+    }
+
+    // the config and the templates it uses, and its assets, can change with
+    // each config
+    fn update_watched_files(&mut self) {
+        if self.watcher.is_none() {
+            return;
+        }
+        let config_files = self.config_files();
+        let asset_files = self
+            .controlconfig
+            ._app_config()
+            .o(self.world())
+            .map(|app_conf| app_conf.assets.paths());
+
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        if let Err(e) = watcher.set_files(WatchedKind::Config, &config_files) {
+            eprintln!("Error {}", e);
+        }
+        // it loaded, so it has all its templates
+        if let Err(e) = watcher.set_folders::<PathBuf>(WatchedKind::Config, &[]) {
+            eprintln!("Error {}", e);
+        }
+        match asset_files {
+            Ok(asset_files) => {
+                if let Err(e) = watcher.set_files(WatchedKind::Asset, &asset_files) {
+                    eprintln!("Error {}", e);
+                }
+            }
+            Err(e) => eprintln!("Error {}", e),
+        }
+    }
+
+    // while the config doesn't load, it might be waiting on a template that
+    // doesn't exist yet, so any new file in the template folders reloads it
+    fn watch_template_folders(&mut self) {
+        if self.watcher.is_none() {
+            return;
+        }
+        // the config might use different files now too
+        let config_files = self.config_files();
+        let folders = template_dirs(self.config_sources().1);
+
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        if let Err(e) = watcher.set_files(WatchedKind::Config, &config_files) {
+            eprintln!("Error {}", e);
+        }
+        if let Err(e) = watcher.set_folders(WatchedKind::Config, &folders) {
+            eprintln!("Error {}", e);
+        }
+    }
+
+    // the files the current config is loaded from, and the template folder
+    fn config_sources(&self) -> (Vec<PathBuf>, PathBuf) {
+        match &self.setlist {
+            Some(setlist) => (
+                vec![setlist.watched_file()],
+                PathBuf::from(ControlConfType::fs_template_foldername()),
+            ),
+            None => match &self.maybe_args {
+                Some(args) => (args.config_paths(), args.template_path.clone()),
+                None => (
+                    vec![ControlConfType::fs_config_filename().into()],
+                    ControlConfType::fs_template_foldername().into(),
+                ),
+            },
+        }
+    }
+
+    // the files the current config is loaded from, with every template they use
+    fn config_files(&self) -> Vec<PathBuf> {
+        let (files, includes_dir) = self.config_sources();
+
+        let mut all_files = files.clone();
+        for file in &files {
+            if let Ok(text) = fs::read_to_string(file) {
+                all_files.extend(template_files(&text, &includes_dir));
+            }
+        }
+        all_files
+    }
+
+    // shader source files to watch, see take_shader_changes
+    pub fn watch_shader_files<P: AsRef<Path>>(&mut self, paths: &[P]) {
+        if let Some(watcher) = &mut self.watcher
+            && let Err(e) = watcher.set_files(WatchedKind::Shader, paths)
+        {
+            eprintln!("Error {}", e);
+        }
+    }
+
+    // the shader files that changed since the last call, so just those
+    // shaders can be rebuilt
+    pub fn take_shader_changes(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.file_changes.shaders)
+    }
+
+    // loads the asset files that changed again, the next world will use
+    // them. if one doesn't load, it keeps the old version
+    fn reload_assets(&mut self) {
+        let changed = std::mem::take(&mut self.file_changes.assets);
+        if changed.is_empty() {
+            return;
+        }
+        let filenames = self.app_config().assets.clone();
+        let errors = filenames.reload(&changed, &self.load_funcs, Arc::make_mut(&mut self.assets));
+        for e in errors {
+            eprintln!("Error {}, keeping the old version", e);
+        }
+    }
+
+    // the yaml for the config that just loaded from the filesystem
+    fn loaded_yaml(&self) -> LivecodeResult<String> {
+        match &self.setlist {
+            Some(setlist) => setlist.yaml(ControlConfType::fs_template_foldername()),
+            None => match &self.maybe_args {
+                Some(args) => read_layered_yaml(&args.config_paths(), &args.template_path),
+                None => read_yaml(
+                    ControlConfType::fs_config_filename(),
+                    ControlConfType::fs_template_foldername(),
+                ),
+            },
+        }
+    }
+
+    // keeps a config that loaded, so we can step back to it later
+    pub(crate) fn remember_config(&mut self, conf: ControlConfType, yaml: String) {
+        let history_conf = conf._app_config().history.clone();
+        let entry = self.history.push(conf, yaml, history_conf.size());
+
+        if history_conf.should_save()
+            && let Some(save_path) = &self.save_path
+        {
+            let folder = capture_folder(save_path, self.run_id).join("configs");
+            if let Err(e) = save_history_entry(&folder, entry) {
+                eprintln!("Error {}", e);
+            }
+        }
+    }
+
+    // the yaml for the config that's showing
+    pub fn current_yaml(&self) -> Option<&str> {
+        self.history.current().map(|entry| entry.yaml())
+    }
+
+    pub fn history(&self) -> &ConfigHistory<ControlConfType> {
+        &self.history
+    }
+
+    // what changed between two configs in the history, by their ids
+    pub fn history_diff(&self, from: u64, to: u64) -> LivecodeResult<Vec<ConfigDiff>> {
+        self.history.diff(from, to)
+    }
+
+    // starts following the config's setlist, or picks up changes to it
+    fn update_setlist(&mut self, conf: &ControlConfType) {
+        let setlist = &conf._app_config().setlist;
+        match &mut self.setlist {
+            Some(state) => state.reloaded(setlist),
+            None if !setlist.is_empty() => {
+                let root = PathBuf::from(ControlConfType::fs_config_filename());
+                self.setlist = Some(SetlistState::new(setlist.clone(), root));
+            }
+            None => {}
+        }
+    }
+
+    // if a scene is queued, load it. call this on the bar
+    fn switch_scene(&mut self) {
+        let Some(setlist) = &mut self.setlist else {
+            return;
+        };
+        let Some(pos) = setlist.take_queued() else {
+            return;
+        };
+        let crossfade = setlist.crossfade();
+
+        match setlist.load::<ControlConfType, _>(ControlConfType::fs_template_foldername()) {
+            Ok(d) => {
+                match self.loaded_yaml() {
+                    Ok(yaml) => self.remember_config(d.clone(), yaml),
+                    Err(e) => eprintln!("Error {}", e),
+                }
+                // we're already on the bar, so don't wait for another one
+                let mut transition = self.transition_for(&d).with_start(TransitionStart::Now);
+                if crossfade > 0.0 {
+                    transition = transition.with_beats(crossfade);
+                }
+                self.use_new_config(d, transition);
+            }
+            Err(e) => eprintln!("Error loading scene {}: {}", pos, e),
         }
     }
 
     // web one, callback
     pub fn update_config_to(&mut self, text: &str) -> Result<(), String> {
         match ControlConfType::cb_reload_and_update_info(&mut self.util, text) {
-            Ok(d) => self.update_config_directly(d).map_err(|x| x.to_string()),
+            Ok(d) => {
+                let new_conf = d.clone();
+                self.remember_config(d.clone(), text.to_owned());
+                self.update_config_directly(d).map_err(|x| x.to_string())?;
+                self.util.reset_stateful_funcs();
+                self.set_lint_warnings(&new_conf);
+                Ok(())
+            }
             Err(e) => Err(e),
         }
     }
 
     pub fn update_config_directly(&mut self, control_conf: ControlConfType) -> LivecodeResult<()> {
-        self.prev_controlconfig = self.controlconfig.clone();
-        self.controlconfig = control_conf;
-        self.queued_configcontrol = None;
-        self.lerp_pct = 0.0;
-        Ok(())
+        let transition = self.transition_for(&control_conf);
+        self.transition_to(control_conf, transition);
+        self.update_user_functions()
+    }
+
+    // the new config says how to get to it. if it doesn't set
+    // transition.beats, lerp_rate still works
+    fn transition_for(&self, conf: &ControlConfType) -> Transition {
+        let app = conf._app_config();
+        if app.transition.beats() > 0.0 {
+            return app.transition;
+        }
+
+        let lerp_rate = app.lerp_rate.o(self.world()).unwrap_or(0.0);
+        if lerp_rate > 0.0 {
+            // lerp_rate is per second
+            let beats = self.app_config().time.bpm / 60.0 / lerp_rate;
+            app.transition.with_beats(beats)
+        } else {
+            app.transition
+        }
+    }
+
+    // if another transition is going, this one starts from wherever that one's at
+    fn transition_to(&mut self, conf: ControlConfType, transition: Transition) {
+        if transition.is_cut() {
+            self.prev_controlconfig = conf.clone();
+            self.transitions.clear();
+        } else {
+            self.transitions
+                .push((conf.clone(), TransitionState::new(transition)));
+        }
+        self.controlconfig = conf;
+    }
+
+    // every config that still gets evaluated, from the oldest
+    fn configs(&self) -> impl Iterator<Item = &ControlConfType> {
+        std::iter::once(&self.prev_controlconfig)
+            .chain(self.transitions.iter().map(|(conf, _)| conf))
+            .chain(std::iter::once(&self.controlconfig))
+    }
+
+    // functions from the config's `functions:`. this includes the configs
+    // we're transitioning from too, since those still get evaluated
+    fn update_user_functions(&mut self) -> LivecodeResult<()> {
+        let funcs = self
+            .configs()
+            .map(|conf| conf._app_config().functions.clone())
+            .collect::<Vec<_>>();
+        self.util.set_user_functions(&funcs)
     }
 
     /// if the bg_alpha is above 0.5 or clear_bg is true
@@ -907,8 +1585,52 @@ where
         self.outgoing_msgs.push((addr, name, value));
     }
 
+    // starts writing the live inputs to `path` every frame
+    pub fn record_inputs<P: AsRef<Path>>(&mut self, path: P) -> LivecodeResult<()> {
+        self.recorder = Some(InputRecorder::new(path)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> LivecodeResult<()> {
+        match self.recorder.take() {
+            Some(mut recorder) => recorder.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // uses the recorded inputs instead of the livecode sources from now on
+    pub fn replay_inputs(&mut self, replay: InputReplay) {
+        self.livecode_src = LivecodeSrc::new(vec![Box::new(replay.src())]);
+        self.replay = Some(replay);
+        self.use_frame_time();
+    }
+
+    // so it's the same each time, whatever speed it renders at
+    pub fn use_frame_time(&mut self) {
+        self.frame_time = true;
+    }
+
+    // if there's a replay and it's past the last recorded frame
+    pub fn replay_is_done(&self) -> bool {
+        self.replay
+            .as_ref()
+            .is_some_and(|replay| replay.is_done(self.frame()))
+    }
+
     // called every frame
     pub fn update(&mut self, app: &MurreletAppInput, reload: bool) -> LivecodeResult<()> {
+        // when replaying, use what the app sent when it was recorded
+        let replayed = self
+            .replay
+            .as_ref()
+            .and_then(|replay| replay.get(app.elapsed_frames()))
+            .map(|recorded| MurreletAppInput {
+                elapsed_frames: app.elapsed_frames(),
+                ..recorded.app().clone()
+            });
+        let app = replayed.as_ref().unwrap_or(app);
+        self.last_app = app.clone();
+
         // use the previous frame's world for this
         let update_input = LivecodeSrcUpdateInput::new(
             self.app_config().debug,
@@ -918,6 +1640,13 @@ where
 
         self.livecode_src.update(&update_input);
 
+        if let Some(recorder) = &mut self.recorder
+            && let Err(e) = recorder.record(app, self.livecode_src.to_world_vals())
+        {
+            eprintln!("Error {}, stopping the recording", e);
+            self.recorder = None;
+        }
+
         // todo, set this as a variable?
         if app.elapsed_frames().is_multiple_of(1) {
             let variables = self
@@ -943,9 +1672,35 @@ where
 
         // needs to happen before checking is on bar
         self.util.update_with_frame(app.elapsed_frames());
+        let dt = self.world().time().seconds_between_render_times();
+        self.util.update_stateful_funcs(app.elapsed_frames(), dt);
 
         // if we can reload whenever, do that. otherwise only reload on bar
 
+        if let (Some(setlist), Some(w)) = (&mut self.setlist, &self.cached_world) {
+            setlist.check_triggers(w)?;
+        }
+        if self.world().time().is_on_bar() {
+            self.switch_scene();
+        }
+
+        // stepping through the history happens right away
+        let stepped = match &self.cached_world {
+            Some(w) => self
+                .history
+                .check_triggers(&self.controlconfig._app_config().history, w)?,
+            None => None,
+        };
+        if let Some(conf) = stepped {
+            let transition = self.transition_for(&conf);
+            self.use_new_config(conf, transition);
+        }
+
+        // collect what the watcher saw, even if it isn't time to reload
+        if let Some(watcher) = &mut self.watcher {
+            self.file_changes.merge(watcher.changes());
+        }
+        self.reload_assets();
         if reload && (!self.app_config().reload_on_bar() || self.world().time().is_on_bar()) {
             self.reload_config();
         }
@@ -961,7 +1716,23 @@ where
 
         // this should happen at the very end
         // cache the world
-        self.set_processed_config()
+        self.set_processed_config()?;
+
+        let freeze = self.app_config().freeze;
+        if freeze
+            && !self.freeze_was_on
+            && let Err(e) = self.freeze()
+        {
+            eprintln!("Error {}", e);
+        }
+        self.freeze_was_on = freeze;
+
+        if self.should_save_svg()
+            && let Err(e) = self.write_capture_bundle()
+        {
+            eprintln!("Error {}", e);
+        }
+        Ok(())
     }
 
     pub fn _timeless_world(&self) -> LivecodeResult<LivecodeWorldState> {
@@ -971,13 +1742,24 @@ where
     pub fn _update_world(&mut self) -> LivecodeResult<()> {
         // this function should only be called after this is set! since the "set processed" is called right away
         let timeless_app_config = self.cached_timeless_app_config.as_ref().unwrap();
-        let timing_conf = timeless_app_config.to_livecode();
+        let mut timing_conf = timeless_app_config.to_livecode();
+        if self.frame_time {
+            timing_conf.realtime = false;
+        }
+
+        let mut more_vals = self.update_envelopes(&timing_conf)?;
+        let time = self.util.time(&timing_conf);
+        more_vals.extend(self.controlconfig._app_config().timeline.vals(&time));
 
         let ctx = &self.controlconfig._app_config().ctx;
 
-        let mut world =
-            self.util
-                .world(&self.livecode_src, &timing_conf, ctx, self.assets.clone())?;
+        let mut world = self.util.world_with_vals(
+            &self.livecode_src,
+            &timing_conf,
+            ctx,
+            self.assets.clone(),
+            more_vals,
+        )?;
 
         let mut md = MixedEvalDefs::new();
 
@@ -997,6 +1779,21 @@ where
         Ok(())
     }
 
+    // these (and the timeline) go in before ctx, so ctx can use them too
+    fn update_envelopes(
+        &mut self,
+        timing_conf: &LivecodeTimingConfig,
+    ) -> LivecodeResult<Vec<(String, LivecodeValue)>> {
+        let envelopes = &self.controlconfig._app_config().envelopes;
+        if envelopes.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let triggers = envelopes.eval_triggers(&self._timeless_world()?)?;
+        let time = self.util.time(timing_conf);
+        Ok(self.envelope_states.update(envelopes, &triggers, &time))
+    }
+
     pub fn world(&self) -> &LivecodeWorldState {
         // self.cached_world.as_ref().unwrap()
         self.cached_world.as_ref().unwrap()
@@ -1037,6 +1834,7 @@ where
             println!("writing to {:?}", img_name);
 
             capture_frame_fn(img_name);
+            self.write_capture_bundle()?;
 
             // save a copy of the config
             if !self.app_config().capture {
@@ -1045,7 +1843,13 @@ where
                 let img_name = capture_frame_name.with_extension("yaml");
 
                 if let Some(env) = &self.maybe_args {
-                    fs::copy(env.config_path.clone(), img_name).unwrap();
+                    if env.overlays.is_empty() {
+                        fs::copy(env.config_path.clone(), img_name).unwrap();
+                    } else {
+                        // the overlays merged in, so it's one file
+                        let yaml = read_layered_yaml(&env.config_paths(), &env.template_path)?;
+                        fs::write(img_name, yaml).expect("Unable to write file");
+                    }
                 } else {
                     println!("Hm, didn't have a base config args, but trying to save...");
                 }
@@ -1054,6 +1858,126 @@ where
         Ok(())
     }
 
+    // add a shader's source to the capture bundle
+    pub fn bundle_shader(&self, name: &str, source: &str) {
+        if let Some(bundle) = &self.bundle {
+            bundle.borrow_mut().add_shader(name, source);
+        }
+    }
+
+    fn bundle_timing(&self) -> BundleTiming {
+        let timing = self.cached_timeless_app_config.as_ref().unwrap();
+        BundleTiming {
+            bpm: timing.bpm,
+            beats_per_bar: timing.beats_per_bar,
+            fps: timing.fps,
+            realtime: timing.realtime && !self.frame_time,
+        }
+    }
+
+    // saves what's needed to remake this frame into the capture folder, see
+    // bundle.rs. this is called when capturing or saving svgs
+    pub fn write_capture_bundle(&self) -> LivecodeResult<()> {
+        let (Some(bundle), Some(entry)) = (&self.bundle, self.history.current()) else {
+            return Ok(());
+        };
+        let frame = self.world().actual_frame_u64();
+        let Some(frame_path) = self.capture_frame_name(frame, "") else {
+            return Ok(());
+        };
+
+        let mut bundle = bundle.borrow_mut();
+        let timing = self.bundle_timing();
+        bundle.write_run(self.maybe_args.as_ref(), timing)?;
+
+        // the templates are found from the file before they were filled in
+        let raw_config = || match &self.maybe_args {
+            Some(args) => args
+                .config_paths()
+                .iter()
+                .map(|path| fs::read_to_string(path).unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => String::new(),
+        };
+        let includes_dir = self
+            .maybe_args
+            .as_ref()
+            .map(|args| args.template_path.clone())
+            .unwrap_or_default();
+        let config = bundle.write_config(entry.id(), entry.yaml(), raw_config, includes_dir)?;
+
+        let time = self.world().time();
+        let sidecar = FrameSidecar {
+            config,
+            seed: self.app_config().seed,
+            timing,
+            seconds: time.seconds(),
+            beat: time.beat(),
+            app: MurreletAppInput {
+                elapsed_frames: frame,
+                ..self.last_app.clone()
+            },
+            vals: self.livecode_src.to_world_vals(),
+            world: self.world().values(),
+        };
+        bundle.write_frame(&frame_path, &sidecar)
+    }
+
+    // the config as it is right now, with the expressions swapped for the
+    // numbers they evaluated to. lazy fields are still expressions, and `app`
+    // is left as it is, so the key bindings and timing keep working
+    pub fn frozen_config(&self) -> LivecodeResult<ControlConfType> {
+        ControlConfType::parse(&self.frozen_yaml()?)
+    }
+
+    pub fn frozen_yaml(&self) -> LivecodeResult<String> {
+        let yaml_err =
+            |err: serde_yaml::Error| LivecodeError::Raw(format!("couldn't freeze: {}", err));
+        let mut frozen =
+            serde_yaml::to_value(ControlConfType::from_conf(self.config())).map_err(yaml_err)?;
+        let running = serde_yaml::to_value(&self.controlconfig).map_err(yaml_err)?;
+        if let (serde_yaml::Value::Mapping(frozen), Some(app)) = (&mut frozen, running.get("app")) {
+            frozen.insert("app".into(), app.clone());
+        }
+        control_to_yaml(&frozen)
+    }
+
+    // the control config that's running, with any changes from the gui or
+    // nest_update, as yaml that parses back to the same thing
+    pub fn control_yaml(&self) -> LivecodeResult<String> {
+        control_to_yaml(&self.controlconfig)
+    }
+
+    pub fn write_config<P: AsRef<Path>>(&self, path: P) -> LivecodeResult<()> {
+        let path = path.as_ref();
+        fs::write(path, self.control_yaml()?)
+            .map_err(|err| LivecodeError::Io(format!("couldn't write {:?}", path), err))
+    }
+
+    // writes frozen_yaml to <capture folder>/frozen/, which can be loaded as
+    // its own config. returns where it went
+    pub fn freeze(&self) -> LivecodeResult<PathBuf> {
+        let Some(save_path) = &self.save_path else {
+            return Err(LivecodeError::Raw(
+                "no save path, so nowhere to freeze to".to_owned(),
+            ));
+        };
+        let folder = capture_folder(save_path, self.run_id).join("frozen");
+        fs::create_dir_all(&folder)
+            .map_err(|err| LivecodeError::Io(format!("couldn't make {:?}", folder), err))?;
+
+        let path = folder.join(format!(
+            "frozen_{:05}_{}.yaml",
+            self.frame(),
+            MurreletTime::now().as_millis_u128()
+        ));
+        fs::write(&path, self.frozen_yaml()?)
+            .map_err(|err| LivecodeError::Io(format!("couldn't write {:?}", path), err))?;
+        println!("froze the config to {:?}", path);
+        Ok(path)
+    }
+
     pub fn capture_with_fn<F>(&self, capture_frame_fn: F) -> LivecodeResult<()>
     where
         F: Fn(PathBuf),
@@ -1143,3 +2067,184 @@ where
         self.run_id
     }
 }
+
+// the other modules' tests use Conf and APP too
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // what TopLevelLiveCode would make, but that one points at murrelet_perform
+    #[derive(Debug, Clone, Livecode, Lerpable)]
+    pub(crate) struct Conf {
+        pub(crate) app: AppConfig,
+        pub(crate) size: f32,
+    }
+
+    impl LiveCoderLoader for ControlConf {
+        fn _app_config(&self) -> &ControlAppConfig {
+            &self.app
+        }
+
+        fn parse(text: &str) -> LivecodeResult<Self> {
+            serde_yaml::from_str(text).map_err(|err| LivecodeError::Raw(err.to_string()))
+        }
+    }
+    impl ConfCommon for Conf {
+        fn config_app_loc(&self) -> &AppConfig {
+            &self.app
+        }
+    }
+    impl CommonTrait for Conf {}
+    impl CommonTrait for ControlConf {}
+    impl LiveCodeCommon<Conf> for ControlConf {
+        fn from_conf(conf: &Conf) -> Self {
+            conf.to_control()
+        }
+    }
+
+    // the default key bindings need a keyboard source, so skip them
+    pub(crate) const APP: &str = "
+app:
+  should_reset: false
+  capture_frame: f > 100
+  freeze: false
+  clear_bg: false
+  bg_alpha: 0.0
+  boop:
+    reset: false
+  gpu:
+    debug: false
+    debug_next: false
+";
+
+    fn livecoder_with_sets(
+        yaml: &str,
+        sets: &[&str],
+    ) -> LivecodeResult<LiveCoder<Conf, ControlConf>> {
+        let mut cmd = vec!["sketch", "config.yaml", "templates"];
+        for set in sets {
+            cmd.extend(["--set", set]);
+        }
+        LiveCoder::new_full(
+            ControlConf::parse(yaml).unwrap(),
+            None,
+            LivecodeSrc::new(vec![]),
+            &AssetLoaders::empty(),
+            Some(BaseConfigArgs::try_parse_from(cmd).unwrap()),
+        )
+    }
+
+    fn livecoder(yaml: &str) -> LiveCoder<Conf, ControlConf> {
+        livecoder_with_sets(yaml, &[]).unwrap()
+    }
+
+    #[test]
+    fn freezing_round_trips() {
+        let mut lc = livecoder(&format!("{}size: f * 2.0\n", APP));
+        lc.update(&MurreletAppInput::default_with_frames(5), false)
+            .unwrap();
+        assert_eq!(lc.config().size, 10.0);
+
+        let frozen: serde_yaml::Value = serde_yaml::from_str(&lc.frozen_yaml().unwrap()).unwrap();
+        assert_eq!(frozen["size"], serde_yaml::Value::from(10.0));
+        // app stays as it was, instead of baking the bindings into false
+        assert_eq!(
+            frozen["app"]["capture_frame"],
+            serde_yaml::Value::from("f > 100")
+        );
+
+        // and loading it again stays put
+        let mut frozen_lc = livecoder(&lc.frozen_yaml().unwrap());
+        for frame in [0, 5, 50] {
+            frozen_lc
+                .update(&MurreletAppInput::default_with_frames(frame), false)
+                .unwrap();
+            assert_eq!(frozen_lc.config().size, 10.0);
+        }
+        assert_eq!(
+            serde_yaml::to_value(lc.frozen_config().unwrap()).unwrap(),
+            frozen
+        );
+    }
+
+    #[test]
+    fn noise_in_ctx_uses_the_seed() {
+        let noise = |ctx: &str, size: &str| {
+            let lc = livecoder(&format!(
+                "{}  seed: 7.0\n  ctx: |\n    {}\nsize: {}\n",
+                APP, ctx, size
+            ));
+            // the first frame is evaluated while it loads
+            lc.config().size
+        };
+        let in_ctx = noise("n = simplex(0.3, 0.7);", "n");
+        let in_field = noise("", "simplex(0.3, 0.7)");
+        assert_eq!(in_ctx, in_field);
+        assert_ne!(in_ctx, noise("", "0.0"));
+    }
+
+    #[test]
+    fn sets_are_checked_at_startup() {
+        let yaml = format!("{}size: f * 2.0\n", APP);
+
+        let mut lc = livecoder_with_sets(&yaml, &["size=3", "app.seed=4"]).unwrap();
+        lc.update(&MurreletAppInput::default_with_frames(5), false)
+            .unwrap();
+        assert_eq!(lc.config().size, 3.0);
+        assert_eq!(lc.config().app.seed, 4.0);
+
+        // a typo'd path, or a value that can't go there
+        assert!(livecoder_with_sets(&yaml, &["sise=3"]).is_err());
+        assert!(livecoder_with_sets(&yaml, &["size.x=3"]).is_err());
+        assert!(livecoder_with_sets(&yaml, &["size=big"]).is_err());
+    }
+
+    #[test]
+    fn new_templates_reload_a_broken_config() {
+        let dir = std::env::temp_dir().join(format!("murrelet_templates_{}", std::process::id()));
+        let templates = dir.join("templates");
+        fs::create_dir_all(&templates).unwrap();
+        let config = dir.join("config.yaml");
+        let yaml = format!("{}size: 1.0\n", APP);
+        fs::write(&config, &yaml).unwrap();
+
+        let args = BaseConfigArgs::try_parse_from([
+            "sketch".as_ref(),
+            config.as_os_str(),
+            templates.as_os_str(),
+        ])
+        .unwrap();
+        let mut lc: LiveCoder<Conf, ControlConf> = LiveCoder::new_full(
+            ControlConf::parse(&yaml).unwrap(),
+            None,
+            LivecodeSrc::new(vec![]),
+            &AssetLoaders::empty(),
+            Some(args),
+        )
+        .unwrap();
+        lc.start_watching();
+
+        // changes come in on another thread, so keep updating for a bit
+        let mut frame = 0;
+        let mut run_for = |lc: &mut LiveCoder<Conf, ControlConf>, secs: f32| {
+            let start = std::time::Instant::now();
+            while start.elapsed().as_secs_f32() < secs {
+                lc.update(&MurreletAppInput::default_with_frames(frame), true)
+                    .unwrap();
+                frame += 1;
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+        };
+
+        // asks for a template that isn't there yet, so it doesn't load
+        fs::write(&config, format!("{}[[sized]]\n", APP)).unwrap();
+        run_for(&mut lc, 1.0);
+        assert_eq!(lc.config().size, 1.0);
+
+        fs::write(templates.join("sized.yaml"), "size: 3.0\n").unwrap();
+        run_for(&mut lc, 1.0);
+        assert_eq!(lc.config().size, 3.0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
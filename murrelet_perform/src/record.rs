@@ -0,0 +1,258 @@
+// records what the live inputs (midi, osc, audio, keys...) said each frame,
+// so a performance can be re-rendered later, e.g. at a higher resolution.
+//
+//   cargo run -- config.yaml templates/ --record-inputs set.inputs.yaml
+//   cargo run -- config.yaml templates/ --replay-inputs set.inputs.yaml
+//
+// the file has one yaml document per frame. while replaying, the recorded
+// values are used instead of the livecode sources, and time goes by frame
+// (like realtime: false), so record with realtime: false if the timing needs
+// to match exactly.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use murrelet_common::{IsLivecodeSrc, LivecodeSrcUpdateInput, LivecodeValue, MurreletAppInput};
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    app: MurreletAppInput,
+    vals: Vec<(String, LivecodeValue)>, // from every IsLivecodeSrc
+}
+impl RecordedFrame {
+    pub fn new(app: MurreletAppInput, vals: Vec<(String, LivecodeValue)>) -> Self {
+        Self { app, vals }
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.app.elapsed_frames()
+    }
+
+    pub fn app(&self) -> &MurreletAppInput {
+        &self.app
+    }
+
+    pub fn vals(&self) -> &[(String, LivecodeValue)] {
+        &self.vals
+    }
+}
+
+pub struct InputRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+impl InputRecorder {
+    pub fn new<P: AsRef<Path>>(path: P) -> LivecodeResult<Self> {
+        let path = path.as_ref().to_owned();
+        let file = File::create(&path)
+            .map_err(|err| LivecodeError::Io(format!("couldn't create {:?}", path), err))?;
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(
+        &mut self,
+        app: &MurreletAppInput,
+        vals: Vec<(String, LivecodeValue)>,
+    ) -> LivecodeResult<()> {
+        let frame = RecordedFrame::new(app.clone(), vals);
+        let yaml = serde_yaml::to_string(&frame)
+            .map_err(|err| LivecodeError::Raw(format!("couldn't record frame: {}", err)))?;
+        write!(self.writer, "---\n{}", yaml)
+            .map_err(|err| LivecodeError::Io(format!("couldn't write {:?}", self.path), err))
+    }
+
+    pub fn flush(&mut self) -> LivecodeResult<()> {
+        self.writer
+            .flush()
+            .map_err(|err| LivecodeError::Io(format!("couldn't write {:?}", self.path), err))
+    }
+}
+
+// a recording that's been loaded back in
+#[derive(Debug, Clone)]
+pub struct InputReplay {
+    frames: Arc<Vec<RecordedFrame>>, // sorted by frame
+}
+impl InputReplay {
+    pub fn load<P: AsRef<Path>>(path: P) -> LivecodeResult<Self> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .map_err(|err| LivecodeError::Io(format!("couldn't read {:?}", path), err))?;
+        Self::parse(&s)
+    }
+
+    pub fn parse(s: &str) -> LivecodeResult<Self> {
+        // a recording that stopped before the first frame is still a recording
+        if s.trim().is_empty() {
+            return Ok(Self {
+                frames: Arc::new(vec![]),
+            });
+        }
+
+        let mut frames = serde_yaml::Deserializer::from_str(s)
+            .map(RecordedFrame::deserialize)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| LivecodeError::Raw(format!("couldn't read recording: {}", err)))?;
+        frames.sort_by_key(|f| f.frame());
+        Ok(Self {
+            frames: Arc::new(frames),
+        })
+    }
+
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    pub fn last_frame(&self) -> Option<u64> {
+        self.frames.last().map(|f| f.frame())
+    }
+
+    // the recorded frame, or the last one before it, so it holds at the end
+    pub fn get(&self, frame: u64) -> Option<&RecordedFrame> {
+        match self.frames.binary_search_by_key(&frame, |f| f.frame()) {
+            Ok(i) => self.frames.get(i),
+            Err(i) => i.checked_sub(1).and_then(|i| self.frames.get(i)),
+        }
+    }
+
+    pub fn is_done(&self, frame: u64) -> bool {
+        self.last_frame().is_none_or(|last| frame > last)
+    }
+
+    // use this as the only livecode source
+    pub fn src(&self) -> ReplaySrc {
+        ReplaySrc {
+            replay: self.clone(),
+            frame: 0,
+        }
+    }
+}
+
+pub struct ReplaySrc {
+    replay: InputReplay,
+    frame: u64,
+}
+
+impl IsLivecodeSrc for ReplaySrc {
+    fn update(&mut self, input: &LivecodeSrcUpdateInput) {
+        self.frame = input.app().elapsed_frames();
+    }
+
+    fn to_exec_funcs(&self) -> Vec<(String, LivecodeValue)> {
+        self.replay
+            .get(self.frame)
+            .map(|f| f.vals.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reload::LiveCodeUtil;
+    use murrelet_common::{Assets, LivecodeSrc};
+    use murrelet_livecode::state::LivecodeTimingConfig;
+    use murrelet_livecode::types::AdditionalContextNode;
+
+    // a knob that moves and a pad that's hit every few frames
+    #[derive(Default)]
+    struct Knobs {
+        frame: u64,
+    }
+    impl IsLivecodeSrc for Knobs {
+        fn update(&mut self, input: &LivecodeSrcUpdateInput) {
+            self.frame = input.app().elapsed_frames();
+        }
+
+        fn to_exec_funcs(&self) -> Vec<(String, LivecodeValue)> {
+            vec![
+                (
+                    "m3".to_owned(),
+                    LivecodeValue::Float(self.frame as f64 * 0.1),
+                ),
+                (
+                    "pad".to_owned(),
+                    LivecodeValue::Bool(self.frame.is_multiple_of(3)),
+                ),
+            ]
+        }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("murrelet_{}_{}.yaml", name, std::process::id()))
+    }
+
+    #[test]
+    fn replaying_gives_the_same_world() {
+        let timing = LivecodeTimingConfig {
+            bpm: 120.0,
+            fps: 30.0,
+            realtime: false,
+            beats_per_bar: 4.0,
+        };
+        let mut util = LiveCodeUtil::new().unwrap();
+        let ctx = AdditionalContextNode::new_dummy();
+        let world_values =
+            |util: &mut LiveCodeUtil, src: &mut LivecodeSrc, app: &MurreletAppInput| {
+                src.update(&LivecodeSrcUpdateInput::new(false, app, false));
+                util.update_with_frame(app.elapsed_frames());
+                util.world(src, &timing, &ctx, Assets::empty_ref())
+                    .unwrap()
+                    .values()
+            };
+
+        let path = temp_file("record");
+        let mut recorder = InputRecorder::new(&path).unwrap();
+        let mut live = LivecodeSrc::new(vec![Box::new(Knobs::default())]);
+        let mut recorded = vec![];
+        for frame in 0..6 {
+            let app = MurreletAppInput::default_with_frames(frame);
+            recorded.push(world_values(&mut util, &mut live, &app));
+            recorder.record(&app, live.to_world_vals()).unwrap();
+        }
+        recorder.flush().unwrap();
+
+        let replay = InputReplay::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.last_frame(), Some(5));
+
+        let mut replayed = LivecodeSrc::new(vec![Box::new(replay.src())]);
+        for (frame, expected) in recorded.iter().enumerate() {
+            let app = replay.get(frame as u64).unwrap().app().clone();
+            assert_eq!(&world_values(&mut util, &mut replayed, &app), expected);
+        }
+    }
+
+    #[test]
+    fn get_holds_the_last_frame() {
+        let frame = |f| RecordedFrame::new(MurreletAppInput::default_with_frames(f), vec![]);
+        let yaml = [frame(5), frame(0), frame(2)]
+            .iter()
+            .map(|f| format!("---\n{}", serde_yaml::to_string(f).unwrap()))
+            .collect::<String>();
+        let replay = InputReplay::parse(&yaml).unwrap();
+
+        let got = |f| replay.get(f).map(|x| x.frame());
+        assert_eq!(got(0), Some(0));
+        assert_eq!(got(1), Some(0)); // in a gap, the one before
+        assert_eq!(got(4), Some(2));
+        assert_eq!(got(5), Some(5));
+        assert_eq!(got(60), Some(5)); // past the end
+        assert!(!replay.is_done(5));
+        assert!(replay.is_done(6));
+
+        let empty = InputReplay::parse("").unwrap();
+        assert!(empty.get(0).is_none());
+        assert!(empty.is_done(0));
+    }
+}
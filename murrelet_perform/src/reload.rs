@@ -1,10 +1,14 @@
 #![allow(dead_code)]
 use evalexpr::HashMapContext;
-use murrelet_common::AssetsRef;
+use murrelet_common::{AssetsRef, LivecodeValue};
 use murrelet_common::{LivecodeSrc, MurreletTime};
 use murrelet_livecode::expr::init_evalexpr_func_ctx;
+use murrelet_livecode::noisefuncs::NoiseFuncs;
+use murrelet_livecode::sourcemap::{YamlSourceMap, with_merged_yaml_source, with_yaml_source};
 use murrelet_livecode::state::*;
+use murrelet_livecode::stateful::StatefulFuncs;
 use murrelet_livecode::types::{AdditionalContextNode, LivecodeError, LivecodeResult};
+use murrelet_livecode::userfuncs::UserFunctions;
 
 // todo, maybe only includde this if not wasm?
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -27,23 +31,54 @@ pub trait LiveCoderLoader: Sized {
     // usually just serde_yaml::from_str(&str)
     fn parse(text: &str) -> LivecodeResult<Self>;
 
+    // parse, but expression errors will point back to the file/line in `source_map`
+    fn parse_with_source(text: &str, source_map: YamlSourceMap) -> LivecodeResult<Self> {
+        with_yaml_source(text, source_map, || Self::parse(text))
+    }
+
+    // parse, for `text` that was merged from `sources` (each file's
+    // preprocessed text and source map, see read_layered_yaml_with_sources)
+    fn parse_merged(text: &str, sources: &[(String, YamlSourceMap)]) -> LivecodeResult<Self> {
+        with_merged_yaml_source(text, sources, || Self::parse(text))
+    }
+
     fn fs_parse<P: AsRef<std::path::Path>>(
         text: &str,
         includes_dir: P,
     ) -> Result<Self, LivecodeError> {
-        let preprocessed = crate::load::preprocess_yaml(text, includes_dir);
-        //serde_yaml::from_str(&stripped_json)
-        Self::parse(&preprocessed)
+        Self::fs_parse_from(text, "config", includes_dir)
+    }
+
+    // filename is just used for error messages
+    fn fs_parse_from<P: AsRef<std::path::Path>>(
+        text: &str,
+        filename: &str,
+        includes_dir: P,
+    ) -> Result<Self, LivecodeError> {
+        let (preprocessed, source_map) =
+            crate::load::preprocess_yaml_with_source_map(text, filename, includes_dir)?;
+        Self::parse_with_source(&preprocessed, source_map)
     }
 
     fn fs_parse_data<P: AsRef<Path>, P2: AsRef<Path>>(
         filename: P,
         includes_dir: P2,
     ) -> Result<Self, LivecodeError> {
-        let mut file = fs::File::open(filename).unwrap();
+        let mut file = fs::File::open(&filename).unwrap();
         let mut data = String::new();
         std::io::Read::read_to_string(&mut file, &mut data).unwrap();
-        Self::fs_parse(&data, includes_dir)
+        Self::fs_parse_from(&data, &filename.as_ref().to_string_lossy(), includes_dir)
+    }
+
+    // each config is merged on top of the ones before it. errors still point
+    // at the file the expression came from
+    fn fs_parse_layered<P: AsRef<Path>, P2: AsRef<Path>>(
+        filenames: &[P],
+        includes_dir: P2,
+    ) -> LivecodeResult<Self> {
+        let (merged, sources) =
+            crate::load::read_layered_yaml_with_sources(filenames, includes_dir)?;
+        Self::parse_merged(&merged, &sources)
     }
 
     fn _fs_load() -> Result<Self, LivecodeError> {
@@ -82,11 +117,14 @@ pub trait LiveCoderLoader: Sized {
     }
 
     fn latest_template_update_time() -> LivecodeResult<MurreletTime> {
-        let dir = Self::fs_template_foldername();
+        let dirs = crate::load::template_dirs(Self::fs_template_foldername());
 
         let mut latest_time = MurreletTime::epoch();
-        for entry in
-            fs::read_dir(dir).map_err(|e| LivecodeError::Io("template error".to_string(), e))?
+        let entries = dirs.iter().map(fs::read_dir).collect::<Result<Vec<_>, _>>();
+        for entry in entries
+            .map_err(|e| LivecodeError::Io("template error".to_string(), e))?
+            .into_iter()
+            .flatten()
         {
             let entry = entry.map_err(|e| LivecodeError::Io("template error".to_string(), e))?;
             let metadata = entry
@@ -115,7 +153,8 @@ pub trait LiveCoderLoader: Sized {
     fn cb_reload_and_update_info(util: &mut LiveCodeUtil, text: &str) -> Result<Self, String> {
         util.reset_info();
 
-        match Self::parse(text) {
+        let source_map = YamlSourceMap::new_identity("config", text);
+        match Self::parse_with_source(text, source_map) {
             Ok(x) => {
                 util.update_info_reloaded();
                 Ok(x)
@@ -130,42 +169,48 @@ pub trait LiveCoderLoader: Sized {
     // filesystem one, hmm, should tidy up
     // result is if things go wrong, option is if it's just not time
     fn fs_load_if_needed_and_update_info(util: &mut LiveCodeUtil) -> LivecodeResult<Option<Self>> {
+        Self::fs_load_if_needed_and_update_info_with(util, &[Self::fs_config_filename()], || {
+            Self::_fs_load()
+        })
+    }
+
+    // same, but watches `config_filenames` and loads with `load`, for when the
+    // config isn't just args[1] (like a scene in a setlist, or overlays)
+    fn fs_load_if_needed_and_update_info_with<P: AsRef<Path>, F>(
+        util: &mut LiveCodeUtil,
+        config_filenames: &[P],
+        load: F,
+    ) -> LivecodeResult<Option<Self>>
+    where
+        F: FnOnce() -> LivecodeResult<Self>,
+    {
         if util.should_check_config() {
             util.reset_info();
 
-            let filename = fs::metadata(Self::fs_config_filename()).map_err(|x| {
-                LivecodeError::Io(
-                    format!("no metadata for path {}", Self::fs_config_filename()),
-                    x,
-                )
-            })?;
-            let modified = filename
-                .modified()
-                .map_err(|err| LivecodeError::Io("error finding modified type".to_string(), err))?;
-
-            let current_modified = murrelet_time_from_system(modified);
+            // the most recent change to any of them
+            let mut current_modified = MurreletTime::epoch();
+            for config_filename in config_filenames {
+                let config_filename = config_filename.as_ref();
+                let filename = fs::metadata(config_filename).map_err(|x| {
+                    LivecodeError::Io(format!("no metadata for path {:?}", config_filename), x)
+                })?;
+                let modified = filename.modified().map_err(|err| {
+                    LivecodeError::Io("error finding modified type".to_string(), err)
+                })?;
+                current_modified = current_modified.max(murrelet_time_from_system(modified));
+            }
 
             let folder_modified = Self::latest_template_update_time()?;
             if current_modified > util.info.config_next_check
                 || folder_modified > util.info.config_next_check
             {
-                match Self::_fs_load() {
-                    Ok(x) => {
-                        if current_modified > util.info.config_next_check {
-                            println!("reloading {:?}", current_modified);
-                        }
-                        if folder_modified > util.info.config_next_check {
-                            println!("reloading because folder {:?}", folder_modified);
-                        }
-
-                        util.update_info_reloaded();
-                        Ok(Some(x))
-                    }
-                    Err(err) => {
-                        util.update_info_error();
-                        Err(err)
-                    }
+                if current_modified > util.info.config_next_check {
+                    println!("reloading {:?}", current_modified);
                 }
+                if folder_modified > util.info.config_next_check {
+                    println!("reloading because folder {:?}", folder_modified);
+                }
+                Self::fs_load_and_update_info_with(util, load).map(Some)
             } else {
                 Ok(None)
             }
@@ -173,20 +218,50 @@ pub trait LiveCoderLoader: Sized {
             Ok(None)
         }
     }
+
+    // loads right away, for when a file watcher already saw the change
+    fn fs_load_and_update_info_with<F>(util: &mut LiveCodeUtil, load: F) -> LivecodeResult<Self>
+    where
+        F: FnOnce() -> LivecodeResult<Self>,
+    {
+        util.reset_info();
+        match load() {
+            Ok(x) => {
+                util.update_info_reloaded();
+                Ok(x)
+            }
+            Err(err) => {
+                util.update_info_error();
+                Err(err)
+            }
+        }
+    }
 }
 
 pub struct LiveCodeUtil {
     info: LiveCodeConfigInfo,
     timing: LiveCodeTiming,
     global_funcs: HashMapContext,
+    stateful_funcs: StatefulFuncs, // lag, count, etc
+    noise_funcs: NoiseFuncs,       // simplex, fbm, etc, seeded by the app
+    compiled_exprs: bool,
 }
 
 impl LiveCodeUtil {
     pub fn new() -> LivecodeResult<LiveCodeUtil> {
+        let stateful_funcs = StatefulFuncs::new();
+        let noise_funcs = NoiseFuncs::new();
+        let mut global_funcs = init_evalexpr_func_ctx()?;
+        stateful_funcs.register(&mut global_funcs)?;
+        noise_funcs.register(&mut global_funcs)?;
+
         Ok(LiveCodeUtil {
             info: LiveCodeConfigInfo::new(),
             timing: LiveCodeTiming::new(),
-            global_funcs: init_evalexpr_func_ctx()?,
+            global_funcs,
+            stateful_funcs,
+            noise_funcs,
+            compiled_exprs: false,
         })
     }
 
@@ -194,10 +269,47 @@ impl LiveCodeUtil {
         self.info.updated()
     }
 
+    pub fn global_funcs(&self) -> &HashMapContext {
+        &self.global_funcs
+    }
+
+    // rebuilds the global functions with the ones from the config, later
+    // ones win if they have the same name
+    pub fn set_user_functions(&mut self, funcs: &[UserFunctions]) -> LivecodeResult<()> {
+        let mut global_funcs = init_evalexpr_func_ctx()?;
+        self.stateful_funcs.register(&mut global_funcs)?;
+        self.noise_funcs.register(&mut global_funcs)?;
+        for f in funcs {
+            f.register(&mut global_funcs)?;
+        }
+        self.global_funcs = global_funcs;
+        Ok(())
+    }
+
+    // worlds made after this will evaluate expressions with CompiledExpr
+    pub fn set_compiled_exprs(&mut self, enabled: bool) {
+        self.compiled_exprs = enabled;
+    }
+
     pub fn update_with_frame(&mut self, frame: u64) {
         self.timing.set_frame(frame);
     }
 
+    // moves lag/slew/etc along, dt is the seconds since the last frame
+    pub fn update_stateful_funcs(&self, frame: u64, dt: f32) {
+        self.stateful_funcs.set_frame(frame, dt);
+    }
+
+    // new config, so the old state won't be used again
+    pub fn reset_stateful_funcs(&self) {
+        self.stateful_funcs.reset();
+    }
+
+    // noise in expressions uses this seed
+    pub fn set_noise_seed(&self, seed: f32) {
+        self.noise_funcs.set_seed(seed);
+    }
+
     pub fn update_last_render_time(&mut self) {
         self.timing.set_last_render_time();
     }
@@ -245,7 +357,10 @@ impl LiveCodeUtil {
         &'a self,
         livecode_src: &'a LivecodeSrc,
     ) -> LivecodeResult<LivecodeWorldState> {
-        LivecodeWorldState::new_timeless(&self.global_funcs, livecode_src)
+        Ok(
+            LivecodeWorldState::new_timeless(&self.global_funcs, livecode_src)?
+                .with_compiled_exprs(self.compiled_exprs),
+        )
     }
 
     pub fn world<'a>(
@@ -255,12 +370,26 @@ impl LiveCodeUtil {
         node: &AdditionalContextNode,
         assets: AssetsRef,
     ) -> LivecodeResult<LivecodeWorldState> {
-        LivecodeWorldState::new(
+        self.world_with_vals(livecode_src, timing_conf, node, assets, vec![])
+    }
+
+    // more_vals are things computed outside, like envelopes
+    pub fn world_with_vals<'a>(
+        &'a self,
+        livecode_src: &'a LivecodeSrc,
+        timing_conf: &LivecodeTimingConfig,
+        node: &AdditionalContextNode,
+        assets: AssetsRef,
+        more_vals: Vec<(String, LivecodeValue)>,
+    ) -> LivecodeResult<LivecodeWorldState> {
+        Ok(LivecodeWorldState::new_with_vals(
             &self.global_funcs,
             livecode_src,
             self.time(timing_conf),
             node.clone(),
             assets,
-        )
+            more_vals,
+        )?
+        .with_compiled_exprs(self.compiled_exprs))
     }
 }
@@ -0,0 +1,651 @@
+// a small http server for changing the config from another device, like a
+// tablet on the same network. start it with the sketch's gui schema, and
+// call `update` once a frame.
+//
+//   GET  /config        the control yaml that's running
+//   PUT  /config        replace it, the body is the new yaml
+//   POST /edit          change a few fields, e.g. {"circles.size": 3}
+//   GET  /world         the world's variables, as json
+//   GET  /world/stream  the same, as server-sent events every frame
+//   GET  /schema        the sketch's MurreletGUISchema, as json
+//
+// the connections are handled on their own threads, and changes wait for the
+// next `update` to be applied.
+//
+// there's no login. it doesn't send CORS headers, and PUT/POST from a browser
+// page on another site are turned away (by the Origin header), so a web page
+// can't change the sketch. anything else that can reach the port can, so bind
+// it to 127.0.0.1 unless the network is trusted.
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use lerpable::Lerpable;
+use murrelet_common::LivecodeValue;
+use murrelet_gui::MurreletGUISchema;
+use murrelet_livecode::boop::Boopable;
+use murrelet_livecode::nestedit::NestEditable;
+use murrelet_livecode::state::LivecodeWorldState;
+use murrelet_livecode::transition::Transitionable;
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+
+use crate::perform::{ConfCommon, LiveCodeCommon, LiveCoder};
+
+// how long a request waits for the sketch to apply it
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+const STREAM_RATE: Duration = Duration::from_millis(33);
+// so a slow or stuck client doesn't hold a thread forever
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+// way bigger than any config, but stops someone asking us to allocate 10gb
+const MAX_BODY: usize = 4 * 1024 * 1024;
+// same idea for the request line and headers
+const MAX_LINE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+// each one is a thread, and /world/stream ones stay open
+const MAX_CONNECTIONS: usize = 32;
+
+#[derive(Debug, Clone)]
+pub enum ServerRequest {
+    SetConfig(String),
+    Edit(Vec<(String, serde_yaml::Value)>), // dotted path, new value
+}
+
+struct PendingRequest {
+    request: ServerRequest,
+    reply: Sender<Result<(), String>>,
+}
+
+// what the sketch last told us, for the GETs
+#[derive(Debug, Clone, Default)]
+struct Published {
+    yaml: String,
+    world: String, // json
+    frame: u64,
+}
+
+pub struct ControlServer {
+    addr: SocketAddr,
+    requests: Receiver<PendingRequest>,
+    published: Arc<Mutex<Published>>,
+    _listener: JoinHandle<()>, // keep it alive!
+}
+impl ControlServer {
+    pub fn start<A: ToSocketAddrs>(addr: A, schema: &MurreletGUISchema) -> LivecodeResult<Self> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|err| LivecodeError::Io("couldn't start the server".to_owned(), err))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|err| LivecodeError::Io("couldn't start the server".to_owned(), err))?;
+        let schema =
+            Arc::new(serde_json::to_string(schema).map_err(|err| {
+                LivecodeError::Raw(format!("couldn't write the schema: {}", err))
+            })?);
+
+        let (request_tx, request_rx) = mpsc::channel::<PendingRequest>();
+        let published = Arc::new(Mutex::new(Published::default()));
+
+        let thread_published = published.clone();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let Some(slot) = ConnectionSlot::take(&connections) else {
+                    respond(
+                        &stream,
+                        "503 Service Unavailable",
+                        "text/plain",
+                        "too many connections\n",
+                    )
+                    .ok();
+                    continue;
+                };
+                let request_tx = request_tx.clone();
+                let published = thread_published.clone();
+                let schema = schema.clone();
+                thread::spawn(move || {
+                    let _slot = slot;
+                    if let Err(e) = handle_connection(stream, &request_tx, &published, &schema) {
+                        println!("control server connection error: {}", e);
+                    }
+                });
+            }
+        });
+
+        println!("control server at http://{}", addr);
+
+        Ok(Self {
+            addr,
+            requests: request_rx,
+            published,
+            _listener: handle,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // call once a frame, applies the changes and shares the new state
+    pub fn update<ConfType, ControlConfType>(
+        &self,
+        livecoder: &mut LiveCoder<ConfType, ControlConfType>,
+    ) where
+        ConfType: ConfCommon + Send + Sync + Lerpable + Boopable + Transitionable + NestEditable,
+        ControlConfType: LiveCodeCommon<ConfType>,
+    {
+        self.handle_requests(|request| match request {
+            ServerRequest::SetConfig(text) => livecoder.update_config_to(&text),
+            ServerRequest::Edit(edits) => {
+                // fields can be missing from the yaml if they have defaults, so
+                // check with the config before adding them
+                let is_field = |path: &str| livecoder.config().nest_getter(path).is_ok();
+                let yaml = apply_edits(
+                    livecoder.current_yaml().unwrap_or_default(),
+                    &edits,
+                    is_field,
+                )?;
+                livecoder.update_config_to(&yaml)
+            }
+        });
+        self.publish(
+            livecoder.current_yaml().unwrap_or_default(),
+            livecoder.world(),
+        );
+    }
+
+    // runs `f` on each waiting request, and sends back what it says
+    pub fn handle_requests<F>(&self, mut f: F)
+    where
+        F: FnMut(ServerRequest) -> Result<(), String>,
+    {
+        while let Ok(pending) = self.requests.try_recv() {
+            // they might have given up waiting, that's okay
+            pending.reply.send(f(pending.request)).ok();
+        }
+    }
+
+    pub fn publish(&self, yaml: &str, world: &LivecodeWorldState) {
+        let world_json = world_to_json(world);
+        let mut published = self.published.lock().unwrap();
+        published.yaml = yaml.to_owned();
+        published.world = world_json;
+        published.frame = world.actual_frame_u64();
+    }
+}
+
+fn world_to_json(world: &LivecodeWorldState) -> String {
+    let vals = world
+        .values()
+        .into_iter()
+        .filter_map(|(name, v)| {
+            let v = match v {
+                LivecodeValue::Float(f) => serde_json::Number::from_f64(f)?.into(),
+                LivecodeValue::Int(i) => i.into(),
+                LivecodeValue::Bool(b) => b.into(),
+            };
+            Some((name, v))
+        })
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::Value::Object(vals).to_string()
+}
+
+// sets each dotted path in the yaml, like NestEdit does for the config. keys
+// that aren't in the yaml yet are only added if `is_field` knows the path,
+// so a typo is an error instead of a new field that does nothing
+pub fn apply_edits<F>(
+    yaml: &str,
+    edits: &[(String, serde_yaml::Value)],
+    is_field: F,
+) -> Result<String, String>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut conf: serde_yaml::Value = serde_yaml::from_str(yaml).map_err(|err| err.to_string())?;
+    for (path, value) in edits {
+        if !has_path(&conf, path) && !is_field(path) {
+            return Err(format!("{} isn't in the config", path));
+        }
+        let mut curr = &mut conf;
+        for key in path.split('.') {
+            curr = match curr {
+                serde_yaml::Value::Sequence(s) => key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| s.get_mut(i))
+                    .ok_or_else(|| format!("{} isn't in the list at {}", key, path))?,
+                serde_yaml::Value::Mapping(m) => m
+                    .entry(serde_yaml::Value::String(key.to_owned()))
+                    .or_insert(serde_yaml::Value::Mapping(Default::default())),
+                _ => return Err(format!("can't set {} inside a value at {}", key, path)),
+            };
+        }
+        *curr = value.clone();
+    }
+    serde_yaml::to_string(&conf).map_err(|err| err.to_string())
+}
+
+fn has_path(conf: &serde_yaml::Value, path: &str) -> bool {
+    let mut curr = conf;
+    for key in path.split('.') {
+        let next = match curr {
+            serde_yaml::Value::Sequence(s) => key.parse::<usize>().ok().and_then(|i| s.get(i)),
+            serde_yaml::Value::Mapping(m) => m.get(key),
+            _ => None,
+        };
+        match next {
+            Some(next) => curr = next,
+            None => return false,
+        }
+    }
+    true
+}
+
+// one of the MAX_CONNECTIONS, given back when the connection's done
+struct ConnectionSlot(Arc<AtomicUsize>);
+impl ConnectionSlot {
+    fn take(connections: &Arc<AtomicUsize>) -> Option<Self> {
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Self(connections.clone()))
+    }
+}
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    body: String,
+    too_large: bool,        // the body was over MAX_BODY, so we didn't read it
+    origin: Option<String>, // browsers send these, curl and friends usually don't
+    host: Option<String>,
+}
+impl HttpRequest {
+    // a browser page from somewhere else, e.g. a site trying to change the
+    // sketch. the page the server's on would have an origin of http://<host>
+    fn is_cross_origin(&self) -> bool {
+        match (&self.origin, &self.host) {
+            (None, _) => false,
+            (Some(origin), Some(host)) => origin.strip_prefix("http://") != Some(host.as_str()),
+            (Some(_), None) => true,
+        }
+    }
+}
+
+fn too_large(what: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("{} too large", what),
+    )
+}
+
+// read_line, but it gives up after MAX_LINE instead of reading forever
+fn read_short_line<R: BufRead>(reader: &mut R, line: &mut String) -> std::io::Result<usize> {
+    let read = reader.by_ref().take(MAX_LINE).read_line(line)?;
+    if read as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(too_large("line"));
+    }
+    Ok(read)
+}
+
+fn read_request(stream: &TcpStream) -> std::io::Result<HttpRequest> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    read_short_line(&mut reader, &mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default().to_owned();
+
+    let mut content_length = 0;
+    let (mut origin, mut host) = (None, None);
+    for header_count in 0.. {
+        let mut header = String::new();
+        if read_short_line(&mut reader, &mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if header_count == MAX_HEADERS {
+            return Err(too_large("headers"));
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().unwrap_or(0);
+        } else if name.eq_ignore_ascii_case("origin") {
+            origin = Some(value.to_owned());
+        } else if name.eq_ignore_ascii_case("host") {
+            host = Some(value.to_owned());
+        }
+    }
+
+    let too_large = content_length > MAX_BODY;
+    let mut body = vec![];
+    if !too_large {
+        body.resize(content_length, 0);
+        reader.read_exact(&mut body)?;
+    }
+
+    Ok(HttpRequest {
+        method,
+        path,
+        body: String::from_utf8_lossy(&body).into_owned(),
+        too_large,
+        origin,
+        host,
+    })
+}
+
+fn respond(
+    mut stream: &TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+// hands the request to the sketch, and waits for it to be applied
+fn send_request(request_tx: &Sender<PendingRequest>, request: ServerRequest) -> (String, String) {
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let sent = request_tx.send(PendingRequest {
+        request,
+        reply: reply_tx,
+    });
+    match sent.map(|_| reply_rx.recv_timeout(REPLY_TIMEOUT)) {
+        Ok(Ok(Ok(()))) => ("200 OK".to_owned(), "ok\n".to_owned()),
+        Ok(Ok(Err(e))) => ("400 Bad Request".to_owned(), format!("{}\n", e)),
+        _ => (
+            "503 Service Unavailable".to_owned(),
+            "the sketch didn't pick it up\n".to_owned(),
+        ),
+    }
+}
+
+fn parse_edits(body: &str) -> Result<Vec<(String, serde_yaml::Value)>, String> {
+    let edits: HashMap<String, serde_json::Value> =
+        serde_json::from_str(body).map_err(|err| err.to_string())?;
+    edits
+        .into_iter()
+        .map(|(path, v)| {
+            serde_yaml::to_value(v)
+                .map(|v| (path, v))
+                .map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    request_tx: &Sender<PendingRequest>,
+    published: &Mutex<Published>,
+    schema: &str,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let request = match read_request(&stream) {
+        Ok(request) => request,
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            respond(
+                &stream,
+                "400 Bad Request",
+                "text/plain",
+                &format!("{}\n", e),
+            )?;
+            // closing with their request still unread resets the connection,
+            // which can lose the response, so read a bit more of it first
+            stream.shutdown(Shutdown::Write)?;
+            std::io::copy(&mut (&stream).take(MAX_LINE), &mut std::io::sink()).ok();
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    if request.too_large {
+        return respond(
+            &stream,
+            "413 Payload Too Large",
+            "text/plain",
+            &format!("the body can be at most {} bytes\n", MAX_BODY),
+        );
+    }
+
+    let changes = matches!(request.method.as_str(), "PUT" | "POST");
+    if changes && request.is_cross_origin() {
+        return respond(
+            &stream,
+            "403 Forbidden",
+            "text/plain",
+            "changes from other sites aren't allowed\n",
+        );
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/config") => {
+            let yaml = published.lock().unwrap().yaml.clone();
+            respond(&stream, "200 OK", "text/yaml", &yaml)
+        }
+        ("PUT", "/config") => {
+            let (status, body) = send_request(request_tx, ServerRequest::SetConfig(request.body));
+            respond(&stream, &status, "text/plain", &body)
+        }
+        ("POST", "/edit") => {
+            let (status, body) = match parse_edits(&request.body) {
+                Ok(edits) => send_request(request_tx, ServerRequest::Edit(edits)),
+                Err(e) => ("400 Bad Request".to_owned(), format!("{}\n", e)),
+            };
+            respond(&stream, &status, "text/plain", &body)
+        }
+        ("GET", "/world") => {
+            let world = published.lock().unwrap().world.clone();
+            respond(&stream, "200 OK", "application/json", &world)
+        }
+        ("GET", "/world/stream") => stream_world(stream, published),
+        ("GET", "/schema") => respond(&stream, "200 OK", "application/json", schema),
+        _ => respond(&stream, "404 Not Found", "text/plain", "not found\n"),
+    }
+}
+
+// keeps sending the world each frame until they hang up
+fn stream_world(mut stream: TcpStream, published: &Mutex<Published>) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n"
+    )?;
+
+    let mut last_frame = None;
+    loop {
+        let (frame, world) = {
+            let published = published.lock().unwrap();
+            (published.frame, published.world.clone())
+        };
+        if last_frame != Some(frame) && !world.is_empty() {
+            write!(stream, "data: {}\n\n", world)?;
+            stream.flush()?;
+            last_frame = Some(frame);
+        }
+        thread::sleep(STREAM_RATE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use murrelet_livecode::state::LivecodeWorldStateInner;
+
+    fn http(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn server_answers_on_localhost() {
+        let server = ControlServer::start("127.0.0.1:0", &MurreletGUISchema::Skip).unwrap();
+        let addr = server.addr();
+        let world = LivecodeWorldState::new_legacy(LivecodeWorldStateInner::new_dummy()).unwrap();
+        server.publish("circles:\n  size: 1\n", &world);
+
+        let config = http(addr, "GET /config HTTP/1.1\r\n\r\n");
+        assert!(config.starts_with("HTTP/1.1 200 OK"));
+        assert!(config.ends_with("circles:\n  size: 1\n"));
+
+        let schema = http(addr, "GET /schema HTTP/1.1\r\n\r\n");
+        assert!(schema.ends_with("\"Skip\""));
+
+        let missing = http(addr, "GET /nope HTTP/1.1\r\n\r\n");
+        assert!(missing.starts_with("HTTP/1.1 404"));
+
+        // the edit waits until the sketch handles it
+        let body = r#"{"circles.size": 3}"#;
+        let edit = thread::spawn(move || {
+            http(
+                addr,
+                &format!(
+                    "POST /edit HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+            )
+        });
+
+        let mut edited = None;
+        while edited.is_none() {
+            server.handle_requests(|request| match request {
+                ServerRequest::Edit(edits) => {
+                    edited = Some(apply_edits("circles:\n  size: 1\n", &edits, |_| false)?);
+                    Ok(())
+                }
+                ServerRequest::SetConfig(_) => Err("expected an edit".to_owned()),
+            });
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(edited.unwrap(), "circles:\n  size: 3\n");
+        assert!(edit.join().unwrap().starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn apply_edits_sets_nested_paths() {
+        let yaml = "app:\n  bpm: 120\nshapes:\n- size: 1\n- size: 2\n";
+        let edits = vec![
+            ("app.bpm".to_owned(), serde_yaml::Value::from(90)),
+            ("shapes.1.size".to_owned(), serde_yaml::Value::from("t * 2")),
+            ("app.seed".to_owned(), serde_yaml::Value::from(4)),
+        ];
+        let is_field = |path: &str| path == "app.seed";
+        assert_eq!(
+            apply_edits(yaml, &edits, is_field).unwrap(),
+            "app:\n  bpm: 90\n  seed: 4\nshapes:\n- size: 1\n- size: t * 2\n"
+        );
+        assert!(apply_edits(yaml, &[("shapes.5".to_owned(), 1.into())], is_field).is_err());
+
+        // typos don't sneak in as new fields
+        let typo = apply_edits(yaml, &[("app.bmp".to_owned(), 90.into())], is_field);
+        assert_eq!(typo.unwrap_err(), "app.bmp isn't in the config");
+        assert!(apply_edits(yaml, &[("shape.0.size".to_owned(), 1.into())], is_field).is_err());
+    }
+
+    #[test]
+    fn server_turns_away_huge_bodies() {
+        let server = ControlServer::start("127.0.0.1:0", &MurreletGUISchema::Skip).unwrap();
+        let response = http(
+            server.addr(),
+            &format!(
+                "PUT /config HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                MAX_BODY + 1
+            ),
+        );
+        assert!(response.starts_with("HTTP/1.1 413"));
+    }
+
+    #[test]
+    fn server_turns_away_other_sites() {
+        let server = ControlServer::start("127.0.0.1:0", &MurreletGUISchema::Skip).unwrap();
+        let addr = server.addr();
+        let edit = move |headers: &str| {
+            http(
+                addr,
+                &format!(
+                    "POST /edit HTTP/1.1\r\n{}Content-Length: 2\r\n\r\n{{}}",
+                    headers
+                ),
+            )
+        };
+
+        let other_site = edit("Host: 127.0.0.1:8765\r\nOrigin: http://example.com\r\n");
+        assert!(other_site.starts_with("HTTP/1.1 403"), "{}", other_site);
+        assert!(!other_site.contains("Access-Control-Allow-Origin"));
+        assert!(edit("Origin: http://example.com\r\n").starts_with("HTTP/1.1 403"));
+
+        // and browsers can't ask first either
+        let preflight = http(addr, "OPTIONS /config HTTP/1.1\r\n\r\n");
+        assert!(preflight.starts_with("HTTP/1.1 404"), "{}", preflight);
+
+        // the page the server is on is fine, it waits for the sketch like before
+        let server_thread = thread::spawn(move || {
+            edit("Host: 127.0.0.1:8765\r\nOrigin: http://127.0.0.1:8765\r\n")
+        });
+        let mut handled = 0;
+        while handled == 0 {
+            server.handle_requests(|_| {
+                handled += 1;
+                Ok(())
+            });
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(server_thread.join().unwrap().starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn server_limits_requests_and_connections() {
+        let server = ControlServer::start("127.0.0.1:0", &MurreletGUISchema::Skip).unwrap();
+        let addr = server.addr();
+
+        let long_path = "a".repeat(MAX_LINE as usize);
+        let long = http(addr, &format!("GET /{} HTTP/1.1\r\n\r\n", long_path));
+        assert!(long.starts_with("HTTP/1.1 400"), "{}", long);
+
+        let headers = "X-Hi: 1\r\n".repeat(MAX_HEADERS + 1);
+        let many = http(addr, &format!("GET /config HTTP/1.1\r\n{}\r\n", headers));
+        assert!(many.starts_with("HTTP/1.1 400"), "{}", many);
+
+        // these sit there without sending anything
+        let idle = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(addr).unwrap())
+            .collect::<Vec<_>>();
+        // it answers right away, before they've sent anything
+        let busy = http(addr, "");
+        assert!(busy.starts_with("HTTP/1.1 503"), "{}", busy);
+
+        // hanging up gives the slots back
+        drop(idle);
+        let mut response = String::new();
+        for _ in 0..100 {
+            response = http(addr, "GET /config HTTP/1.1\r\n\r\n");
+            if response.starts_with("HTTP/1.1 200") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    }
+}
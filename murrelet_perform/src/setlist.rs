@@ -0,0 +1,303 @@
+// a list of scenes to move through during a set.
+//
+//   app:
+//     setlist:
+//       next: kNf       # queue the next scene
+//       prev: kPf       # queue the previous one
+//       crossfade: 4    # beats to lerp between scenes, 0 uses app.transition
+//       scenes:
+//         - scenes/birds.yaml          # another config, next to this one
+//         - { circles: { count: 20 } } # this config, with these changes
+//
+// the config with the setlist is the first scene, then the ones in `scenes`.
+// queued scenes switch in on the next bar.
+use std::path::{Path, PathBuf};
+
+use lerpable::{Lerpable, step};
+use murrelet_gui::CanMakeGUI;
+use murrelet_livecode::livecode::{
+    GetLivecodeIdentifiers, LivecodeExpr, LivecodeFunction, LivecodeVariable,
+};
+use murrelet_livecode::nestedit::{NestEditable, NestedMod};
+use murrelet_livecode::state::LivecodeWorldState;
+use murrelet_livecode::stateful::is_triggered;
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+use serde::{Deserialize, Serialize};
+
+use crate::load::{merge_yaml, read_yaml};
+use crate::reload::LiveCoderLoader;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum SetlistScene {
+    File(String),
+    // yaml to lay over the config with the setlist
+    Inline(#[cfg_attr(feature = "schemars", schemars(schema_with = "any_yaml"))] serde_yaml::Value),
+}
+
+#[cfg(feature = "schemars")]
+fn any_yaml(_: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+    schemars::schema::Schema::Bool(true)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Setlist {
+    #[serde(default)]
+    scenes: Vec<SetlistScene>,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    next: Option<LivecodeExpr>,
+    #[cfg_attr(feature = "schemars", schemars(with = "Option<String>"))]
+    prev: Option<LivecodeExpr>,
+    #[serde(default)]
+    crossfade: f32, // in beats
+}
+impl Setlist {
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    fn triggers(&self) -> impl Iterator<Item = &LivecodeExpr> {
+        self.next.iter().chain(self.prev.iter())
+    }
+}
+
+pub(crate) fn check_trigger(
+    e: &Option<LivecodeExpr>,
+    w: &LivecodeWorldState,
+) -> LivecodeResult<bool> {
+    match e {
+        Some(e) => e
+            .eval_with_context(&w.to_local())
+            .and_then(|v| is_triggered(&v))
+            .map_err(|err| e.to_err(err)),
+        None => Ok(false),
+    }
+}
+
+// where we are in the setlist. `root` is the config with the setlist, the
+// file scenes are relative to it
+#[derive(Debug, Clone)]
+pub struct SetlistState {
+    setlist: Setlist,
+    root: PathBuf,
+    pos: usize, // 0 is the root config, 1 is the first in `scenes`
+    queued: Option<usize>,
+    next_was_on: bool,
+    prev_was_on: bool,
+}
+impl SetlistState {
+    pub fn new(setlist: Setlist, root: PathBuf) -> Self {
+        Self {
+            setlist,
+            root,
+            pos: 0,
+            queued: None,
+            next_was_on: false,
+            prev_was_on: false,
+        }
+    }
+
+    // when the config reloads, keep our place. scene files don't have the
+    // setlist, so only the root and inline scenes update it
+    pub fn reloaded(&mut self, setlist: &Setlist) {
+        if !matches!(self.scene(), Some(SetlistScene::File(_))) {
+            self.pos = self.pos.min(setlist.scenes.len());
+            self.setlist = setlist.clone();
+        }
+    }
+
+    pub fn crossfade(&self) -> f32 {
+        self.setlist.crossfade
+    }
+
+    // queues a scene when next or prev turns on
+    pub fn check_triggers(&mut self, w: &LivecodeWorldState) -> LivecodeResult<()> {
+        let next = check_trigger(&self.setlist.next, w)?;
+        let prev = check_trigger(&self.setlist.prev, w)?;
+
+        let from = self.queued.unwrap_or(self.pos);
+        if next && !self.next_was_on {
+            self.queued = Some((from + 1).min(self.setlist.scenes.len()));
+        } else if prev && !self.prev_was_on {
+            self.queued = Some(from.saturating_sub(1));
+        }
+        self.next_was_on = next;
+        self.prev_was_on = prev;
+        Ok(())
+    }
+
+    // the queued scene, if it's different from this one
+    pub fn take_queued(&mut self) -> Option<usize> {
+        let queued = self.queued.take()?;
+        if queued == self.pos {
+            None
+        } else {
+            self.pos = queued;
+            Some(queued)
+        }
+    }
+
+    // the file to watch for changes. inline scenes are part of the root
+    pub fn watched_file(&self) -> PathBuf {
+        match self.scene() {
+            Some(SetlistScene::File(f)) => self.scene_path(f),
+            _ => self.root.clone(),
+        }
+    }
+
+    fn scene(&self) -> Option<&SetlistScene> {
+        self.pos
+            .checked_sub(1)
+            .and_then(|i| self.setlist.scenes.get(i))
+    }
+
+    fn scene_path(&self, f: &str) -> PathBuf {
+        self.root.parent().unwrap_or(Path::new(".")).join(f)
+    }
+
+    pub fn load<C: LiveCoderLoader, P: AsRef<Path>>(&self, includes_dir: P) -> LivecodeResult<C> {
+        match self.scene() {
+            None => C::fs_parse_data(&self.root, includes_dir),
+            Some(SetlistScene::File(f)) => C::fs_parse_data(self.scene_path(f), includes_dir),
+            Some(SetlistScene::Inline(_)) => C::parse(&self.yaml(includes_dir)?),
+        }
+    }
+
+    // the current scene's yaml, with the templates filled in
+    pub fn yaml<P: AsRef<Path>>(&self, includes_dir: P) -> LivecodeResult<String> {
+        match self.scene() {
+            None => read_yaml(&self.root, includes_dir),
+            Some(SetlistScene::File(f)) => read_yaml(self.scene_path(f), includes_dir),
+            Some(SetlistScene::Inline(overlay)) => {
+                let yaml_err = |err: serde_yaml::Error| {
+                    LivecodeError::Raw(format!("error applying inline scene: {}", err))
+                };
+                let mut conf: serde_yaml::Value =
+                    serde_yaml::from_str(&read_yaml(&self.root, includes_dir)?)
+                        .map_err(yaml_err)?;
+                merge_yaml(&mut conf, overlay);
+                serde_yaml::to_string(&conf).map_err(yaml_err)
+            }
+        }
+    }
+}
+
+impl GetLivecodeIdentifiers for Setlist {
+    fn variable_identifiers(&self) -> Vec<LivecodeVariable> {
+        self.triggers()
+            .flat_map(|e| {
+                e.iter_read_variable_identifiers()
+                    .map(LivecodeVariable::from_str)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn function_identifiers(&self) -> Vec<LivecodeFunction> {
+        self.triggers()
+            .flat_map(|e| {
+                e.iter_function_identifiers()
+                    .map(LivecodeFunction::from_str)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl CanMakeGUI for Setlist {
+    fn make_gui() -> murrelet_gui::MurreletGUISchema {
+        murrelet_gui::MurreletGUISchema::Val(murrelet_gui::ValueGUI::Defs)
+    }
+}
+
+impl NestEditable for Setlist {
+    fn nest_update(&self, _mods: NestedMod) -> Self {
+        self.clone()
+    }
+
+    fn nest_get(&self, _getter: &[&str]) -> LivecodeResult<String> {
+        Err(LivecodeError::NestGetExtra("Setlist".to_owned()))
+    }
+}
+
+impl Lerpable for Setlist {
+    fn lerpify<T: lerpable::IsLerpingMethod>(&self, other: &Self, pct: &T) -> Self {
+        step(self, other, pct)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use murrelet_common::LivecodeValue;
+    use murrelet_livecode::expr::ExprWorldContextValues;
+    use murrelet_livecode::state::LivecodeWorldStateInner;
+
+    fn setlist(yaml: &str) -> Setlist {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn world(next: bool, prev: bool) -> LivecodeWorldState {
+        let vals = ExprWorldContextValues::new(vec![
+            ("k".to_owned(), LivecodeValue::Bool(next)),
+            ("j".to_owned(), LivecodeValue::Bool(prev)),
+        ]);
+        LivecodeWorldState::new_legacy(LivecodeWorldStateInner::new_dummy())
+            .unwrap()
+            .clone_with_vals(vals, "")
+    }
+
+    #[test]
+    fn queues_scenes_when_triggers_turn_on() {
+        let list = setlist("scenes: [birds.yaml, { circles: { count: 2 } }]\nnext: k\nprev: j\n");
+        let mut state = SetlistState::new(list, PathBuf::from("sketch/config.yaml"));
+
+        // holding it down only counts once
+        state.check_triggers(&world(true, false)).unwrap();
+        state.check_triggers(&world(true, false)).unwrap();
+        assert_eq!(state.take_queued(), Some(1));
+        assert_eq!(state.take_queued(), None);
+        assert_eq!(state.watched_file(), PathBuf::from("sketch/birds.yaml"));
+
+        // queueing twice before the bar moves two, but not past the end
+        for _ in 0..3 {
+            state.check_triggers(&world(false, false)).unwrap();
+            state.check_triggers(&world(true, false)).unwrap();
+        }
+        assert_eq!(state.take_queued(), Some(2));
+        assert_eq!(state.watched_file(), PathBuf::from("sketch/config.yaml"));
+
+        // and back
+        state.check_triggers(&world(false, true)).unwrap();
+        assert_eq!(state.take_queued(), Some(1));
+
+        // going nowhere doesn't switch
+        state.check_triggers(&world(true, false)).unwrap();
+        state.check_triggers(&world(false, true)).unwrap();
+        assert_eq!(state.take_queued(), None);
+    }
+
+    #[test]
+    fn reloading_keeps_the_place() {
+        let list = setlist("scenes: [birds.yaml, { a: 1 }, { a: 2 }]\nnext: k\n");
+        let mut state = SetlistState::new(list, PathBuf::from("config.yaml"));
+        for _ in 0..3 {
+            state.check_triggers(&world(true, false)).unwrap();
+            state.check_triggers(&world(false, false)).unwrap();
+        }
+        assert_eq!(state.take_queued(), Some(3));
+
+        // the root lost a scene, so stay on the last one
+        state.reloaded(&setlist("scenes: [birds.yaml, { a: 1 }]\nnext: k\n"));
+        assert_eq!(state.pos, 2);
+        assert_eq!(state.setlist.scenes.len(), 2);
+
+        // scene files don't have the setlist, so they don't change it
+        state.pos = 1;
+        state.reloaded(&Setlist::default());
+        assert_eq!(state.pos, 1);
+        assert_eq!(state.setlist.scenes.len(), 2);
+    }
+}
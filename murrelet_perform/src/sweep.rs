@@ -0,0 +1,271 @@
+// renders a few versions of a config side by side, to compare seeds or
+// settings.
+//
+//   cargo run -- config.yaml templates/ --values 0..36
+//   cargo run -- config.yaml templates/ --sweep circles.size --values 0.5..2:8
+//
+// app.seed (the default) reloads the config with each seed, since noise
+// reads it while evaluating. other paths use NestEditable::nest_update on
+// the evaluated config, and it's an error if the path isn't one it can set.
+// murrelet_svg puts them into one svg.
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Parser;
+use lerpable::Lerpable;
+use murrelet_common::LivecodeSrc;
+use murrelet_livecode::boop::Boopable;
+use murrelet_livecode::nestedit::{NestEditable, NestedMod};
+use murrelet_livecode::transition::Transitionable;
+use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+
+use crate::asset_loader::AssetLoaders;
+use crate::cli::BaseConfigArgs;
+use crate::headless::{FrameRange, Headless, HeadlessArgs};
+use crate::perform::{ConfCommon, LiveCodeCommon, LiveCoder, check_nest_update};
+
+const SEED_PATH: &str = "app.seed";
+
+// `1,2,5` is those, `0..36` is 0 up to 35, and `0.5..2:8` is 8 evenly
+// spaced from 0.5 to 2
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepValues(Vec<String>);
+impl SweepValues {
+    pub fn values(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl FromStr for SweepValues {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |x: &str| {
+            x.trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid number: {}", x))
+        };
+
+        let values: Vec<String> = match s.split_once("..") {
+            None => s.split(',').map(|x| x.trim().to_owned()).collect(),
+            Some((start, rest)) => match rest.split_once(':') {
+                None => {
+                    // without a count it steps by 1, so it needs whole numbers
+                    let parse_int = |x: &str| {
+                        x.trim().parse::<i64>().map_err(|_| {
+                            format!("invalid whole number: {} (add a :count for decimals)", x)
+                        })
+                    };
+                    (parse_int(start)?..parse_int(rest)?)
+                        .map(|x| x.to_string())
+                        .collect()
+                }
+                Some((end, count)) => {
+                    let (start, end) = (parse(start)?, parse(end)?);
+                    let count = count
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid count: {}", count))?;
+                    (0..count)
+                        .map(|i| {
+                            let pct = if count > 1 {
+                                i as f32 / (count - 1) as f32
+                            } else {
+                                0.0
+                            };
+                            (start + (end - start) * pct).to_string()
+                        })
+                        .collect()
+                }
+            },
+        };
+        if values.is_empty() || values.iter().any(|x| x.is_empty()) {
+            return Err(format!("no values to sweep in {:?}", s));
+        }
+        Ok(SweepValues(values))
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct SweepArgs {
+    #[command(flatten)]
+    pub base: BaseConfigArgs,
+
+    #[arg(long, default_value = SEED_PATH, help = "app.seed, or a path like circles.size")]
+    pub sweep: String,
+    #[arg(long, help = "like 1,2,5 or 0..36 or 0.5..2:8")]
+    pub values: SweepValues,
+    #[arg(long, default_value_t = 0, help = "which frame to draw")]
+    pub frame: u64,
+    #[arg(long, help = "tiles per row, defaults to about square")]
+    pub columns: Option<usize>,
+}
+
+pub struct Sweep<ConfType, ControlConfType> {
+    args: SweepArgs,
+    save_path: PathBuf,
+    _conf: PhantomData<(ConfType, ControlConfType)>,
+}
+impl<ConfType, ControlConfType> Sweep<ConfType, ControlConfType>
+where
+    ConfType: ConfCommon + Send + Sync + Lerpable + Boopable + Transitionable + NestEditable,
+    ControlConfType: LiveCodeCommon<ConfType>,
+{
+    pub fn new(save_path: PathBuf) -> Self {
+        Self::new_with_args(SweepArgs::parse(), save_path)
+    }
+
+    pub fn new_with_args(args: SweepArgs, save_path: PathBuf) -> Self {
+        Self {
+            args,
+            save_path,
+            _conf: PhantomData,
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        let count = self.args.values.values().len();
+        self.args
+            .columns
+            .unwrap_or_else(|| (count as f32).sqrt().ceil() as usize)
+            .max(1)
+    }
+
+    fn headless_args(&self, seed: Option<f32>) -> HeadlessArgs {
+        HeadlessArgs {
+            base: self.args.base.clone(),
+            frames: FrameRange::new(self.args.frame..self.args.frame + 1),
+            seed,
+        }
+    }
+
+    // calls `tile` with the label and config for each value. the livecoder
+    // is the one that made the config, for things like the svg settings
+    pub fn run<F>(&self, load_funcs: &AssetLoaders, mut tile: F) -> LivecodeResult<()>
+    where
+        F: FnMut(&str, &ConfType, &LiveCoder<ConfType, ControlConfType>) -> LivecodeResult<()>,
+    {
+        let path = &self.args.sweep;
+        let values = self.args.values.values();
+
+        if path == SEED_PATH {
+            for value in values {
+                let seed = value.parse::<f32>().map_err(|_| {
+                    LivecodeError::Raw(format!("seed should be a number, not {}", value))
+                })?;
+                let label = format!("{} = {}", path, value);
+                let mut headless = Headless::new_with_args(
+                    self.headless_args(Some(seed)),
+                    self.save_path.clone(),
+                    LivecodeSrc::new(vec![]),
+                    load_funcs,
+                )?;
+                headless.run(|livecoder| tile(&label, livecoder.config(), livecoder))?;
+            }
+        } else {
+            let mut headless = Headless::<ConfType, ControlConfType>::new_with_args(
+                self.headless_args(None),
+                self.save_path.clone(),
+                LivecodeSrc::new(vec![]),
+                load_funcs,
+            )?;
+            headless.run(|livecoder| {
+                // check them all before drawing any, otherwise a typo draws
+                // the same thing in every tile
+                let mut confs = vec![];
+                for value in values {
+                    let mods: HashMap<String, String> =
+                        [(path.clone(), value.clone())].into_iter().collect();
+                    let conf = livecoder.config().nest_update(NestedMod::from_dict(&mods));
+                    check_nest_update(&conf, path, value, "--sweep")?;
+                    confs.push(conf);
+                }
+                for (value, conf) in values.iter().zip(&confs) {
+                    tile(&format!("{} = {}", path, value), conf, livecoder)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::perform::tests::{APP, Conf, ControlConf};
+
+    fn values(s: &str) -> Vec<String> {
+        s.parse::<SweepValues>().unwrap().values().to_vec()
+    }
+
+    #[test]
+    fn lists_and_ranges() {
+        assert_eq!(values("1, 2,5"), vec!["1", "2", "5"]);
+        assert_eq!(values("-2..2"), vec!["-2", "-1", "0", "1"]);
+        assert_eq!(values("0.5..2:4"), vec!["0.5", "1", "1.5", "2"]);
+        assert_eq!(values("3..9:1"), vec!["3"]);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        // these used to truncate to 0..2
+        assert!("0.5..2".parse::<SweepValues>().is_err());
+        assert!("0..2.9".parse::<SweepValues>().is_err());
+
+        assert!("".parse::<SweepValues>().is_err());
+        assert!("1,,2".parse::<SweepValues>().is_err());
+        assert!("2..2".parse::<SweepValues>().is_err());
+        assert!("0..1:0".parse::<SweepValues>().is_err());
+        assert!("0..1:x".parse::<SweepValues>().is_err());
+        assert!("a..1:3".parse::<SweepValues>().is_err());
+    }
+
+    #[test]
+    fn bad_paths_fail_before_drawing() {
+        let dir = std::env::temp_dir().join(format!("murrelet_sweep_{}", std::process::id()));
+        let templates = dir.join("templates");
+        fs::create_dir_all(&templates).unwrap();
+        let config = dir.join("config.yaml");
+        fs::write(&config, format!("{}size: 1.0\n", APP)).unwrap();
+
+        let sweep =
+            |path: &str| {
+                let args = SweepArgs::try_parse_from([
+                    "sketch".as_ref(),
+                    config.as_os_str(),
+                    templates.as_os_str(),
+                    "--sweep".as_ref(),
+                    path.as_ref(),
+                    "--values".as_ref(),
+                    "2,3".as_ref(),
+                ])
+                .unwrap();
+                let mut tiles = vec![];
+                let result = Sweep::<Conf, ControlConf>::new_with_args(args, dir.join("captures"))
+                    .run(&AssetLoaders::empty(), |label, conf, _| {
+                        tiles.push((label.to_owned(), conf.size));
+                        Ok(())
+                    });
+                (result, tiles)
+            };
+
+        let (result, tiles) = sweep("size");
+        assert!(result.is_ok());
+        assert_eq!(
+            tiles,
+            vec![("size = 2".to_owned(), 2.0), ("size = 3".to_owned(), 3.0)]
+        );
+
+        let (result, tiles) = sweep("sise");
+        assert!(result.is_err());
+        assert!(tiles.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,350 @@
+// watches the files a sketch is using, so changing one only reloads the
+// things that depend on it. it watches the folders instead of the files,
+// since a lot of editors save by replacing the file.
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use murrelet_livecode::types::LivecodeResult;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WatchedKind {
+    Config, // the config, overlays or setlist scene, and every template they use
+    Shader, // shader source files
+    Asset,  // vector and raster files from app.assets
+}
+
+// what changed since the last time we asked
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileChanges {
+    pub config: bool,
+    pub shaders: Vec<PathBuf>,
+    pub assets: Vec<PathBuf>,
+}
+impl FileChanges {
+    pub fn is_empty(&self) -> bool {
+        !self.config && self.shaders.is_empty() && self.assets.is_empty()
+    }
+
+    // for holding on to changes until they're used
+    pub fn merge(&mut self, other: FileChanges) {
+        self.config |= other.config;
+        for path in other.shaders {
+            if !self.shaders.contains(&path) {
+                self.shaders.push(path);
+            }
+        }
+        for path in other.assets {
+            if !self.assets.contains(&path) {
+                self.assets.push(path);
+            }
+        }
+    }
+}
+
+pub struct FileWatcher {
+    backend: backend::Backend,
+    files: HashMap<WatchedKind, HashSet<PathBuf>>,
+    folders: HashMap<WatchedKind, HashSet<PathBuf>>, // any file in these counts
+    dirs: HashSet<PathBuf>,                          // what the backend is watching
+}
+
+impl FileWatcher {
+    pub fn new() -> LivecodeResult<Self> {
+        Ok(Self {
+            backend: backend::Backend::new()?,
+            files: HashMap::new(),
+            folders: HashMap::new(),
+            dirs: HashSet::new(),
+        })
+    }
+
+    // replaces the files watched for `kind`
+    pub fn set_files<P: AsRef<Path>>(
+        &mut self,
+        kind: WatchedKind,
+        paths: &[P],
+    ) -> LivecodeResult<()> {
+        let files = paths.iter().map(|p| watch_key(p.as_ref())).collect();
+        self.files.insert(kind, files);
+        self.update_dirs()
+    }
+
+    // replaces the folders watched for `kind`, where a change to any file
+    // counts, even one that didn't exist before
+    pub fn set_folders<P: AsRef<Path>>(
+        &mut self,
+        kind: WatchedKind,
+        paths: &[P],
+    ) -> LivecodeResult<()> {
+        let folders = paths.iter().map(|p| folder_key(p.as_ref())).collect();
+        self.folders.insert(kind, folders);
+        self.update_dirs()
+    }
+
+    pub fn files(&self, kind: WatchedKind) -> Vec<PathBuf> {
+        let mut files = self
+            .files
+            .get(&kind)
+            .map(|f| f.iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        files.sort();
+        files
+    }
+
+    fn update_dirs(&mut self) -> LivecodeResult<()> {
+        let dirs = self
+            .files
+            .values()
+            .flatten()
+            .filter_map(|f| f.parent().map(Path::to_path_buf))
+            .chain(self.folders.values().flatten().cloned())
+            .collect::<HashSet<_>>();
+
+        for dir in self.dirs.difference(&dirs) {
+            self.backend.unwatch(dir);
+        }
+
+        // keep the ones that worked, even if one didn't
+        let mut result = Ok(());
+        let mut watched = self
+            .dirs
+            .intersection(&dirs)
+            .cloned()
+            .collect::<HashSet<_>>();
+        for dir in dirs.difference(&self.dirs) {
+            match self.backend.watch(dir) {
+                Ok(()) => {
+                    watched.insert(dir.clone());
+                }
+                Err(err) => result = Err(err),
+            }
+        }
+        self.dirs = watched;
+        result
+    }
+
+    // everything that changed since the last call
+    pub fn changes(&mut self) -> FileChanges {
+        let mut changes = FileChanges::default();
+        for path in self.backend.changed_paths() {
+            let path = watch_key(&path);
+            for kind in [WatchedKind::Config, WatchedKind::Shader, WatchedKind::Asset] {
+                if !self.is_watching(kind, &path) {
+                    continue;
+                }
+                let changed = match kind {
+                    WatchedKind::Config => {
+                        changes.config = true;
+                        continue;
+                    }
+                    WatchedKind::Shader => &mut changes.shaders,
+                    WatchedKind::Asset => &mut changes.assets,
+                };
+                if !changed.contains(&path) {
+                    changed.push(path.clone());
+                }
+            }
+        }
+        changes
+    }
+
+    fn is_watching(&self, kind: WatchedKind, path: &Path) -> bool {
+        let in_folder = |dir: &Path| self.folders.get(&kind).is_some_and(|f| f.contains(dir));
+        self.files.get(&kind).is_some_and(|f| f.contains(path))
+            || path.parent().is_some_and(in_folder)
+    }
+}
+
+// the same file gets the same key however it was written, even if it's
+// missing for a moment while it's being saved
+pub(crate) fn watch_key(path: &Path) -> PathBuf {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let parent = parent
+        .canonicalize()
+        .unwrap_or_else(|_| parent.to_path_buf());
+    match path.file_name() {
+        Some(name) => parent.join(name),
+        None => parent,
+    }
+}
+
+fn folder_key(dir: &Path) -> PathBuf {
+    dir.canonicalize().unwrap_or_else(|_| watch_key(dir))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{Receiver, channel};
+
+    use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+    use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+    pub(super) struct Backend {
+        watcher: RecommendedWatcher,
+        events: Receiver<notify::Result<Event>>,
+    }
+
+    impl Backend {
+        pub(super) fn new() -> LivecodeResult<Self> {
+            let (tx, events) = channel();
+            let watcher = notify::recommended_watcher(tx).map_err(|err| {
+                LivecodeError::Raw(format!("couldn't start watching files: {}", err))
+            })?;
+            Ok(Self { watcher, events })
+        }
+
+        pub(super) fn watch(&mut self, dir: &Path) -> LivecodeResult<()> {
+            self.watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(|err| LivecodeError::Raw(format!("couldn't watch {:?}: {}", dir, err)))
+        }
+
+        pub(super) fn unwatch(&mut self, dir: &Path) {
+            // it's fine if it's already gone
+            let _ = self.watcher.unwatch(dir);
+        }
+
+        pub(super) fn changed_paths(&mut self) -> Vec<PathBuf> {
+            let mut paths = vec![];
+            for event in self.events.try_iter() {
+                match event {
+                    Ok(event) => {
+                        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                            paths.extend(event.paths);
+                        }
+                    }
+                    Err(err) => eprintln!("Error watching files {}", err),
+                }
+            }
+            paths
+        }
+    }
+}
+
+// nothing to watch on the web, the config comes in through a callback
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use std::path::{Path, PathBuf};
+
+    use murrelet_livecode::types::{LivecodeError, LivecodeResult};
+
+    pub(super) struct Backend;
+
+    impl Backend {
+        pub(super) fn new() -> LivecodeResult<Self> {
+            Err(LivecodeError::Raw(
+                "can't watch files on the web".to_string(),
+            ))
+        }
+
+        pub(super) fn watch(&mut self, _dir: &Path) -> LivecodeResult<()> {
+            Ok(())
+        }
+
+        pub(super) fn unwatch(&mut self, _dir: &Path) {}
+
+        pub(super) fn changed_paths(&mut self) -> Vec<PathBuf> {
+            vec![]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    // changes come in on another thread, so give them a moment
+    fn wait_for_changes(watcher: &mut FileWatcher) -> FileChanges {
+        let start = Instant::now();
+        let mut changes = FileChanges::default();
+        while start.elapsed() < Duration::from_secs(1) {
+            changes.merge(watcher.changes());
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        changes
+    }
+
+    #[test]
+    fn changes_only_mark_what_uses_the_file() {
+        let dir = std::env::temp_dir().join(format!("murrelet_watch_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.yaml");
+        let template = dir.join("template.yaml");
+        let shader = dir.join("blur.wgsl");
+        let asset = dir.join("shape.svg");
+        let other = dir.join("notes.txt");
+        for f in [&config, &template, &shader, &asset, &other] {
+            fs::write(f, "").unwrap();
+        }
+
+        let mut watcher = FileWatcher::new().unwrap();
+        watcher
+            .set_files(WatchedKind::Config, &[&config, &template])
+            .unwrap();
+        watcher.set_files(WatchedKind::Shader, &[&shader]).unwrap();
+        watcher.set_files(WatchedKind::Asset, &[&asset]).unwrap();
+
+        fs::write(&other, "hi").unwrap();
+        assert!(wait_for_changes(&mut watcher).is_empty());
+
+        fs::write(&template, "a: 1").unwrap();
+        let changes = wait_for_changes(&mut watcher);
+        assert!(changes.config);
+        assert!(changes.shaders.is_empty() && changes.assets.is_empty());
+
+        fs::write(&asset, "<svg/>").unwrap();
+        let changes = wait_for_changes(&mut watcher);
+        assert!(!changes.config);
+        assert_eq!(changes.assets, vec![watch_key(&asset)]);
+
+        // replacing the file, like an editor saving
+        let tmp = dir.join("blur.wgsl.tmp");
+        fs::write(&tmp, "// new").unwrap();
+        fs::rename(&tmp, &shader).unwrap();
+        let changes = wait_for_changes(&mut watcher);
+        assert!(!changes.config);
+        assert_eq!(changes.shaders, vec![watch_key(&shader)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn folders_count_new_files() {
+        let dir =
+            std::env::temp_dir().join(format!("murrelet_watch_folders_{}", std::process::id()));
+        let templates = dir.join("templates");
+        fs::create_dir_all(&templates).unwrap();
+        let config = dir.join("config.yaml");
+        fs::write(&config, "").unwrap();
+
+        let mut watcher = FileWatcher::new().unwrap();
+        watcher.set_files(WatchedKind::Config, &[&config]).unwrap();
+
+        // a template that didn't exist yet isn't one of the files
+        fs::write(templates.join("grid.yaml"), "a: 1").unwrap();
+        assert!(wait_for_changes(&mut watcher).is_empty());
+
+        watcher
+            .set_folders(WatchedKind::Config, &[&templates])
+            .unwrap();
+        fs::write(templates.join("dots.yaml"), "a: 1").unwrap();
+        let changes = wait_for_changes(&mut watcher);
+        assert!(changes.config);
+        assert!(changes.shaders.is_empty() && changes.assets.is_empty());
+
+        // and it stops when they're cleared
+        watcher
+            .set_folders::<PathBuf>(WatchedKind::Config, &[])
+            .unwrap();
+        fs::write(templates.join("lines.yaml"), "a: 1").unwrap();
+        assert!(wait_for_changes(&mut watcher).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,11 +3,13 @@ use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
     fmt,
+    path::{Path, PathBuf},
     rc::Rc,
 };
 
 use glam::*;
 use itertools::Itertools;
+use lerpable::Lerpable;
 use murrelet_common::*;
 use murrelet_draw::{
     curve_drawer::CurveDrawer,
@@ -16,7 +18,18 @@ use murrelet_draw::{
     style::{MurreletCurve, MurreletPath, MurreletPathAnnotation, StyledPath, StyledPathSvgFill},
     svg::{SvgPathDef, SvgShape, TransformedSvgShape},
 };
-use murrelet_perform::perform::SvgDrawConfig;
+use murrelet_livecode::nestedit::NestEditable;
+use murrelet_livecode::{
+    boop::Boopable,
+    transition::Transitionable,
+    types::{LivecodeError, LivecodeResult},
+};
+use murrelet_perform::{
+    asset_loader::AssetLoaders,
+    headless::Headless,
+    perform::{ConfCommon, LiveCodeCommon, SvgDrawConfig},
+    sweep::Sweep,
+};
 use svg::{
     Document, Node,
     node::element::{Group, path::Data},
@@ -413,12 +426,34 @@ impl SvgDocCreator {
     }
 
     pub fn save_doc(&self, paths: &SvgPathCache) {
+        if let Some(path) = self.try_save_doc(paths).unwrap() {
+            println!("{:?}", path);
+        }
+    }
+
+    // like save_doc, but hands back the error. returns where it saved, if it did
+    pub fn try_save_doc(&self, paths: &SvgPathCache) -> LivecodeResult<Option<PathBuf>> {
         if let Some(svg_draw_config) = self.svg_draw_config.capture_path() {
-            let document = self.make_doc(paths);
             let path = svg_draw_config.with_extension("svg");
-            println!("{:?}", path);
-            std::fs::create_dir_all(path.parent().expect("?")).ok();
-            svg::save(path, &document).unwrap();
+            save_svg(&path, &self.make_doc(paths))?;
+            Ok(Some(path))
+        } else {
+            Ok(None)
+        }
+    }
+
+    // returns where it saved, if it did
+    pub fn save_contact_sheet(
+        &self,
+        tiles: &[(String, SvgPathCacheRef)],
+        columns: usize,
+    ) -> LivecodeResult<Option<PathBuf>> {
+        if let Some(svg_draw_config) = self.svg_draw_config.capture_path() {
+            let path = svg_draw_config.with_extension("svg");
+            save_svg(&path, &self.make_contact_sheet(tiles, columns))?;
+            Ok(Some(path))
+        } else {
+            Ok(None)
         }
     }
 
@@ -498,17 +533,21 @@ impl SvgDocCreator {
         (doc, defs.into_iter().join("\n"))
     }
 
-    // this one's meant for svgs for pen plotters, so it drops fill styles
-    fn make_doc(&self, paths: &SvgPathCache) -> Document {
-        let target_size = self.svg_draw_config.full_target_width(); // guides are at 10x 10x, gives 1cm margin
-
-        let (view_box_x, view_box_y) = if let Some(r) = self.svg_draw_config.resolution {
+    fn view_box(&self) -> (u32, u32) {
+        if let Some(r) = self.svg_draw_config.resolution {
             let [width, height] = r.as_dims();
             // (width * 2, height * 2) // i'm not sure why i had this?
             (width, height)
         } else {
             (800, 800)
-        };
+        }
+    }
+
+    // this one's meant for svgs for pen plotters, so it drops fill styles
+    fn make_doc(&self, paths: &SvgPathCache) -> Document {
+        let target_size = self.svg_draw_config.full_target_width(); // guides are at 10x 10x, gives 1cm margin
+
+        let (view_box_x, view_box_y) = self.view_box();
         let mut doc = Document::new()
             .set(
                 "xmlns:inkscape",
@@ -547,6 +586,65 @@ impl SvgDocCreator {
         doc
     }
 
+    // each tile is laid out like its own doc, in rows of `columns`, with the
+    // label underneath
+    fn make_contact_sheet(&self, tiles: &[(String, SvgPathCacheRef)], columns: usize) -> Document {
+        let target_size = self.svg_draw_config.full_target_width();
+        let (tile_w, tile_h) = self.view_box();
+        let label_h = tile_h / 10;
+
+        let columns = columns.max(1);
+        let rows = tiles.len().div_ceil(columns).max(1);
+        let (view_box_x, view_box_y) = (tile_w * columns as u32, (tile_h + label_h) * rows as u32);
+
+        let mut doc = Document::new()
+            .set(
+                "xmlns:inkscape",
+                "http://www.inkscape.org/namespaces/inkscape",
+            )
+            .set("viewBox", (0, 0, view_box_x, view_box_y))
+            .set("width", format!("{:?}mm", target_size * columns as f32))
+            .set(
+                "height",
+                format!("{:?}mm", target_size * view_box_y as f32 / tile_w as f32),
+            );
+
+        if let Some(bg_color) = self.svg_draw_config.bg_color() {
+            let bg_rect = svg::node::element::Rectangle::new()
+                .set("x", 0)
+                .set("y", 0)
+                .set("width", view_box_x)
+                .set("height", view_box_y)
+                .set("fill", bg_color.to_svg_rgb());
+            doc = doc.add(bg_rect);
+        }
+
+        for (i, (label, paths)) in tiles.iter().enumerate() {
+            let x = (i % columns) as u32 * tile_w;
+            let y = (i / columns) as u32 * (tile_h + label_h);
+
+            let (group, _) = self.make_html(&paths.0.borrow());
+            let tile = svg::node::element::Group::new()
+                .set(
+                    "transform",
+                    format!("translate({}, {})", x + tile_w / 2, y + tile_h / 2),
+                )
+                .add(group);
+
+            let text = svg::node::element::Text::new()
+                .set("x", x + tile_w / 2)
+                .set("y", y + tile_h + label_h * 2 / 3)
+                .set("text-anchor", "middle")
+                .set("font-family", "monospace")
+                .set("font-size", format!("{}px", label_h / 2))
+                .add(svg::node::Text::new(label.clone()));
+
+            doc = doc.add(tile).add(text);
+        }
+
+        doc
+    }
+
     pub fn create_guides(&self) -> Vec<Vec<Vec2>> {
         let size = 0.5;
         let multi = 8.0;
@@ -667,6 +765,10 @@ impl SvgPathCacheRef {
         self.0.borrow().save_doc();
     }
 
+    pub fn try_save_doc(&self) -> LivecodeResult<Option<PathBuf>> {
+        self.0.borrow().try_save_doc()
+    }
+
     pub fn clear(&self, layer: &str) {
         self.0.borrow_mut().clear(layer);
     }
@@ -764,6 +866,10 @@ impl SvgPathCache {
         self.config.save_doc(self);
     }
 
+    pub fn try_save_doc(&self) -> LivecodeResult<Option<PathBuf>> {
+        self.config.try_save_doc(self)
+    }
+
     // can add these to a document. I don't give the full svg so I can leave things
     // like <image> defs alone and just update the paths and patternTransforms.
     pub fn make_html(&self) -> (String, String) {
@@ -888,3 +994,60 @@ impl ToSvgData for Vec<Vec2> {
         cd.to_svg_data()
     }
 }
+
+// makes the folder if it needs to
+fn save_svg(path: &Path, document: &Document) -> LivecodeResult<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| LivecodeError::Io(format!("couldn't make {:?}", parent), err))?;
+    }
+    svg::save(path, document)
+        .map_err(|err| LivecodeError::Io(format!("couldn't save {:?}", path), err))
+}
+
+// renders the headless frames, drawing each into a fresh SvgPathCache and
+// saving it to the capture folder
+pub fn save_headless_svgs<ConfType, ControlConfType, F>(
+    headless: &mut Headless<ConfType, ControlConfType>,
+    mut draw: F,
+) -> LivecodeResult<()>
+where
+    ConfType: ConfCommon + Send + Sync + Lerpable + Boopable + Transitionable + NestEditable,
+    ControlConfType: LiveCodeCommon<ConfType>,
+    F: FnMut(&ConfType, &SvgPathCacheRef),
+{
+    headless.run(|livecoder| {
+        let paths = SvgPathCache::svg_draw(&livecoder.svg_save_path());
+        draw(livecoder.config(), &paths);
+        paths.try_save_doc()?;
+        livecoder.write_capture_bundle()
+    })
+}
+
+// renders each of the sweep's versions with `draw`, and saves them all in one
+// svg in the capture folder
+pub fn save_sweep_svg<ConfType, ControlConfType, F>(
+    sweep: &Sweep<ConfType, ControlConfType>,
+    load_funcs: &AssetLoaders,
+    mut draw: F,
+) -> LivecodeResult<()>
+where
+    ConfType: ConfCommon + Send + Sync + Lerpable + Boopable + Transitionable + NestEditable,
+    ControlConfType: LiveCodeCommon<ConfType>,
+    F: FnMut(&ConfType, &SvgPathCacheRef),
+{
+    let mut tiles = vec![];
+    let mut sheet_config = None;
+    sweep.run(load_funcs, |label, conf, livecoder| {
+        let paths = SvgPathCache::svg_draw(&livecoder.svg_save_path());
+        draw(conf, &paths);
+        tiles.push((label.to_owned(), paths));
+        sheet_config.get_or_insert_with(|| livecoder.svg_save_path_with_prefix("sweep"));
+        Ok(())
+    })?;
+
+    if let Some(sheet_config) = sheet_config {
+        SvgDocCreator::new(&sheet_config).save_contact_sheet(&tiles, sweep.columns())?;
+    }
+    Ok(())
+}